@@ -0,0 +1,417 @@
+use rustrait_core::checker::check_output_types;
+use rustrait_core::decoder::{decode_prost_plan, decode_prost_plan_with_registry};
+use rustrait_core::diagnostics::{Diagnostic, Severity};
+use rustrait_core::explain::{explain, ExplainOptions};
+use rustrait_core::extensions::arguments::Argument;
+use rustrait_core::extensions::registry::ExtensionRegistry;
+use rustrait_core::extensions::signature::Signature;
+use rustrait_core::format::{load_plan_file, write_plan, write_plan_file, PlanFormat};
+use rustrait_core::graph::{to_dot, to_mermaid};
+use rustrait_core::plans::Plan;
+use rustrait_core::validator::validate_extensions;
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::process::ExitCode;
+use substrait::proto;
+
+mod run;
+
+const USAGE: &str = "\
+usage: rustrait <command> [options]
+
+commands:
+  validate [--extensions PATH]... [--strict] [--json] PLAN
+      check the extension declarations, relations and function types of the plan
+  explain [--extensions PATH]... [--schemas] [--format tree|dot|mermaid] PLAN
+      print the plan as an operator tree or as a graph
+  convert [--to binary|json|text] PLAN OUTPUT
+      write the plan in another format, to OUTPUT or to standard output if OUTPUT is -
+  run [--extensions PATH]... [--table NAME=PATH]... [--output table|csv|json] PLAN
+      execute the plan with DataFusion, reading the tables it names from .parquet, .csv,
+      .json, .ndjson or .arrow files
+  extensions [--json] [PATH]...
+      list the functions of extension files or directories, or of the standard extensions
+
+Plans are read in binary, JSON or text format. Extension files given with --extensions are
+registered as /<file name> in addition to the standard extensions.
+
+exit status: 0 on success, 1 if the plan is invalid or fails to run, 2 on usage or input
+errors
+";
+
+// Failures that are not findings about the plan, reported on stderr with exit status 2
+type CliError = String;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.split_first() {
+        Some((command, args)) => match command.as_str() {
+            "validate" => validate(args),
+            "explain" => explain_plan(args),
+            "convert" => convert(args),
+            "run" => run::run(args),
+            "extensions" => list_extensions(args),
+            "help" | "--help" | "-h" => {
+                print!("{}", USAGE);
+                Ok(ExitCode::SUCCESS)
+            }
+            _ => Err(format!("unknown command {}", command)),
+        },
+        None => Err("no command given".to_string()),
+    };
+    match result {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("rustrait: {}\n\n{}", error, USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+// The arguments of a command. Flags take no value, options take one and may be repeated.
+struct Args {
+    positional: Vec<String>,
+    flags: Vec<&'static str>,
+    options: HashMap<&'static str, Vec<String>>,
+}
+
+impl Args {
+    fn parse(
+        args: &[String],
+        flags: &[&'static str],
+        options: &[&'static str],
+    ) -> Result<Args, CliError> {
+        let mut parsed = Args {
+            positional: vec![],
+            flags: vec![],
+            options: HashMap::new(),
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            // positional arguments include - for standard output
+            if !arg.starts_with("--") {
+                parsed.positional.push(arg.clone());
+                continue;
+            }
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            if let Some(flag) = flags.iter().find(|f| **f == name) {
+                if inline_value.is_some() {
+                    return Err(format!("{} takes no value", flag));
+                }
+                parsed.flags.push(flag);
+            } else if let Some(option) = options.iter().find(|o| **o == name) {
+                let value = match inline_value {
+                    Some(value) => value,
+                    None => match args.next() {
+                        Some(value) => value.clone(),
+                        None => return Err(format!("{} needs a value", option)),
+                    },
+                };
+                parsed.options.entry(option).or_default().push(value);
+            } else {
+                return Err(format!("unknown option {}", name));
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.contains(&name)
+    }
+
+    fn values(&self, name: &str) -> &[String] {
+        self.options
+            .get(name)
+            .map_or(&[], |values| values.as_slice())
+    }
+
+    // The last value given for the option
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values(name).last().map(|value| value.as_str())
+    }
+
+    fn single_path(&self) -> Result<&str, CliError> {
+        match self.positional.as_slice() {
+            [path] => Ok(path),
+            [] => Err("no plan file given".to_string()),
+            _ => Err(format!(
+                "expected one plan file, got {}",
+                self.positional.join(" ")
+            )),
+        }
+    }
+}
+
+fn validate(args: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(args, &["--strict", "--json"], &["--extensions"])?;
+    let path = args.single_path()?;
+    let loaded = match load_registry(args.values("--extensions")) {
+        Ok(registry) => load_plan_file(path)
+            .map(|proto_plan| (registry, proto_plan))
+            .map_err(|e| Diagnostic::error("plan", e.to_string())),
+        Err(error) => Err(Diagnostic::error("extensions", error)),
+    };
+    let (registry, proto_plan) = match loaded {
+        Ok(loaded) => loaded,
+        // JSON output stays a single document, with the load error as its diagnostic
+        Err(diagnostic) if args.flag("--json") => {
+            println!("{}", validation_json(path, false, &[diagnostic]));
+            return Ok(ExitCode::from(2));
+        }
+        Err(diagnostic) => return Err(diagnostic.message),
+    };
+
+    let diagnostics = validate_plan(&proto_plan, &registry, args.flag("--strict"));
+    let valid = !has_errors(&diagnostics);
+    if args.flag("--json") {
+        println!("{}", validation_json(path, valid, &diagnostics));
+    } else {
+        for diagnostic in &diagnostics {
+            println!("{}", diagnostic);
+        }
+        if valid {
+            println!("{} is valid", path);
+        }
+    }
+    Ok(if valid {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    })
+}
+
+fn validation_json(path: &str, valid: bool, diagnostics: &[Diagnostic]) -> serde_json::Value {
+    json!({
+        "plan": path,
+        "valid": valid,
+        "diagnostics": diagnostics.iter().map(Diagnostic::to_json).collect::<Vec<_>>(),
+    })
+}
+
+// Each check relies on the previous ones passing: the plan is only decoded once its extension
+// declarations are consistent and registered, and only type checked once it is decoded
+fn validate_plan(
+    proto_plan: &proto::Plan,
+    registry: &ExtensionRegistry,
+    strict: bool,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = match validate_extensions(proto_plan) {
+        Ok(diagnostics) => diagnostics,
+        Err(diagnostics) => return diagnostics,
+    };
+    for (i, extension_uri) in proto_plan.extension_uris.iter().enumerate() {
+        if !registry.contains(&extension_uri.uri) {
+            diagnostics.push(Diagnostic::error(
+                &format!("extension_uris[{}]", i),
+                format!("extension {} is not registered", extension_uri.uri),
+            ));
+        }
+    }
+    if has_errors(&diagnostics) {
+        return diagnostics;
+    }
+
+    let plan = match decode(proto_plan, registry) {
+        Ok(plan) => plan,
+        Err(found) => {
+            diagnostics.extend(found);
+            return diagnostics;
+        }
+    };
+    match check_output_types(&plan, registry, strict) {
+        Ok(found) | Err(found) => diagnostics.extend(found),
+    }
+    diagnostics
+}
+
+fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
+
+// Functions are resolved through the registry if it has every extension of the plan
+fn decode(proto_plan: &proto::Plan, registry: &ExtensionRegistry) -> Result<Plan, Vec<Diagnostic>> {
+    let registered = proto_plan
+        .extension_uris
+        .iter()
+        .all(|extension_uri| registry.contains(&extension_uri.uri));
+    if registered {
+        decode_prost_plan_with_registry(proto_plan, registry)
+    } else {
+        decode_prost_plan(proto_plan)
+    }
+}
+
+fn explain_plan(args: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(args, &["--schemas"], &["--extensions", "--format"])?;
+    let path = args.single_path()?;
+    let registry = load_registry(args.values("--extensions"))?;
+    let proto_plan = load_plan_file(path).map_err(|e| e.to_string())?;
+    let plan = match decode(&proto_plan, &registry) {
+        Ok(plan) => plan,
+        Err(diagnostics) => {
+            for diagnostic in &diagnostics {
+                println!("{}", diagnostic);
+            }
+            return Ok(ExitCode::from(1));
+        }
+    };
+    let output = match args.value("--format").unwrap_or("tree") {
+        "tree" => explain(
+            &plan,
+            ExplainOptions {
+                schemas: args.flag("--schemas"),
+            },
+        ),
+        "dot" => to_dot(&plan),
+        "mermaid" => to_mermaid(&plan),
+        format => return Err(format!("unknown explain format {}", format)),
+    };
+    print!("{}", output);
+    Ok(ExitCode::SUCCESS)
+}
+
+fn convert(args: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(args, &[], &["--to"])?;
+    let (input, output) = match args.positional.as_slice() {
+        [input, output] => (input, output),
+        _ => return Err("expected a plan file and an output file".to_string()),
+    };
+    let format = match args.value("--to") {
+        Some(name) => plan_format(name)?,
+        None if output == "-" => {
+            return Err("--to is needed to write to standard output".to_string())
+        }
+        None => PlanFormat::from_path(Path::new(output)).ok_or_else(|| {
+            format!(
+                "cannot tell the format of {} from its extension, give it with --to",
+                output
+            )
+        })?,
+    };
+    let proto_plan = load_plan_file(input).map_err(|e| e.to_string())?;
+    if output == "-" {
+        let bytes = write_plan(&proto_plan, format).map_err(|e| e.to_string())?;
+        std::io::stdout()
+            .write_all(&bytes)
+            .map_err(|e| format!("cannot write to standard output: {}", e))?;
+    } else {
+        write_plan_file(&proto_plan, output, format).map_err(|e| e.to_string())?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn plan_format(name: &str) -> Result<PlanFormat, CliError> {
+    match name {
+        "binary" => Ok(PlanFormat::Binary),
+        "json" => Ok(PlanFormat::Json),
+        "text" => Ok(PlanFormat::Text),
+        _ => Err(format!(
+            "unknown plan format {}, expected binary, json or text",
+            name
+        )),
+    }
+}
+
+fn list_extensions(args: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(args, &["--json"], &[])?;
+    let (registry, mut uris) = if args.positional.is_empty() {
+        let registry = ExtensionRegistry::standard();
+        let uris: Vec<String> = registry.uris().cloned().collect();
+        (registry, uris)
+    } else {
+        let mut registry = ExtensionRegistry::new();
+        let mut uris = vec![];
+        for path in &args.positional {
+            uris.extend(load_extensions(&mut registry, Path::new(path))?);
+        }
+        (registry, uris)
+    };
+    uris.sort();
+    uris.dedup();
+
+    let mut files = vec![];
+    for uri in &uris {
+        let extensions = registry.get(uri).expect("listed extensions are registered");
+        let mut functions = vec![];
+        for function in &extensions.scalar_functions {
+            let variants = function.variants.iter().map(|v| &v.arguments);
+            functions.push((
+                "scalar",
+                &function.name,
+                signatures(&function.name, variants),
+            ));
+        }
+        for function in &extensions.aggregate_functions {
+            let variants = function.variants.iter().map(|v| &v.arguments);
+            functions.push((
+                "aggregate",
+                &function.name,
+                signatures(&function.name, variants),
+            ));
+        }
+        for function in &extensions.window_functions {
+            let variants = function.variants.iter().map(|v| &v.arguments);
+            functions.push((
+                "window",
+                &function.name,
+                signatures(&function.name, variants),
+            ));
+        }
+        files.push((uri, functions));
+    }
+
+    if args.flag("--json") {
+        let files: Vec<_> = files
+            .iter()
+            .map(|(uri, functions)| {
+                let functions: Vec<_> = functions
+                    .iter()
+                    .map(|(kind, name, signatures)| {
+                        json!({"kind": kind, "name": name, "signatures": signatures})
+                    })
+                    .collect();
+                json!({"uri": uri, "functions": functions})
+            })
+            .collect();
+        println!("{}", serde_json::Value::Array(files));
+    } else {
+        for (uri, functions) in &files {
+            println!("{}", uri);
+            for (kind, _, signatures) in functions {
+                for signature in signatures {
+                    println!("  {:<9} {}", kind, signature);
+                }
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn signatures<'a>(name: &str, variants: impl Iterator<Item = &'a Vec<Argument>>) -> Vec<String> {
+    variants
+        .map(|arguments| Signature::from_arguments(name, arguments).encode())
+        .collect()
+}
+
+// The standard extensions, along with the given extension files and directories
+fn load_registry(paths: &[String]) -> Result<ExtensionRegistry, CliError> {
+    let mut registry = ExtensionRegistry::standard();
+    for path in paths {
+        load_extensions(&mut registry, Path::new(path))?;
+    }
+    Ok(registry)
+}
+
+fn load_extensions(registry: &mut ExtensionRegistry, path: &Path) -> Result<Vec<String>, CliError> {
+    let result = if path.is_dir() {
+        registry.load_directory(path)
+    } else {
+        registry.load_file(path).map(|uri| vec![uri])
+    };
+    result.map_err(|e| e.to_string())
+}
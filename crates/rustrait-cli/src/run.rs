@@ -0,0 +1,135 @@
+use crate::{decode, load_registry, Args, CliError};
+use datafusion::arrow::csv::WriterBuilder;
+use datafusion::arrow::json::ArrayWriter;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::arrow::util::pretty::pretty_format_batches;
+use datafusion::datasource::file_format::options::ArrowReadOptions;
+use datafusion::error::Result;
+use datafusion::execution::context::SessionContext;
+use datafusion::physical_plan::collect;
+use datafusion::prelude::{CsvReadOptions, NdJsonReadOptions, ParquetReadOptions};
+use rustrait_core::format::load_plan_file;
+use rustrait_core::plans::Plan;
+use rustrait_datafusion::physical::to_execution_plan;
+use std::io::Write;
+use std::path::Path;
+use std::process::ExitCode;
+
+// Executes the plan with DataFusion, reading the tables of the plan from the files bound to
+// their names with --table NAME=PATH
+pub fn run(args: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(args, &[], &["--extensions", "--table", "--output"])?;
+    let path = args.single_path()?;
+    let output = args.value("--output").unwrap_or("table");
+    if !matches!(output, "table" | "csv" | "json") {
+        return Err(format!(
+            "unknown output format {}, expected table, csv or json",
+            output
+        ));
+    }
+    let registry = load_registry(args.values("--extensions"))?;
+    let proto_plan = load_plan_file(path).map_err(|e| e.to_string())?;
+    let plan = match decode(&proto_plan, &registry) {
+        Ok(plan) => plan,
+        Err(diagnostics) => {
+            for diagnostic in &diagnostics {
+                eprintln!("{}", diagnostic);
+            }
+            return Ok(ExitCode::from(1));
+        }
+    };
+
+    let runtime =
+        tokio::runtime::Runtime::new().map_err(|e| format!("cannot start the runtime: {}", e))?;
+    runtime.block_on(async {
+        let ctx = SessionContext::new();
+        for table in args.values("--table") {
+            register_table(&ctx, table).await?;
+        }
+        let batches = match execute(&ctx, &plan).await {
+            Ok(batches) => batches,
+            Err(error) => {
+                eprintln!("cannot run {}: {}", path, error);
+                return Ok(ExitCode::from(1));
+            }
+        };
+        print_batches(&batches, output).map_err(|e| format!("cannot print the result: {}", e))?;
+        Ok::<_, CliError>(ExitCode::SUCCESS)
+    })
+}
+
+// The format of the file is told by its extension
+async fn register_table(ctx: &SessionContext, table: &str) -> Result<(), CliError> {
+    let Some((name, path)) = table.split_once('=') else {
+        return Err(format!("expected --table NAME=PATH, got {}", table));
+    };
+    if !Path::new(path).exists() {
+        return Err(format!(
+            "table {} refers to {}, which does not exist",
+            name, path
+        ));
+    }
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let result = match extension.as_str() {
+        "parquet" => {
+            ctx.register_parquet(name, path, ParquetReadOptions::default())
+                .await
+        }
+        "csv" => ctx.register_csv(name, path, CsvReadOptions::new()).await,
+        "json" => {
+            ctx.register_json(name, path, NdJsonReadOptions::default())
+                .await
+        }
+        "ndjson" => {
+            let options = NdJsonReadOptions::default().file_extension(".ndjson");
+            ctx.register_json(name, path, options).await
+        }
+        "arrow" => {
+            ctx.register_arrow(name, path, ArrowReadOptions::default())
+                .await
+        }
+        _ => {
+            return Err(format!(
+                "cannot tell the format of {} from its extension, expected parquet, csv, json, \
+                 ndjson or arrow",
+                path
+            ))
+        }
+    };
+    result.map_err(|e| format!("cannot register table {}: {}", name, e))
+}
+
+async fn execute(ctx: &SessionContext, plan: &Plan) -> Result<Vec<RecordBatch>> {
+    let execution_plan = to_execution_plan(ctx, plan).await?;
+    let schema = execution_plan.schema();
+    let batches = collect(execution_plan, ctx.task_ctx()).await?;
+    // an empty batch still carries the column names
+    if batches.is_empty() {
+        return Ok(vec![RecordBatch::new_empty(schema)]);
+    }
+    Ok(batches)
+}
+
+fn print_batches(batches: &[RecordBatch], output: &str) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    match output {
+        "csv" => {
+            let mut writer = WriterBuilder::new().with_header(true).build(&mut stdout);
+            for batch in batches {
+                writer.write(batch)?;
+            }
+        }
+        "json" => {
+            let mut writer = ArrayWriter::new(&mut stdout);
+            writer.write_batches(&batches.iter().collect::<Vec<_>>())?;
+            writer.finish()?;
+            writeln!(stdout)?;
+        }
+        _ => writeln!(stdout, "{}", pretty_format_batches(batches)?)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,272 @@
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn rustrait(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rustrait"))
+        .args(args)
+        .output()
+        .expect("rustrait runs")
+}
+
+fn status(output: &Output) -> i32 {
+    output.status.code().expect("rustrait exits with a status")
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).expect("output is UTF-8")
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).expect("errors are UTF-8")
+}
+
+fn stdout_json(output: &Output) -> Value {
+    serde_json::from_slice(&output.stdout).expect("output is JSON")
+}
+
+// A directory of its own for each test, as tests run concurrently
+fn temp_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rustrait-cli-{}-{}", test, std::process::id()));
+    fs::create_dir_all(&dir).expect("temporary directory is created");
+    dir
+}
+
+fn simple_select() -> String {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../rustrait-core/simple-select.substrait")
+        .display()
+        .to_string()
+}
+
+// The simple select, calling a function it does not declare
+fn invalid_plan(dir: &Path) -> String {
+    let plan = fs::read_to_string(simple_select()).expect("plan is readable");
+    let plan = plan.replace("\"functionReference\": 0", "\"functionReference\": 9");
+    let path = dir.join("invalid.json");
+    fs::write(&path, plan).expect("plan is written");
+    path.display().to_string()
+}
+
+#[test]
+fn validate_exits_with_the_outcome() {
+    let dir = temp_dir("validate");
+    let valid = rustrait(&["validate", &simple_select()]);
+    assert_eq!(status(&valid), 0, "{}", stdout(&valid));
+    assert!(stdout(&valid).ends_with("is valid\n"));
+
+    let invalid = rustrait(&["validate", &invalid_plan(&dir)]);
+    assert_eq!(status(&invalid), 1);
+    assert!(stdout(&invalid).contains("error at "));
+
+    let missing = rustrait(&["validate", "missing.json"]);
+    assert_eq!(status(&missing), 2);
+    assert!(stderr(&missing).contains("usage: rustrait"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn validate_writes_json() {
+    let dir = temp_dir("validate-json");
+    let valid = rustrait(&["validate", "--json", &simple_select()]);
+    assert_eq!(status(&valid), 0);
+    let output = stdout_json(&valid);
+    assert_eq!(output["plan"], simple_select());
+    assert_eq!(output["valid"], true);
+    assert!(output["diagnostics"].is_array());
+
+    let invalid = rustrait(&["validate", "--json", &invalid_plan(&dir)]);
+    assert_eq!(status(&invalid), 1);
+    let output = stdout_json(&invalid);
+    assert_eq!(output["valid"], false);
+    let diagnostics = output["diagnostics"]
+        .as_array()
+        .expect("diagnostics are listed");
+    let diagnostic = diagnostics
+        .iter()
+        .find(|diagnostic| diagnostic["severity"] == "error")
+        .expect("the undeclared function is reported");
+    assert!(diagnostic["path"].is_string());
+    assert!(diagnostic["message"].is_string());
+
+    // plans that cannot be loaded are reported in the same shape
+    let missing = rustrait(&["validate", "--json", "missing.json"]);
+    assert_eq!(status(&missing), 2);
+    assert_eq!(stderr(&missing), "");
+    let output = stdout_json(&missing);
+    assert_eq!(output["plan"], "missing.json");
+    assert_eq!(output["valid"], false);
+    assert_eq!(output["diagnostics"][0]["severity"], "error");
+    assert_eq!(output["diagnostics"][0]["path"], "plan");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn explain_prints_the_plan() {
+    let dir = temp_dir("explain");
+    let tree = rustrait(&["explain", &simple_select()]);
+    assert_eq!(status(&tree), 0);
+    assert!(stdout(&tree).contains("Read[table=FOO, columns=[BAR: i32?]]"));
+
+    let dot = rustrait(&["explain", "--format", "dot", &simple_select()]);
+    assert_eq!(status(&dot), 0);
+    assert!(stdout(&dot).starts_with("digraph"));
+
+    let invalid = rustrait(&["explain", &invalid_plan(&dir)]);
+    assert_eq!(status(&invalid), 1);
+    assert!(stdout(&invalid).contains("error at "));
+
+    let unknown = rustrait(&["explain", "--format", "svg", &simple_select()]);
+    assert_eq!(status(&unknown), 2);
+    assert!(stderr(&unknown).contains("unknown explain format svg"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn convert_writes_every_format() {
+    let dir = temp_dir("convert");
+    let binary = dir.join("plan.bin").display().to_string();
+    let converted = rustrait(&["convert", "--to", "binary", &simple_select(), &binary]);
+    assert_eq!(status(&converted), 0, "{}", stderr(&converted));
+
+    // the binary plan converts back to the same JSON
+    let json = rustrait(&["convert", "--to", "json", &binary, "-"]);
+    assert_eq!(status(&json), 0);
+    let original = rustrait(&["convert", "--to", "json", &simple_select(), "-"]);
+    assert_eq!(stdout_json(&json), stdout_json(&original));
+
+    let text = rustrait(&["convert", "--to", "text", &simple_select(), "-"]);
+    assert_eq!(status(&text), 0);
+    assert!(!stdout(&text).is_empty());
+
+    let no_format = rustrait(&["convert", &simple_select(), "-"]);
+    assert_eq!(status(&no_format), 2);
+    assert!(stderr(&no_format).contains("--to is needed"));
+
+    let unknown = rustrait(&["convert", "--to", "yaml", &simple_select(), "-"]);
+    assert_eq!(status(&unknown), 2);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn extensions_lists_functions() {
+    let listed = rustrait(&["extensions"]);
+    assert_eq!(status(&listed), 0);
+    assert!(stdout(&listed).contains("add:i32_i32"));
+
+    let json = rustrait(&["extensions", "--json"]);
+    assert_eq!(status(&json), 0);
+    let files = stdout_json(&json);
+    let arithmetic = files
+        .as_array()
+        .expect("extension files are listed")
+        .iter()
+        .find(|file| {
+            file["uri"]
+                .as_str()
+                .is_some_and(|uri| uri.ends_with("functions_arithmetic.yaml"))
+        })
+        .expect("standard extensions are listed");
+    let add = arithmetic["functions"]
+        .as_array()
+        .expect("functions are listed")
+        .iter()
+        .find(|function| function["name"] == "add")
+        .expect("add is listed");
+    assert_eq!(add["kind"], "scalar");
+    assert!(add["signatures"]
+        .as_array()
+        .expect("signatures are listed")
+        .contains(&Value::from("add:i32_i32")));
+
+    let missing = rustrait(&["extensions", "missing.yaml"]);
+    assert_eq!(status(&missing), 2);
+}
+
+#[test]
+fn run_prints_every_output_format() {
+    let dir = temp_dir("run");
+    let csv = dir.join("foo.csv");
+    fs::write(&csv, "BAR\n1\n2\n").unwrap();
+    let table = format!("FOO={}", csv.display());
+    let run = |output: &str| {
+        rustrait(&[
+            "run",
+            "--table",
+            &table,
+            "--output",
+            output,
+            &simple_select(),
+        ])
+    };
+
+    let printed = run("table");
+    assert_eq!(status(&printed), 0, "{}", stderr(&printed));
+    let lines: Vec<String> = stdout(&printed)
+        .lines()
+        .map(|line| line.split_whitespace().collect::<String>())
+        .collect();
+    assert_eq!(
+        lines,
+        vec![
+            "+--------+",
+            "|EXPR$0|",
+            "+--------+",
+            "|2|",
+            "|3|",
+            "+--------+"
+        ]
+    );
+
+    let csv_output = run("csv");
+    assert_eq!(status(&csv_output), 0, "{}", stderr(&csv_output));
+    assert_eq!(stdout(&csv_output), "EXPR$0\n2\n3\n");
+
+    let json_output = run("json");
+    assert_eq!(status(&json_output), 0, "{}", stderr(&json_output));
+    assert_eq!(
+        stdout_json(&json_output),
+        serde_json::json!([{"EXPR$0": 2}, {"EXPR$0": 3}])
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn run_exits_with_the_outcome() {
+    let dir = temp_dir("run-errors");
+    let csv = dir.join("foo.csv");
+    fs::write(&csv, "BAR\n1\n").unwrap();
+    let table = format!("FOO={}", csv.display());
+
+    let malformed = rustrait(&["run", "--table", "FOO", &simple_select()]);
+    assert_eq!(status(&malformed), 2);
+    assert!(stderr(&malformed).contains("expected --table NAME=PATH, got FOO"));
+
+    let missing_table = rustrait(&["run", "--table", "FOO=missing.csv", &simple_select()]);
+    assert_eq!(status(&missing_table), 2);
+    assert!(stderr(&missing_table).contains("does not exist"));
+
+    let missing_plan = rustrait(&["run", "--table", &table, "missing.json"]);
+    assert_eq!(status(&missing_plan), 2);
+
+    let unknown = rustrait(&[
+        "run",
+        "--table",
+        &table,
+        "--output",
+        "xml",
+        &simple_select(),
+    ]);
+    assert_eq!(status(&unknown), 2);
+    assert!(stderr(&unknown).contains("unknown output format xml"));
+
+    // the plan reads a table that is not given
+    let unbound = rustrait(&["run", &simple_select()]);
+    assert_eq!(status(&unbound), 1);
+    assert!(stderr(&unbound).starts_with("cannot run "));
+
+    let invalid = rustrait(&["run", "--table", &table, &invalid_plan(&dir)]);
+    assert_eq!(status(&invalid), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,1204 @@
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::extensions::overload::{bind_arguments, derive_output_type, BoundArgument};
+use crate::extensions::registry::ExtensionRegistry;
+use crate::extensions::signature::VariantReference;
+use crate::plans::expressions::field_reference::FieldReference;
+use crate::plans::expressions::literal::Literal;
+use crate::plans::expressions::scalar_function::ScalarFunctionInvocation;
+use crate::plans::expressions::{Expression, FunctionArgument};
+use crate::plans::{
+    Aggregate, Emit, ExchangeKind, Join, JoinType, KeyedJoin, Plan, ReadType, Rel, Set,
+};
+use crate::types::Type;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+// The columns output by a relation. Columns computed by expressions have no name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Schema {
+    pub columns: Vec<Column>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Column {
+    pub name: Option<String>,
+    pub r#type: Type,
+}
+
+impl Schema {
+    pub fn types(&self) -> Vec<Type> {
+        self.columns.iter().map(|c| c.r#type.clone()).collect()
+    }
+
+    fn concat(&self, other: &Schema) -> Schema {
+        Schema {
+            columns: self.columns.iter().chain(&other.columns).cloned().collect(),
+        }
+    }
+
+    fn with_nullable(&self) -> Schema {
+        Schema {
+            columns: self
+                .columns
+                .iter()
+                .map(|c| Column {
+                    name: c.name.clone(),
+                    r#type: c.r#type.clone().with_nullable(true),
+                })
+                .collect(),
+        }
+    }
+}
+
+// Derives the output schema of the plan, validating every relation and expression on the way
+pub fn check_plan(plan: &Plan) -> Result<Schema, Vec<Diagnostic>> {
+    let mut checker = TypeChecker::new(None, Severity::Error, &plan.subtrees);
+    let schema = checker.check_subtrees_and_root(plan);
+    match schema {
+        Some(schema) if checker.diagnostics.is_empty() => Ok(schema),
+        _ => Err(checker.diagnostics),
+    }
+}
+
+// Derives the output schema of a relation outside of its plan, so it must not contain
+// references to subtrees
+pub fn output_schema(rel: &Rel) -> Result<Schema, Vec<Diagnostic>> {
+    let mut checker = TypeChecker::new(None, Severity::Error, &[]);
+    let schema = checker.check_rel(rel, "root");
+    match schema {
+        Some(schema) if checker.diagnostics.is_empty() => Ok(schema),
+        _ => Err(checker.diagnostics),
+    }
+}
+
+// Infers the type of an expression evaluated against rows of the input schema
+pub fn expression_type(expression: &Expression, input: &Schema) -> Result<Type, Vec<Diagnostic>> {
+    let mut checker = TypeChecker::new(None, Severity::Error, &[]);
+    let t = checker.check_expression(expression, input, "expression");
+    match t {
+        Some(t) if checker.diagnostics.is_empty() => Ok(t),
+        _ => Err(checker.diagnostics),
+    }
+}
+
+// Checks that the output type declared by every scalar function invocation matches the type
+// derived from the function definition and its argument types. Mismatches are reported as
+// warnings, or as errors that fail the check in strict mode.
+pub fn check_output_types(
+    plan: &Plan,
+    registry: &ExtensionRegistry,
+    strict: bool,
+) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+    let mut checker = TypeChecker::new(
+        Some(registry),
+        if strict {
+            Severity::Error
+        } else {
+            Severity::Warning
+        },
+        &plan.subtrees,
+    );
+    checker.check_subtrees_and_root(plan);
+    if checker
+        .diagnostics
+        .iter()
+        .any(|d| d.severity == Severity::Error)
+    {
+        return Err(checker.diagnostics);
+    }
+    Ok(checker.diagnostics)
+}
+
+// The output schemas of a relation and the relations below it, derived in a single pass.
+// Relations are identified by their address, so the schemas borrow the plan they describe.
+pub struct RelSchemas<'a> {
+    schemas: HashMap<*const Rel, Schema>,
+    plan: PhantomData<&'a Rel>,
+}
+
+impl RelSchemas<'_> {
+    // None if the schema of the relation cannot be determined
+    pub fn get(&self, rel: &Rel) -> Option<&Schema> {
+        self.schemas.get(&(rel as *const Rel))
+    }
+}
+
+// Schemas of all relations of the plan, including those of its subtrees
+pub fn plan_schemas(plan: &Plan) -> RelSchemas<'_> {
+    let mut checker = TypeChecker::new(None, Severity::Error, &plan.subtrees);
+    checker.check_subtrees_and_root(plan);
+    RelSchemas {
+        schemas: checker.schemas,
+        plan: PhantomData,
+    }
+}
+
+// Schemas of a relation outside of its plan, so references to subtrees have none
+pub fn rel_schemas(rel: &Rel) -> RelSchemas<'_> {
+    let mut checker = TypeChecker::new(None, Severity::Error, &[]);
+    checker.check_rel(rel, "root");
+    RelSchemas {
+        schemas: checker.schemas,
+        plan: PhantomData,
+    }
+}
+
+struct TypeChecker<'a> {
+    // output types of function invocations are only checked when a registry is given
+    registry: Option<&'a ExtensionRegistry>,
+    // severity of output type mismatches; structural problems are always errors
+    severity: Severity,
+    diagnostics: Vec<Diagnostic>,
+    subtrees: &'a [Rel],
+    subtree_schemas: HashMap<usize, Option<Schema>>,
+    // subtrees currently being checked, to detect cyclic references
+    checking: Vec<usize>,
+    // output schema of every relation checked so far, by address
+    schemas: HashMap<*const Rel, Schema>,
+}
+
+impl<'a> TypeChecker<'a> {
+    fn new(
+        registry: Option<&'a ExtensionRegistry>,
+        severity: Severity,
+        subtrees: &'a [Rel],
+    ) -> TypeChecker<'a> {
+        TypeChecker {
+            registry,
+            severity,
+            diagnostics: vec![],
+            subtrees,
+            subtree_schemas: HashMap::new(),
+            checking: vec![],
+            schemas: HashMap::new(),
+        }
+    }
+
+    // Checks every subtree once, including those no relation refers to, and then the root
+    fn check_subtrees_and_root(&mut self, plan: &Plan) -> Option<Schema> {
+        for i in 0..plan.subtrees.len() {
+            self.check_subtree(i, "subtrees");
+        }
+        self.check_rel(&plan.root, "root")
+    }
+
+    fn check_subtree(&mut self, index: usize, path: &str) -> Option<Schema> {
+        if self.checking.contains(&index) {
+            self.error(path, format!("subtree {} refers to itself", index));
+            return None;
+        }
+        if let Some(schema) = self.subtree_schemas.get(&index) {
+            return schema.clone();
+        }
+        let subtree = match self.subtrees.get(index) {
+            Some(subtree) => subtree,
+            None => {
+                self.error(
+                    path,
+                    format!(
+                        "reference to subtree {} but the plan has {} subtrees",
+                        index,
+                        self.subtrees.len()
+                    ),
+                );
+                return None;
+            }
+        };
+        self.checking.push(index);
+        let schema = self.check_rel(subtree, &format!("subtrees[{}]", index));
+        self.checking.pop();
+        self.subtree_schemas.insert(index, schema.clone());
+        schema
+    }
+
+    fn report(&mut self, path: &str, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity: self.severity,
+            path: path.to_string(),
+            message,
+        });
+    }
+
+    fn error(&mut self, path: &str, message: String) {
+        self.diagnostics.push(Diagnostic::error(path, message));
+    }
+
+    // Rows must have a literal of the column's type for every column of the base schema
+    fn check_virtual_table(&mut self, rows: &[Vec<Literal>], types: &[Type], path: &str) {
+        for (i, row) in rows.iter().enumerate() {
+            if row.len() != types.len() {
+                self.error(
+                    path,
+                    format!(
+                        "row {} has {} values but the base schema has {} columns",
+                        i,
+                        row.len(),
+                        types.len()
+                    ),
+                );
+                continue;
+            }
+            for (j, (literal, t)) in row.iter().zip(types).enumerate() {
+                let value_type = literal.output_type();
+                // non-nullable values may be stored in nullable columns
+                if value_type != *t && value_type.with_nullable(true) != *t {
+                    self.error(
+                        path,
+                        format!(
+                            "value {} in column {} of row {} does not have the column type {}",
+                            literal, j, i, t
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    // Returns the columns produced by the relation, or None if they cannot be determined
+    fn check_rel(&mut self, rel: &Rel, path: &str) -> Option<Schema> {
+        let path = format!("{}.{}", path, rel_kind(rel));
+        let direct = match rel {
+            Rel::Read(read) => {
+                match &read.read_type {
+                    ReadType::LocalFiles { items } if items.is_empty() => {
+                        self.error(&path, "local files read lists no files".to_string())
+                    }
+                    ReadType::VirtualTable { rows } => {
+                        self.check_virtual_table(rows, &read.base_schema.types, &path)
+                    }
+                    _ => {}
+                }
+                Some(Schema {
+                    columns: read
+                        .base_schema
+                        .column_names()
+                        .into_iter()
+                        .zip(&read.base_schema.types)
+                        .map(|(name, t)| Column {
+                            name: Some(name),
+                            r#type: t.clone(),
+                        })
+                        .collect(),
+                })
+            }
+            Rel::Filter(filter) => {
+                let input = self.check_rel(&filter.input, &format!("{}.input", path))?;
+                self.check_condition(&filter.condition, &input, &format!("{}.condition", path));
+                Some(input)
+            }
+            Rel::Fetch(fetch) => {
+                let input = self.check_rel(&fetch.input, &format!("{}.input", path))?;
+                if fetch.offset < 0 {
+                    self.error(
+                        &format!("{}.offset", path),
+                        format!("offset must not be negative but is {}", fetch.offset),
+                    );
+                }
+                Some(input)
+            }
+            Rel::Aggregate(aggregate) => self.check_aggregate(aggregate, &path),
+            Rel::Sort(sort) => {
+                let input = self.check_rel(&sort.input, &format!("{}.input", path))?;
+                for (i, sort_field) in sort.sorts.iter().enumerate() {
+                    self.check_expression(
+                        &sort_field.expression,
+                        &input,
+                        &format!("{}.sorts[{}]", path, i),
+                    );
+                }
+                Some(input)
+            }
+            Rel::Join(join) => self.check_join(join, &path),
+            Rel::Project(project) => {
+                let input = self.check_rel(&project.input, &format!("{}.input", path))?;
+                let mut output = input.clone();
+                for (i, expression) in project.expressions.iter().enumerate() {
+                    let path = format!("{}.expressions[{}]", path, i);
+                    // later columns cannot be typed reliably
+                    let t = self.check_expression(expression, &input, &path)?;
+                    output.columns.push(Column {
+                        name: expression_name(expression, &input),
+                        r#type: t,
+                    });
+                }
+                Some(output)
+            }
+            Rel::Set(set) => self.check_set(set, &path),
+            Rel::Reference(reference) => self.check_subtree(reference.subtree, &path),
+            Rel::Cross(cross) => {
+                let left = self.check_rel(&cross.left, &format!("{}.left", path));
+                let right = self.check_rel(&cross.right, &format!("{}.right", path));
+                Some(left?.concat(&right?))
+            }
+            Rel::HashJoin(join) | Rel::MergeJoin(join) => self.check_keyed_join(join, &path),
+            Rel::Exchange(exchange) => {
+                let input = self.check_rel(&exchange.input, &format!("{}.input", path))?;
+                if exchange.partition_count < 1 {
+                    self.error(
+                        &format!("{}.partition_count", path),
+                        format!(
+                            "partition count must be positive but is {}",
+                            exchange.partition_count
+                        ),
+                    );
+                }
+                match &exchange.kind {
+                    ExchangeKind::ScatterByFields(fields) => {
+                        self.check_keys(fields, &input, &format!("{}.fields", path))
+                    }
+                    ExchangeKind::SingleTarget(expression)
+                    | ExchangeKind::MultiTarget { expression, .. } => {
+                        self.check_expression(expression, &input, &format!("{}.expression", path));
+                    }
+                    ExchangeKind::RoundRobin { .. } | ExchangeKind::Broadcast => {}
+                }
+                Some(input)
+            }
+        }?;
+        let schema = self.apply_emit(rel.emit(), direct, &path)?;
+        self.schemas.insert(rel as *const Rel, schema.clone());
+        Some(schema)
+    }
+
+    fn apply_emit(&mut self, emit: &Emit, direct: Schema, path: &str) -> Option<Schema> {
+        let mapping = match emit {
+            Emit::Direct => return Some(direct),
+            Emit::Remap(mapping) => mapping,
+        };
+        let mut columns = vec![];
+        for (i, field) in mapping.iter().enumerate() {
+            match usize::try_from(*field)
+                .ok()
+                .and_then(|f| direct.columns.get(f))
+            {
+                Some(column) => columns.push(column.clone()),
+                None => self.error(
+                    &format!("{}.emit[{}]", path, i),
+                    format!(
+                        "emit refers to column {} but the relation outputs {} columns",
+                        field,
+                        direct.columns.len()
+                    ),
+                ),
+            }
+        }
+        if columns.len() != mapping.len() {
+            return None;
+        }
+        Some(Schema { columns })
+    }
+
+    // Output is the distinct grouping expressions, followed by the measures and, when there are
+    // several grouping sets, the index of the grouping set each row belongs to.
+    fn check_aggregate(&mut self, aggregate: &Aggregate, path: &str) -> Option<Schema> {
+        let input = self.check_rel(&aggregate.input, &format!("{}.input", path))?;
+        let mut keys: Vec<&Expression> = vec![];
+        let mut columns = vec![];
+        let mut complete = true;
+        for (i, grouping) in aggregate.groupings.iter().enumerate() {
+            for (j, expression) in grouping.iter().enumerate() {
+                let path = format!("{}.groupings[{}][{}]", path, i, j);
+                let t = self.check_expression(expression, &input, &path);
+                if keys.contains(&expression) {
+                    continue;
+                }
+                keys.push(expression);
+                match t {
+                    Some(t) => {
+                        // keys missing from some grouping set are null in the rows of that set
+                        let nullable = t.nullable()
+                            || !aggregate.groupings.iter().all(|g| g.contains(expression));
+                        columns.push(Column {
+                            name: expression_name(expression, &input),
+                            r#type: t.with_nullable(nullable),
+                        });
+                    }
+                    None => complete = false,
+                }
+            }
+        }
+
+        for (i, measure) in aggregate.measures.iter().enumerate() {
+            let path = format!("{}.measures[{}]", path, i);
+            for (j, arg) in measure.function.args.iter().enumerate() {
+                if let FunctionArgument::Value(e) = arg {
+                    self.check_expression(e, &input, &format!("{}.args[{}]", path, j));
+                }
+            }
+            for (j, sort_field) in measure.function.sorts.iter().enumerate() {
+                self.check_expression(
+                    &sort_field.expression,
+                    &input,
+                    &format!("{}.sorts[{}]", path, j),
+                );
+            }
+            if let Some(filter) = &measure.filter {
+                self.check_condition(filter, &input, &format!("{}.filter", path));
+            }
+            columns.push(Column {
+                name: None,
+                r#type: measure.function.output_type.clone(),
+            });
+        }
+
+        if aggregate.groupings.len() > 1 {
+            columns.push(Column {
+                name: None,
+                r#type: Type::I32 { nullable: false },
+            });
+        }
+        complete.then_some(Schema { columns })
+    }
+
+    fn check_join(&mut self, join: &Join, path: &str) -> Option<Schema> {
+        let left = self.check_rel(&join.left, &format!("{}.left", path));
+        let right = self.check_rel(&join.right, &format!("{}.right", path));
+        let (left, right) = (left?, right?);
+        let combined = left.concat(&right);
+        if let Some(expression) = &join.expression {
+            self.check_condition(expression, &combined, &format!("{}.expression", path));
+        }
+        if let Some(filter) = &join.post_join_filter {
+            self.check_condition(filter, &combined, &format!("{}.post_join_filter", path));
+        }
+        Some(join_output(&join.join_type, left, right))
+    }
+
+    fn check_keyed_join(&mut self, join: &KeyedJoin, path: &str) -> Option<Schema> {
+        let left = self.check_rel(&join.left, &format!("{}.left", path));
+        let right = self.check_rel(&join.right, &format!("{}.right", path));
+        let (left, right) = (left?, right?);
+        if join.left_keys.len() != join.right_keys.len() {
+            self.error(
+                path,
+                format!(
+                    "join has {} left keys but {} right keys",
+                    join.left_keys.len(),
+                    join.right_keys.len()
+                ),
+            );
+        }
+        self.check_keys(&join.left_keys, &left, &format!("{}.left_keys", path));
+        self.check_keys(&join.right_keys, &right, &format!("{}.right_keys", path));
+        let combined = left.concat(&right);
+        if let Some(filter) = &join.post_join_filter {
+            self.check_condition(filter, &combined, &format!("{}.post_join_filter", path));
+        }
+        Some(join_output(&join.join_type, left, right))
+    }
+
+    fn check_keys(&mut self, keys: &[FieldReference], input: &Schema, path: &str) {
+        for (i, key) in keys.iter().enumerate() {
+            self.check_field_reference(key, input, &format!("{}[{}]", path, i));
+        }
+    }
+
+    // All inputs must have the same column types. The output takes the first input's names and
+    // a column is nullable if it is nullable in any input.
+    fn check_set(&mut self, set: &Set, path: &str) -> Option<Schema> {
+        let inputs: Vec<Option<Schema>> = set
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(i, input)| self.check_rel(input, &format!("{}.inputs[{}]", path, i)))
+            .collect();
+        let mut output = match inputs.first() {
+            Some(first) => first.clone()?,
+            None => {
+                self.error(path, "set relation has no inputs".to_string());
+                return None;
+            }
+        };
+        for (i, input) in inputs.iter().enumerate().skip(1) {
+            let Some(input) = input else {
+                continue;
+            };
+            let input_path = format!("{}.inputs[{}]", path, i);
+            if input.columns.len() != output.columns.len() {
+                self.error(
+                    &input_path,
+                    format!(
+                        "set input has {} columns but the first input has {}",
+                        input.columns.len(),
+                        output.columns.len()
+                    ),
+                );
+                continue;
+            }
+            for (j, (column, output_column)) in
+                input.columns.iter().zip(&mut output.columns).enumerate()
+            {
+                let t = &column.r#type;
+                let output_type = &output_column.r#type;
+                if t.clone().with_nullable(false) != output_type.clone().with_nullable(false) {
+                    self.error(
+                        &input_path,
+                        format!(
+                            "set input column {} is {} but the first input has {}",
+                            j, t, output_type
+                        ),
+                    );
+                } else if t.nullable() && !output_type.nullable() {
+                    output_column.r#type = output_type.clone().with_nullable(true);
+                }
+            }
+        }
+        Some(output)
+    }
+
+    fn check_condition(&mut self, condition: &Expression, input: &Schema, path: &str) {
+        match self.check_expression(condition, input, path) {
+            Some(Type::Bool { .. }) | None => {}
+            Some(t) => self.error(path, format!("condition must be boolean but is {}", t)),
+        }
+    }
+
+    fn check_expression(
+        &mut self,
+        expression: &Expression,
+        input: &Schema,
+        path: &str,
+    ) -> Option<Type> {
+        match expression {
+            Expression::Literal(literal) => Some(literal.output_type()),
+            Expression::FieldReference(field_reference) => {
+                self.check_field_reference(field_reference, input, path)
+            }
+            Expression::ScalarFunction(invocation) => {
+                let argument_types: Vec<Option<Type>> = invocation
+                    .args
+                    .iter()
+                    .enumerate()
+                    .map(|(i, arg)| match arg {
+                        FunctionArgument::Value(e) => {
+                            self.check_expression(e, input, &format!("{}.args[{}]", path, i))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                if self.registry.is_some()
+                    && invocation
+                        .args
+                        .iter()
+                        .zip(&argument_types)
+                        .all(|(arg, t)| !matches!(arg, FunctionArgument::Value(_)) || t.is_some())
+                {
+                    let arguments: Vec<BoundArgument> = invocation
+                        .args
+                        .iter()
+                        .zip(&argument_types)
+                        .map(|(arg, t)| match arg {
+                            FunctionArgument::Value(_) => {
+                                BoundArgument::Value(t.as_ref().expect("checked above"))
+                            }
+                            FunctionArgument::Enum(e) => BoundArgument::Enum(e),
+                            FunctionArgument::Type(t) => BoundArgument::Type(t),
+                        })
+                        .collect();
+                    self.check_invocation(invocation, &arguments, path);
+                }
+                Some(invocation.output_type.clone())
+            }
+            // which conversions are possible is up to the consumer, so only the input is checked
+            Expression::Cast(cast) => {
+                self.check_expression(&cast.input, input, &format!("{}.input", path));
+                Some(cast.r#type.clone())
+            }
+        }
+    }
+
+    fn check_field_reference(
+        &mut self,
+        field_reference: &FieldReference,
+        input: &Schema,
+        path: &str,
+    ) -> Option<Type> {
+        let column = usize::try_from(field_reference.field)
+            .ok()
+            .and_then(|f| input.columns.get(f));
+        match column {
+            Some(column) => Some(column.r#type.clone()),
+            None => {
+                self.error(
+                    path,
+                    format!(
+                        "field reference {} is out of bounds for input with {} columns",
+                        field_reference.field,
+                        input.columns.len()
+                    ),
+                );
+                None
+            }
+        }
+    }
+
+    fn check_invocation(
+        &mut self,
+        invocation: &ScalarFunctionInvocation,
+        arguments: &[BoundArgument],
+        path: &str,
+    ) {
+        let function = &invocation.function;
+        let definition = self
+            .registry
+            .and_then(|r| r.get(&function.extension.0))
+            .and_then(|e| {
+                e.scalar_functions
+                    .iter()
+                    .find(|sf| sf.name == function.name())
+            });
+        let definition = match definition {
+            Some(definition) => definition,
+            None => {
+                return self.report(
+                    path,
+                    format!(
+                        "scalar function {} is not defined in {}",
+                        function.signature.0, function.extension.0
+                    ),
+                )
+            }
+        };
+
+        let derived = match function.variant {
+            Some(VariantReference::Scalar(index)) => {
+                let variant = match definition.variants.get(index) {
+                    Some(variant) => variant,
+                    None => {
+                        return self.error(
+                            path,
+                            format!(
+                                "scalar function {} has no variant {}",
+                                function.signature.0, index
+                            ),
+                        )
+                    }
+                };
+                match bind_arguments(variant, arguments) {
+                    Some(bindings) => derive_output_type(variant, arguments, &bindings),
+                    None => Err(format!(
+                        "arguments ({}) do not match {}",
+                        arguments
+                            .iter()
+                            .map(|a| a.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        function.signature.0
+                    )),
+                }
+            }
+            _ => definition
+                .resolve_overload(arguments)
+                .map(|resolution| resolution.output_type)
+                .map_err(|e| e.to_string()),
+        };
+
+        match derived {
+            Ok(derived) if derived == invocation.output_type => {}
+            Ok(derived) => self.report(
+                path,
+                format!(
+                    "{} declares output type {} but its arguments derive {}",
+                    function.signature.0, invocation.output_type, derived
+                ),
+            ),
+            Err(reason) => self.report(path, reason),
+        }
+    }
+}
+
+// The columns output by a join of the given type
+fn join_output(join_type: &JoinType, left: Schema, right: Schema) -> Schema {
+    let mark = Column {
+        name: None,
+        r#type: Type::Bool { nullable: true },
+    };
+    match join_type {
+        JoinType::Inner => left.concat(&right),
+        JoinType::Outer => left.with_nullable().concat(&right.with_nullable()),
+        JoinType::Left | JoinType::LeftSingle => left.concat(&right.with_nullable()),
+        JoinType::Right | JoinType::RightSingle => left.with_nullable().concat(&right),
+        JoinType::LeftSemi | JoinType::LeftAnti => left,
+        JoinType::RightSemi | JoinType::RightAnti => right,
+        JoinType::LeftMark => Schema {
+            columns: left.columns.into_iter().chain([mark]).collect(),
+        },
+        JoinType::RightMark => Schema {
+            columns: right.columns.into_iter().chain([mark]).collect(),
+        },
+    }
+}
+
+fn rel_kind(rel: &Rel) -> &'static str {
+    match rel {
+        Rel::Read(_) => "read",
+        Rel::Filter(_) => "filter",
+        Rel::Fetch(_) => "fetch",
+        Rel::Aggregate(_) => "aggregate",
+        Rel::Sort(_) => "sort",
+        Rel::Join(_) => "join",
+        Rel::Project(_) => "project",
+        Rel::Set(_) => "set",
+        Rel::Cross(_) => "cross",
+        Rel::Reference(_) => "reference",
+        Rel::HashJoin(_) => "hash_join",
+        Rel::MergeJoin(_) => "merge_join",
+        Rel::Exchange(_) => "exchange",
+    }
+}
+
+// Plain field references keep the name of the column they refer to
+fn expression_name(expression: &Expression, input: &Schema) -> Option<String> {
+    match expression {
+        Expression::FieldReference(field_reference) => usize::try_from(field_reference.field)
+            .ok()
+            .and_then(|f| input.columns.get(f))
+            .and_then(|c| c.name.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plans::expressions::field_reference::FieldReference;
+    use crate::plans::expressions::literal::{Bool, I64};
+    use crate::plans::expressions::{Function, FunctionSignature, URI};
+    use crate::plans::{Filter, Project, Read, Set, SetOp};
+    use crate::types::NamedStruct;
+
+    fn read(emit: Emit) -> Rel {
+        Rel::Read(Read {
+            emit,
+            base_schema: NamedStruct {
+                names: vec!["id".to_string(), "flag".to_string()],
+                types: vec![Type::I64 { nullable: false }, Type::Bool { nullable: true }],
+            },
+            read_type: ReadType::NamedTable {
+                names: vec!["t".to_string()],
+            },
+        })
+    }
+
+    fn field(field: i32) -> Expression {
+        Expression::FieldReference(FieldReference { field })
+    }
+
+    fn column(name: Option<&str>, r#type: Type) -> Column {
+        Column {
+            name: name.map(|n| n.to_string()),
+            r#type,
+        }
+    }
+
+    #[test]
+    fn project_honors_emit() {
+        let rel = Rel::Project(Project {
+            emit: Emit::Remap(vec![2, 0]),
+            input: Box::new(read(Emit::Direct)),
+            expressions: vec![field(1)],
+        });
+        assert_eq!(
+            output_schema(&rel).unwrap(),
+            Schema {
+                columns: vec![
+                    column(Some("flag"), Type::Bool { nullable: true }),
+                    column(Some("id"), Type::I64 { nullable: false }),
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn left_join_makes_right_side_nullable() {
+        let rel = Rel::Join(Join {
+            emit: Emit::Direct,
+            left: Box::new(read(Emit::Remap(vec![0]))),
+            right: Box::new(read(Emit::Remap(vec![0]))),
+            expression: None,
+            post_join_filter: None,
+            join_type: JoinType::Left,
+        });
+        assert_eq!(
+            output_schema(&rel).unwrap().types(),
+            vec![Type::I64 { nullable: false }, Type::I64 { nullable: true }]
+        );
+    }
+
+    #[test]
+    fn set_columns_are_nullable_if_any_input_is() {
+        let other = Rel::Read(Read {
+            emit: Emit::Direct,
+            base_schema: NamedStruct {
+                names: vec!["other_id".to_string(), "other_flag".to_string()],
+                types: vec![Type::I64 { nullable: true }, Type::Bool { nullable: false }],
+            },
+            read_type: ReadType::NamedTable {
+                names: vec!["u".to_string()],
+            },
+        });
+        let rel = Rel::Set(Set {
+            emit: Emit::Direct,
+            inputs: vec![read(Emit::Direct), other],
+            op: SetOp::UnionAll,
+        });
+        assert_eq!(
+            output_schema(&rel).unwrap(),
+            Schema {
+                columns: vec![
+                    column(Some("id"), Type::I64 { nullable: true }),
+                    column(Some("flag"), Type::Bool { nullable: true }),
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn reports_mismatched_set_columns() {
+        let rel = Rel::Set(Set {
+            emit: Emit::Direct,
+            inputs: vec![
+                read(Emit::Direct),
+                read(Emit::Remap(vec![1, 0])),
+                read(Emit::Remap(vec![0])),
+            ],
+            op: SetOp::UnionDistinct,
+        });
+        let diagnostics = output_schema(&rel).unwrap_err();
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "error at root.set.inputs[1]: set input column 0 is boolean? but the first input \
+                 has i64",
+                "error at root.set.inputs[1]: set input column 1 is i64 but the first input has \
+                 boolean?",
+                "error at root.set.inputs[2]: set input has 1 columns but the first input has 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_mismatched_join_keys() {
+        let rel = Rel::HashJoin(KeyedJoin {
+            emit: Emit::Direct,
+            left: Box::new(read(Emit::Direct)),
+            right: Box::new(read(Emit::Remap(vec![0]))),
+            left_keys: vec![FieldReference { field: 0 }],
+            right_keys: vec![FieldReference { field: 1 }, FieldReference { field: 0 }],
+            post_join_filter: None,
+            join_type: JoinType::LeftSemi,
+        });
+        let diagnostics = output_schema(&rel).unwrap_err();
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "error at root.hash_join: join has 1 left keys but 2 right keys",
+                "error at root.hash_join.right_keys[0]: field reference 1 is out of bounds for \
+                 input with 1 columns",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_mistyped_virtual_table_rows() {
+        let i64 = |value| {
+            Literal::I64(I64 {
+                value,
+                nullable: false,
+            })
+        };
+        let rel = Rel::Read(Read {
+            emit: Emit::Direct,
+            base_schema: NamedStruct {
+                names: vec!["id".to_string(), "flag".to_string()],
+                types: vec![Type::I64 { nullable: false }, Type::Bool { nullable: true }],
+            },
+            read_type: ReadType::VirtualTable {
+                rows: vec![
+                    vec![
+                        i64(1),
+                        Literal::Bool(Bool {
+                            value: true,
+                            nullable: false,
+                        }),
+                    ],
+                    vec![i64(2)],
+                    vec![i64(3), i64(4)],
+                ],
+            },
+        });
+        let diagnostics = output_schema(&rel).unwrap_err();
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "error at root.read: row 1 has 1 values but the base schema has 2 columns",
+                "error at root.read: value 4:i64 in column 1 of row 2 does not have the column \
+                 type boolean?",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_out_of_bounds_field_reference() {
+        let rel = Rel::Filter(Filter {
+            emit: Emit::Direct,
+            input: Box::new(Rel::Project(Project {
+                emit: Emit::Direct,
+                input: Box::new(read(Emit::Direct)),
+                expressions: vec![field(5)],
+            })),
+            condition: field(1),
+        });
+        let diagnostics = output_schema(&rel).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error at root.filter.input.project.expressions[0]: \
+             field reference 5 is out of bounds for input with 2 columns"
+        );
+    }
+
+    const NULLABILITY: &str = r#"
+    scalar_functions:
+      - name: "pick"
+        impls:
+          - args:
+              - { value: i32? }
+            nullability: DISCRETE
+            return: i32
+    "#;
+
+    fn registry() -> ExtensionRegistry {
+        let mut registry = ExtensionRegistry::standard();
+        registry
+            .register_yaml("/nullability.yaml", NULLABILITY)
+            .unwrap();
+        registry
+    }
+
+    // A project of a single scalar function invocation over a read of an i32, a nullable i32
+    // and two decimal columns
+    fn invocation(
+        extension: &str,
+        signature: &str,
+        args: Vec<i32>,
+        output_type: Type,
+        variant: Option<VariantReference>,
+    ) -> Plan {
+        Plan {
+            root: Box::new(Rel::Project(Project {
+                emit: Emit::Direct,
+                input: Box::new(Rel::Read(Read {
+                    emit: Emit::Direct,
+                    base_schema: NamedStruct {
+                        names: vec![
+                            "n".to_string(),
+                            "m".to_string(),
+                            "d".to_string(),
+                            "e".to_string(),
+                        ],
+                        types: vec![
+                            Type::I32 { nullable: false },
+                            Type::I32 { nullable: true },
+                            Type::Decimal {
+                                nullable: false,
+                                precision: 10,
+                                scale: 2,
+                            },
+                            Type::Decimal {
+                                nullable: false,
+                                precision: 5,
+                                scale: 3,
+                            },
+                        ],
+                    },
+                    read_type: ReadType::NamedTable {
+                        names: vec!["t".to_string()],
+                    },
+                })),
+                expressions: vec![Expression::ScalarFunction(ScalarFunctionInvocation {
+                    function: Function {
+                        signature: FunctionSignature(signature.to_string()),
+                        extension: URI(extension.to_string()),
+                        variant,
+                    },
+                    args: args
+                        .into_iter()
+                        .map(|f| FunctionArgument::Value(field(f)))
+                        .collect(),
+                    output_type,
+                })],
+            })),
+            names: vec![],
+            subtrees: vec![],
+        }
+    }
+
+    #[test]
+    fn reports_mismatched_output_types() {
+        let plan = invocation(
+            "/functions_arithmetic.yaml",
+            "add:i32_i32",
+            vec![0, 0],
+            Type::I64 { nullable: false },
+            None,
+        );
+        let diagnostics = check_output_types(&plan, &registry(), false).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].path, "root.project.expressions[0]");
+        assert_eq!(
+            diagnostics[0].message,
+            "add:i32_i32 declares output type i64 but its arguments derive i32"
+        );
+
+        let diagnostics = check_output_types(&plan, &registry(), true).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+
+        let plan = invocation(
+            "/functions_arithmetic.yaml",
+            "add:i32_i32",
+            vec![0, 0],
+            Type::I32 { nullable: false },
+            None,
+        );
+        assert!(check_output_types(&plan, &registry(), true)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn mirrors_argument_nullability() {
+        let plan = invocation(
+            "/functions_arithmetic.yaml",
+            "add:i32_i32",
+            vec![0, 1],
+            Type::I32 { nullable: true },
+            None,
+        );
+        assert!(check_output_types(&plan, &registry(), true)
+            .unwrap()
+            .is_empty());
+
+        let plan = invocation(
+            "/functions_arithmetic.yaml",
+            "add:i32_i32",
+            vec![0, 1],
+            Type::I32 { nullable: false },
+            None,
+        );
+        let diagnostics = check_output_types(&plan, &registry(), true).unwrap_err();
+        assert!(
+            diagnostics[0].message.ends_with("derive i32?"),
+            "{}",
+            diagnostics[0]
+        );
+    }
+
+    #[test]
+    fn matches_discrete_nullability_exactly() {
+        // the declared output nullability holds regardless of the argument
+        let plan = invocation(
+            "/nullability.yaml",
+            "pick:i32",
+            vec![1],
+            Type::I32 { nullable: false },
+            None,
+        );
+        assert!(check_output_types(&plan, &registry(), true)
+            .unwrap()
+            .is_empty());
+
+        // a required argument does not match the nullable parameter
+        let plan = invocation(
+            "/nullability.yaml",
+            "pick:i32",
+            vec![0],
+            Type::I32 { nullable: false },
+            None,
+        );
+        let diagnostics = check_output_types(&plan, &registry(), true).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(
+            diagnostics[0].message.contains("no variant"),
+            "{}",
+            diagnostics[0]
+        );
+    }
+
+    #[test]
+    fn runs_return_type_programs() {
+        // decimal<10,2> + decimal<5,3> has scale max(2,3) and precision 3 + max(8,2) + 1
+        let plan = invocation(
+            "/functions_arithmetic_decimal.yaml",
+            "add:dec_dec",
+            vec![2, 3],
+            Type::Decimal {
+                nullable: false,
+                precision: 12,
+                scale: 3,
+            },
+            None,
+        );
+        assert!(check_output_types(&plan, &registry(), true)
+            .unwrap()
+            .is_empty());
+
+        let plan = invocation(
+            "/functions_arithmetic_decimal.yaml",
+            "add:dec_dec",
+            vec![2, 3],
+            Type::Decimal {
+                nullable: false,
+                precision: 10,
+                scale: 2,
+            },
+            None,
+        );
+        let diagnostics = check_output_types(&plan, &registry(), false).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(
+            diagnostics[0].message.ends_with("derive decimal<12,3>"),
+            "{}",
+            diagnostics[0]
+        );
+    }
+
+    #[test]
+    fn reports_unknown_variants() {
+        let plan = invocation(
+            "/functions_arithmetic.yaml",
+            "add:i32_i32",
+            vec![0, 0],
+            Type::I32 { nullable: false },
+            Some(VariantReference::Scalar(100)),
+        );
+        let diagnostics = check_output_types(&plan, &registry(), false).unwrap_err();
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>(),
+            vec!["error at root.project.expressions[0]: scalar function add:i32_i32 has no variant 100"]
+        );
+    }
+
+    #[test]
+    fn reports_non_boolean_condition() {
+        let rel = Rel::Filter(Filter {
+            emit: Emit::Direct,
+            input: Box::new(read(Emit::Direct)),
+            condition: field(0),
+        });
+        let diagnostics = output_schema(&rel).unwrap_err();
+        assert_eq!(diagnostics[0].path, "root.filter.condition");
+        assert_eq!(
+            diagnostics[0].message,
+            "condition must be boolean but is i64"
+        );
+    }
+}
@@ -1,146 +1,737 @@
+use crate::diagnostics::Diagnostic;
+use crate::extensions::registry::ExtensionRegistry;
+use crate::extensions::signature::Signature;
+use crate::plans::expressions::aggregate_function::{
+    AggregateFunctionInvocation, AggregationInvocation, AggregationPhase,
+};
+use crate::plans::expressions::cast::{Cast, FailureBehavior};
 use crate::plans::expressions::field_reference::FieldReference;
-use crate::plans::expressions::literal::{Bool, Literal, I32, I64};
+use crate::plans::expressions::literal::{
+    Binary, Bool, Date, Decimal, Literal, Str, Timestamp, FP32, FP64, I32, I64,
+};
 use crate::plans::expressions::scalar_function::ScalarFunctionInvocation;
+use crate::plans::expressions::sort_field::{SortDirection, SortField};
 use crate::plans::expressions::{Expression, Function, FunctionArgument, FunctionSignature, URI};
-use crate::plans::{Plan, Project, Read, Rel};
+use crate::plans::{
+    Aggregate, Cross, DelimitedTextOptions, Emit, Exchange, ExchangeKind, Fetch, FileFormat,
+    FileOrFiles, FilePath, Filter, Join, JoinType, KeyedJoin, Measure, Plan, Project, Read,
+    ReadType, Reference, Rel, Set, SetOp, Sort,
+};
 use crate::types;
-use crate::types::{NamedStruct, Type};
+use crate::types::{NamedStruct, Type, TypeParameter};
+use prost::Message;
 use std::collections::HashMap;
+use std::fmt;
 use substrait::proto;
-use substrait::proto::extensions::simple_extension_declaration::{ExtensionFunction, MappingType};
+use substrait::proto::extensions::simple_extension_declaration::{
+    ExtensionFunction, ExtensionType, ExtensionTypeVariation, MappingType,
+};
+use substrait::proto::read_rel::local_files;
+use substrait::proto::read_rel::local_files::file_or_files;
+
+// Join types added to the specification after the pinned protos, which have no enum variants
+// for them. Their values follow the later releases.
+pub(crate) const JOIN_TYPE_RIGHT_SEMI: i32 = 8;
+pub(crate) const JOIN_TYPE_RIGHT_ANTI: i32 = 9;
+pub(crate) const JOIN_TYPE_RIGHT_SINGLE: i32 = 10;
+pub(crate) const JOIN_TYPE_LEFT_MARK: i32 = 11;
+pub(crate) const JOIN_TYPE_RIGHT_MARK: i32 = 12;
+
+pub(crate) const KEYED_JOIN_TYPE_LEFT_SINGLE: i32 = 9;
+pub(crate) const KEYED_JOIN_TYPE_RIGHT_SINGLE: i32 = 10;
+pub(crate) const KEYED_JOIN_TYPE_LEFT_MARK: i32 = 11;
+pub(crate) const KEYED_JOIN_TYPE_RIGHT_MARK: i32 = 12;
+
+// Delimited text files were added after the pinned protos too. Until then they travel as an
+// extension file format holding the message of the later releases.
+pub(crate) const DELIMITED_TEXT_TYPE_URL: &str =
+    "type.googleapis.com/substrait.ReadRel.LocalFiles.FileOrFiles.DelimiterSeparatedTextReadOptions";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct DelimiterSeparatedTextReadOptions {
+    #[prost(string, tag = "1")]
+    pub field_delimiter: String,
+    #[prost(uint64, tag = "2")]
+    pub max_line_size: u64,
+    #[prost(string, tag = "3")]
+    pub quote: String,
+    #[prost(uint64, tag = "4")]
+    pub header_lines_to_skip: u64,
+    #[prost(string, tag = "5")]
+    pub escape: String,
+    #[prost(string, optional, tag = "6")]
+    pub value_treated_as_null: Option<String>,
+}
 
 trait PlanDecoder<PlanFormat> {
-    fn decode(&mut self, plan: &PlanFormat) -> Plan;
+    fn decode(&mut self, plan: &PlanFormat) -> Result<Plan, Vec<Diagnostic>>;
 }
 
+// Problems found while decoding, located by the path of the proto message they concern.
+// Relations are decoded up to the first problem, while every problem with the extension
+// declarations is reported at once.
+type DecodeResult<T> = Result<T, Diagnostic>;
+
 #[derive(Debug, Eq, Hash, PartialEq)]
 struct ExtensionAnchor(u32);
 
 #[derive(Debug, Eq, Hash, PartialEq)]
 struct FunctionAnchor(u32);
 
-pub fn decode_prost_plan(plan: &proto::Plan) -> Plan {
-    let mut decoder = ProstPlanDecoder::new();
-    return decoder.decode(plan);
+#[derive(Debug, Eq, Hash, PartialEq)]
+struct TypeAnchor(u32);
+
+#[derive(Debug, Eq, Hash, PartialEq)]
+struct TypeVariationAnchor(u32);
+
+pub fn decode_prost_plan(plan: &proto::Plan) -> Result<Plan, Vec<Diagnostic>> {
+    let mut decoder = ProstPlanDecoder::new(None);
+    decoder.decode(plan)
 }
 
-pub struct ProstPlanDecoder {
+// Decodes the plan, requiring every declared extension URI to be present in the registry and
+// every declared function to resolve to one of its functions
+pub fn decode_prost_plan_with_registry(
+    plan: &proto::Plan,
+    registry: &ExtensionRegistry,
+) -> Result<Plan, Vec<Diagnostic>> {
+    let mut decoder = ProstPlanDecoder::new(Some(registry));
+    decoder.decode(plan)
+}
+
+pub struct ProstPlanDecoder<'a> {
+    registry: Option<&'a ExtensionRegistry>,
     extension_uri_map: HashMap<ExtensionAnchor, URI>,
     function_map: HashMap<FunctionAnchor, Function>,
+    type_map: HashMap<TypeAnchor, (URI, String)>,
+    type_variation_map: HashMap<TypeVariationAnchor, (URI, String)>,
+    // position of the root in the plan relations, which subtree ordinals skip over
+    root_ordinal: usize,
 }
 
-impl PlanDecoder<proto::Plan> for ProstPlanDecoder {
-    fn decode(&mut self, plan: &proto::Plan) -> Plan {
-        self.gather_extensions(plan);
+impl PlanDecoder<proto::Plan> for ProstPlanDecoder<'_> {
+    fn decode(&mut self, plan: &proto::Plan) -> Result<Plan, Vec<Diagnostic>> {
+        let diagnostics = self.gather_extensions(plan);
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+        // without an explicit root, the last relation is the one the others feed into
+        self.root_ordinal = plan
+            .relations
+            .iter()
+            .position(|r| matches!(r.rel_type, Some(proto::plan_rel::RelType::Root(_))))
+            .unwrap_or(plan.relations.len().saturating_sub(1));
         self.decode_plan(plan)
+            .map_err(|diagnostic| vec![diagnostic])
     }
 }
 
-impl ProstPlanDecoder {
-    fn new() -> ProstPlanDecoder {
-        return ProstPlanDecoder {
+impl<'a> ProstPlanDecoder<'a> {
+    fn new(registry: Option<&'a ExtensionRegistry>) -> ProstPlanDecoder<'a> {
+        ProstPlanDecoder {
+            registry,
             extension_uri_map: HashMap::new(),
             function_map: HashMap::new(),
-        };
+            type_map: HashMap::new(),
+            type_variation_map: HashMap::new(),
+            root_ordinal: 0,
+        }
     }
 
-    fn decode_plan(&self, p: &proto::Plan) -> Plan {
-        let root = p.relations.get(0).expect("assume 1 rel for now");
-        let rel_type = root.rel_type.as_ref().expect("expecting presence of plan");
-        return Plan {
-            root: Box::new(match rel_type {
-                proto::plan_rel::RelType::Rel(rel) => self.decode_relation(rel),
-                proto::plan_rel::RelType::Root(rel_root) => {
-                    self.decode_relation(rel_root.input.as_ref().expect("argh"))
-                }
-            }),
+    fn decode_plan(&self, p: &proto::Plan) -> DecodeResult<Plan> {
+        let path = format!("relations[{}]", self.root_ordinal);
+        let root = p
+            .relations
+            .get(self.root_ordinal)
+            .ok_or_else(|| Diagnostic::error("relations", "plan has no relations".to_string()))?;
+        let (root, names) = match required(&root.rel_type, &path, "relation")? {
+            proto::plan_rel::RelType::Rel(rel) => (self.decode_relation(rel, &path)?, vec![]),
+            proto::plan_rel::RelType::Root(rel_root) => {
+                let path = format!("{}.root.input", path);
+                let input = required(&rel_root.input, &path, "root input")?;
+                (self.decode_relation(input, &path)?, rel_root.names.clone())
+            }
         };
+        let subtrees = p
+            .relations
+            .iter()
+            .enumerate()
+            .filter(|(ordinal, _)| *ordinal != self.root_ordinal)
+            .map(|(ordinal, subtree)| -> DecodeResult<Rel> {
+                let path = format!("relations[{}]", ordinal);
+                match required(&subtree.rel_type, &path, "relation")? {
+                    proto::plan_rel::RelType::Rel(rel) => self.decode_relation(rel, &path),
+                    proto::plan_rel::RelType::Root(_) => Err(Diagnostic::error(
+                        &path,
+                        "plan has multiple roots".to_string(),
+                    )),
+                }
+            })
+            .collect::<DecodeResult<Vec<_>>>()?;
+        Ok(Plan {
+            root: Box::new(root),
+            names,
+            subtrees,
+        })
     }
 
-    fn decode_relation(&self, rel: &proto::Rel) -> Rel {
-        let rel_type = rel.rel_type.as_ref().expect("rel_type must be set");
-        match rel_type {
-            proto::rel::RelType::Read(rr) => Rel::Read(self.decode_read(rr)),
-            // RelType::Filter(_) => {}
-            // RelType::Fetch(_) => {}
-            // RelType::Aggregate(_) => {}
-            // RelType::Sort(_) => {}
-            // RelType::Join(_) => {}
-            proto::rel::RelType::Project(pr) => Rel::Project(self.decode_project(pr)),
-            // RelType::Set(_) => {}
+    fn decode_relation(&self, rel: &proto::Rel, path: &str) -> DecodeResult<Rel> {
+        let rel_type = required(&rel.rel_type, path, "rel_type")?;
+        Ok(match rel_type {
+            proto::rel::RelType::Read(rr) => {
+                Rel::Read(self.decode_read(rr, &format!("{}.read", path))?)
+            }
+            proto::rel::RelType::Filter(fr) => {
+                Rel::Filter(self.decode_filter(fr, &format!("{}.filter", path))?)
+            }
+            proto::rel::RelType::Fetch(fr) => {
+                Rel::Fetch(self.decode_fetch(fr, &format!("{}.fetch", path))?)
+            }
+            proto::rel::RelType::Aggregate(ar) => {
+                Rel::Aggregate(self.decode_aggregate(ar, &format!("{}.aggregate", path))?)
+            }
+            proto::rel::RelType::Sort(sr) => {
+                Rel::Sort(self.decode_sort(sr, &format!("{}.sort", path))?)
+            }
+            proto::rel::RelType::Join(jr) => {
+                Rel::Join(self.decode_join(jr, &format!("{}.join", path))?)
+            }
+            proto::rel::RelType::Project(pr) => {
+                Rel::Project(self.decode_project(pr, &format!("{}.project", path))?)
+            }
+            proto::rel::RelType::Set(sr) => {
+                Rel::Set(self.decode_set(sr, &format!("{}.set", path))?)
+            }
             // RelType::ExtensionSingle(_) => {}
             // RelType::ExtensionMulti(_) => {}
             // RelType::ExtensionLeaf(_) => {}
-            // RelType::Cross(_) => {}
-            // RelType::Reference(_) => {}
+            proto::rel::RelType::Cross(cr) => {
+                Rel::Cross(self.decode_cross(cr, &format!("{}.cross", path))?)
+            }
+            proto::rel::RelType::Reference(rr) => {
+                Rel::Reference(self.decode_reference(rr, &format!("{}.reference", path))?)
+            }
             // RelType::Write(_) => {}
             // RelType::Ddl(_) => {}
-            // RelType::HashJoin(_) => {}
-            // RelType::MergeJoin(_) => {}
+            proto::rel::RelType::HashJoin(hjr) => {
+                Rel::HashJoin(self.decode_hash_join(hjr, &format!("{}.hash_join", path))?)
+            }
+            proto::rel::RelType::MergeJoin(mjr) => {
+                Rel::MergeJoin(self.decode_merge_join(mjr, &format!("{}.merge_join", path))?)
+            }
             // RelType::NestedLoopJoin(_) => {}
             // RelType::Window(_) => {}
-            // RelType::Exchange(_) => {}
+            proto::rel::RelType::Exchange(er) => {
+                Rel::Exchange(self.decode_exchange(er, &format!("{}.exchange", path))?)
+            }
             // RelType::Expand(_) => {}
-            _ => panic!("cannot handle rel: {:?}", rel),
+            rt => return Err(unsupported(path, "relation", rt)),
+        })
+    }
+
+    fn decode_emit(&self, common: &Option<proto::RelCommon>) -> Emit {
+        match common.as_ref().and_then(|c| c.emit_kind.as_ref()) {
+            None | Some(proto::rel_common::EmitKind::Direct(_)) => Emit::Direct,
+            Some(proto::rel_common::EmitKind::Emit(emit)) => {
+                Emit::Remap(emit.output_mapping.clone())
+            }
+        }
+    }
+
+    // Decodes the input found at `<path>.<field>`
+    fn decode_input(
+        &self,
+        input: &Option<Box<proto::Rel>>,
+        path: &str,
+        field: &str,
+    ) -> DecodeResult<Box<Rel>> {
+        let path = format!("{}.{}", path, field);
+        let input = required(input, &path, field)?;
+        Ok(Box::new(self.decode_relation(input, &path)?))
+    }
+
+    fn decode_read(&self, rr: &proto::ReadRel, path: &str) -> DecodeResult<Read> {
+        let base_schema = self.decode_named_struct(
+            required(&rr.base_schema, path, "base schema")?,
+            &format!("{}.base_schema", path),
+        )?;
+        let read_type = match required(&rr.read_type, path, "read type")? {
+            proto::read_rel::ReadType::NamedTable(nt) => ReadType::NamedTable {
+                names: nt.names.clone(),
+            },
+            proto::read_rel::ReadType::LocalFiles(lf) => ReadType::LocalFiles {
+                items: lf
+                    .items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| {
+                        decode_file_or_files(item, &format!("{}.local_files.items[{}]", path, i))
+                    })
+                    .collect::<DecodeResult<_>>()?,
+            },
+            proto::read_rel::ReadType::VirtualTable(vt) => ReadType::VirtualTable {
+                rows: vt
+                    .values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, row)| {
+                        row.fields
+                            .iter()
+                            .enumerate()
+                            .map(|(j, f)| {
+                                let path = format!("{}.virtual_table.values[{}][{}]", path, i, j);
+                                self.decode_literal(f, &path)
+                            })
+                            .collect::<DecodeResult<Vec<_>>>()
+                    })
+                    .collect::<DecodeResult<_>>()?,
+            },
+            // ReadType::ExtensionTable(_) => {}
+            rt => return Err(unsupported(path, "read type", rt)),
+        };
+        Ok(Read {
+            emit: self.decode_emit(&rr.common),
+            base_schema,
+            read_type,
+        })
+    }
+
+    fn decode_filter(&self, fr: &proto::FilterRel, path: &str) -> DecodeResult<Filter> {
+        Ok(Filter {
+            emit: self.decode_emit(&fr.common),
+            input: self.decode_input(&fr.input, path, "input")?,
+            condition: self.decode_expression(
+                required(&fr.condition, path, "filter condition")?,
+                &format!("{}.condition", path),
+            )?,
+        })
+    }
+
+    fn decode_fetch(&self, fr: &proto::FetchRel, path: &str) -> DecodeResult<Fetch> {
+        Ok(Fetch {
+            emit: self.decode_emit(&fr.common),
+            input: self.decode_input(&fr.input, path, "input")?,
+            offset: fr.offset,
+            count: fr.count,
+        })
+    }
+
+    fn decode_aggregate(&self, ar: &proto::AggregateRel, path: &str) -> DecodeResult<Aggregate> {
+        Ok(Aggregate {
+            emit: self.decode_emit(&ar.common),
+            input: self.decode_input(&ar.input, path, "input")?,
+            groupings: ar
+                .groupings
+                .iter()
+                .enumerate()
+                .map(|(i, g)| {
+                    g.grouping_expressions
+                        .iter()
+                        .enumerate()
+                        .map(|(j, expr)| {
+                            self.decode_expression(
+                                expr,
+                                &format!("{}.groupings[{}][{}]", path, i, j),
+                            )
+                        })
+                        .collect::<DecodeResult<Vec<_>>>()
+                })
+                .collect::<DecodeResult<_>>()?,
+            measures: ar
+                .measures
+                .iter()
+                .enumerate()
+                .map(|(i, m)| -> DecodeResult<Measure> {
+                    let path = format!("{}.measures[{}]", path, i);
+                    Ok(Measure {
+                        function: self.decode_aggregate_function(
+                            required(&m.measure, &path, "measure")?,
+                            &path,
+                        )?,
+                        filter: m
+                            .filter
+                            .as_ref()
+                            .map(|f| self.decode_expression(f, &format!("{}.filter", path)))
+                            .transpose()?,
+                    })
+                })
+                .collect::<DecodeResult<_>>()?,
+        })
+    }
+
+    fn decode_sort(&self, sr: &proto::SortRel, path: &str) -> DecodeResult<Sort> {
+        Ok(Sort {
+            emit: self.decode_emit(&sr.common),
+            input: self.decode_input(&sr.input, path, "input")?,
+            sorts: self.decode_sort_fields(&sr.sorts, path)?,
+        })
+    }
+
+    fn decode_join(&self, jr: &proto::JoinRel, path: &str) -> DecodeResult<Join> {
+        Ok(Join {
+            emit: self.decode_emit(&jr.common),
+            left: self.decode_input(&jr.left, path, "left")?,
+            right: self.decode_input(&jr.right, path, "right")?,
+            expression: jr
+                .expression
+                .as_ref()
+                .map(|e| self.decode_expression(e, &format!("{}.expression", path)))
+                .transpose()?,
+            post_join_filter: jr
+                .post_join_filter
+                .as_ref()
+                .map(|e| self.decode_expression(e, &format!("{}.post_join_filter", path)))
+                .transpose()?,
+            join_type: decode_join_type(jr.r#type, path)?,
+        })
+    }
+
+    #[allow(deprecated)]
+    fn decode_hash_join(&self, hjr: &proto::HashJoinRel, path: &str) -> DecodeResult<KeyedJoin> {
+        let (left_keys, right_keys) =
+            self.decode_join_keys(&hjr.keys, &hjr.left_keys, &hjr.right_keys, path)?;
+        Ok(KeyedJoin {
+            emit: self.decode_emit(&hjr.common),
+            left: self.decode_input(&hjr.left, path, "left")?,
+            right: self.decode_input(&hjr.right, path, "right")?,
+            left_keys,
+            right_keys,
+            post_join_filter: hjr
+                .post_join_filter
+                .as_ref()
+                .map(|e| self.decode_expression(e, &format!("{}.post_join_filter", path)))
+                .transpose()?,
+            join_type: decode_keyed_join_type(hjr.r#type, path)?,
+        })
+    }
+
+    #[allow(deprecated)]
+    fn decode_merge_join(&self, mjr: &proto::MergeJoinRel, path: &str) -> DecodeResult<KeyedJoin> {
+        let (left_keys, right_keys) =
+            self.decode_join_keys(&mjr.keys, &mjr.left_keys, &mjr.right_keys, path)?;
+        Ok(KeyedJoin {
+            emit: self.decode_emit(&mjr.common),
+            left: self.decode_input(&mjr.left, path, "left")?,
+            right: self.decode_input(&mjr.right, path, "right")?,
+            left_keys,
+            right_keys,
+            post_join_filter: mjr
+                .post_join_filter
+                .as_ref()
+                .map(|e| self.decode_expression(e, &format!("{}.post_join_filter", path)))
+                .transpose()?,
+            join_type: decode_keyed_join_type(mjr.r#type, path)?,
+        })
+    }
+
+    // Keys compared for equality, or the left and right keys older producers write instead
+    fn decode_join_keys(
+        &self,
+        keys: &[proto::ComparisonJoinKey],
+        left_keys: &[proto::expression::FieldReference],
+        right_keys: &[proto::expression::FieldReference],
+        path: &str,
+    ) -> DecodeResult<(Vec<FieldReference>, Vec<FieldReference>)> {
+        use proto::comparison_join_key::comparison_type::InnerType;
+        use proto::comparison_join_key::SimpleComparisonType;
+        if keys.is_empty() {
+            return Ok((
+                self.decode_keys(left_keys, &format!("{}.left_keys", path))?,
+                self.decode_keys(right_keys, &format!("{}.right_keys", path))?,
+            ));
         }
+        keys.iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let path = format!("{}.keys[{}]", path, i);
+                let comparison = required(&key.comparison, &path, "comparison")?;
+                if comparison.inner_type != Some(InnerType::Simple(SimpleComparisonType::Eq as i32))
+                {
+                    return Err(Diagnostic::error(
+                        &path,
+                        format!("cannot handle comparison {:?}", comparison.inner_type),
+                    ));
+                }
+                let left = required(&key.left, &path, "left")?;
+                let right = required(&key.right, &path, "right")?;
+                Ok((
+                    self.decode_field_reference(left, &format!("{}.left", path))?,
+                    self.decode_field_reference(right, &format!("{}.right", path))?,
+                ))
+            })
+            .collect()
+    }
+
+    fn decode_keys(
+        &self,
+        keys: &[proto::expression::FieldReference],
+        path: &str,
+    ) -> DecodeResult<Vec<FieldReference>> {
+        keys.iter()
+            .enumerate()
+            .map(|(i, k)| self.decode_field_reference(k, &format!("{}[{}]", path, i)))
+            .collect()
+    }
+
+    fn decode_exchange(&self, er: &proto::ExchangeRel, path: &str) -> DecodeResult<Exchange> {
+        use proto::exchange_rel::ExchangeKind as Kind;
+        let kind = match required(&er.exchange_kind, path, "exchange kind")? {
+            Kind::ScatterByFields(scatter) => ExchangeKind::ScatterByFields(self.decode_keys(
+                &scatter.fields,
+                &format!("{}.scatter_by_fields.fields", path),
+            )?),
+            Kind::SingleTarget(single) => {
+                let path = format!("{}.single_target", path);
+                ExchangeKind::SingleTarget(self.decode_expression(
+                    required(&single.expression, &path, "single target expression")?,
+                    &format!("{}.expression", path),
+                )?)
+            }
+            Kind::MultiTarget(multi) => {
+                let path = format!("{}.multi_target", path);
+                ExchangeKind::MultiTarget {
+                    expression: self.decode_expression(
+                        required(&multi.expression, &path, "multi target expression")?,
+                        &format!("{}.expression", path),
+                    )?,
+                    constrained_to_count: multi.constrained_to_count,
+                }
+            }
+            Kind::RoundRobin(round_robin) => ExchangeKind::RoundRobin {
+                exact: round_robin.exact,
+            },
+            Kind::Broadcast(_) => ExchangeKind::Broadcast,
+        };
+        Ok(Exchange {
+            emit: self.decode_emit(&er.common),
+            input: self.decode_input(&er.input, path, "input")?,
+            partition_count: er.partition_count,
+            kind,
+        })
+    }
+
+    fn decode_set(&self, sr: &proto::SetRel, path: &str) -> DecodeResult<Set> {
+        Ok(Set {
+            emit: self.decode_emit(&sr.common),
+            inputs: sr
+                .inputs
+                .iter()
+                .enumerate()
+                .map(|(i, input)| self.decode_relation(input, &format!("{}.inputs[{}]", path, i)))
+                .collect::<DecodeResult<_>>()?,
+            op: match proto::set_rel::SetOp::try_from(sr.op) {
+                Ok(proto::set_rel::SetOp::MinusPrimary) => SetOp::MinusPrimary,
+                Ok(proto::set_rel::SetOp::MinusMultiset) => SetOp::MinusMultiset,
+                Ok(proto::set_rel::SetOp::IntersectionPrimary) => SetOp::IntersectionPrimary,
+                Ok(proto::set_rel::SetOp::IntersectionMultiset) => SetOp::IntersectionMultiset,
+                Ok(proto::set_rel::SetOp::UnionDistinct) => SetOp::UnionDistinct,
+                Ok(proto::set_rel::SetOp::UnionAll) => SetOp::UnionAll,
+                Ok(proto::set_rel::SetOp::Unspecified) | Err(_) => {
+                    return Err(Diagnostic::error(
+                        path,
+                        format!("cannot handle set op {}", sr.op),
+                    ))
+                }
+            },
+        })
+    }
+
+    fn decode_cross(&self, cr: &proto::CrossRel, path: &str) -> DecodeResult<Cross> {
+        Ok(Cross {
+            emit: self.decode_emit(&cr.common),
+            left: self.decode_input(&cr.left, path, "left")?,
+            right: self.decode_input(&cr.right, path, "right")?,
+        })
     }
 
-    fn decode_read(&self, rr: &proto::ReadRel) -> Read {
-        Read {
-            base_schema: self
-                .decode_named_struct(rr.base_schema.as_ref().expect("base schema must be set")),
+    fn decode_reference(&self, rr: &proto::ReferenceRel, path: &str) -> DecodeResult<Reference> {
+        let ordinal = usize::try_from(rr.subtree_ordinal).map_err(|_| {
+            Diagnostic::error(
+                path,
+                format!("subtree ordinal {} is negative", rr.subtree_ordinal),
+            )
+        })?;
+        if ordinal == self.root_ordinal {
+            return Err(Diagnostic::error(
+                path,
+                "relation refers to the plan root".to_string(),
+            ));
         }
+        Ok(Reference {
+            subtree: if ordinal > self.root_ordinal {
+                ordinal - 1
+            } else {
+                ordinal
+            },
+        })
     }
 
-    fn decode_project(&self, pr: &proto::ProjectRel) -> Project {
-        let input = pr.input.as_ref().expect("project input must be set");
-        Project {
-            input: Box::new(self.decode_relation(input.as_ref())),
+    fn decode_project(&self, pr: &proto::ProjectRel, path: &str) -> DecodeResult<Project> {
+        Ok(Project {
+            emit: self.decode_emit(&pr.common),
+            input: self.decode_input(&pr.input, path, "input")?,
             expressions: pr
                 .expressions
                 .iter()
-                .map(|expr| self.decode_expression(expr))
-                .collect(),
-        }
+                .enumerate()
+                .map(|(i, expr)| {
+                    self.decode_expression(expr, &format!("{}.expressions[{}]", path, i))
+                })
+                .collect::<DecodeResult<_>>()?,
+        })
+    }
+
+    fn decode_sort_fields(
+        &self,
+        sorts: &[proto::SortField],
+        path: &str,
+    ) -> DecodeResult<Vec<SortField>> {
+        sorts
+            .iter()
+            .enumerate()
+            .map(|(i, sf)| self.decode_sort_field(sf, &format!("{}.sorts[{}]", path, i)))
+            .collect()
+    }
+
+    fn decode_sort_field(&self, sf: &proto::SortField, path: &str) -> DecodeResult<SortField> {
+        let direction = match required(&sf.sort_kind, path, "sort kind")? {
+            proto::sort_field::SortKind::Direction(direction) => {
+                match proto::sort_field::SortDirection::try_from(*direction) {
+                    Ok(proto::sort_field::SortDirection::AscNullsFirst) => {
+                        SortDirection::AscNullsFirst
+                    }
+                    Ok(proto::sort_field::SortDirection::AscNullsLast) => {
+                        SortDirection::AscNullsLast
+                    }
+                    Ok(proto::sort_field::SortDirection::DescNullsFirst) => {
+                        SortDirection::DescNullsFirst
+                    }
+                    Ok(proto::sort_field::SortDirection::DescNullsLast) => {
+                        SortDirection::DescNullsLast
+                    }
+                    Ok(proto::sort_field::SortDirection::Clustered) => SortDirection::Clustered,
+                    Ok(proto::sort_field::SortDirection::Unspecified) | Err(_) => {
+                        return Err(Diagnostic::error(
+                            path,
+                            format!("cannot handle sort direction {}", direction),
+                        ))
+                    }
+                }
+            }
+            proto::sort_field::SortKind::ComparisonFunctionReference(_) => {
+                return Err(Diagnostic::error(
+                    path,
+                    "cannot handle comparison function sorts".to_string(),
+                ))
+            }
+        };
+        Ok(SortField {
+            expression: self.decode_expression(
+                required(&sf.expr, path, "sort expression")?,
+                &format!("{}.expr", path),
+            )?,
+            direction,
+        })
+    }
+
+    fn decode_aggregate_function(
+        &self,
+        af: &proto::AggregateFunction,
+        path: &str,
+    ) -> DecodeResult<AggregateFunctionInvocation> {
+        Ok(AggregateFunctionInvocation {
+            function: self.lookup_function(af.function_reference, path)?,
+            args: self.decode_function_arguments(&af.arguments, path)?,
+            output_type: self.decode_type(
+                required(&af.output_type, path, "output type")?,
+                &format!("{}.output_type", path),
+            )?,
+            phase: match proto::AggregationPhase::try_from(af.phase) {
+                Ok(proto::AggregationPhase::InitialToIntermediate) => {
+                    AggregationPhase::InitialToIntermediate
+                }
+                Ok(proto::AggregationPhase::IntermediateToIntermediate) => {
+                    AggregationPhase::IntermediateToIntermediate
+                }
+                Ok(proto::AggregationPhase::InitialToResult) => AggregationPhase::InitialToResult,
+                Ok(proto::AggregationPhase::IntermediateToResult) => {
+                    AggregationPhase::IntermediateToResult
+                }
+                Ok(proto::AggregationPhase::Unspecified) | Err(_) => {
+                    return Err(Diagnostic::error(
+                        path,
+                        format!("cannot handle aggregation phase {}", af.phase),
+                    ))
+                }
+            },
+            invocation: match proto::aggregate_function::AggregationInvocation::try_from(
+                af.invocation,
+            ) {
+                // unspecified is treated as ALL
+                Ok(proto::aggregate_function::AggregationInvocation::Unspecified)
+                | Ok(proto::aggregate_function::AggregationInvocation::All) => {
+                    AggregationInvocation::All
+                }
+                Ok(proto::aggregate_function::AggregationInvocation::Distinct) => {
+                    AggregationInvocation::Distinct
+                }
+                Err(_) => {
+                    return Err(Diagnostic::error(
+                        path,
+                        format!("cannot handle aggregation invocation {}", af.invocation),
+                    ))
+                }
+            },
+            sorts: self.decode_sort_fields(&af.sorts, path)?,
+        })
+    }
+
+    fn lookup_function(&self, function_reference: u32, path: &str) -> DecodeResult<Function> {
+        self.function_map
+            .get(&FunctionAnchor(function_reference))
+            .cloned()
+            .ok_or_else(|| {
+                Diagnostic::error(
+                    path,
+                    format!("function anchor {} is not declared", function_reference),
+                )
+            })
     }
 
-    fn decode_named_struct(&self, ns: &proto::NamedStruct) -> NamedStruct {
-        NamedStruct {
+    fn decode_named_struct(
+        &self,
+        ns: &proto::NamedStruct,
+        path: &str,
+    ) -> DecodeResult<NamedStruct> {
+        let path = format!("{}.struct", path);
+        Ok(NamedStruct {
             names: ns.names.clone(),
-            types: ns
-                .r#struct
-                .as_ref()
-                .expect("types should be present")
+            types: required(&ns.r#struct, &path, "struct")?
                 .types
                 .iter()
-                .map(|t| self.decode_type(t))
-                .collect(),
-        }
+                .enumerate()
+                .map(|(i, t)| self.decode_type(t, &format!("{}.types[{}]", path, i)))
+                .collect::<DecodeResult<_>>()?,
+        })
     }
 
-    fn decode_expression(&self, expr: &proto::Expression) -> Expression {
-        match expr.rex_type.as_ref().expect("rex_type is set") {
+    fn decode_expression(&self, expr: &proto::Expression, path: &str) -> DecodeResult<Expression> {
+        Ok(match required(&expr.rex_type, path, "rex_type")? {
             proto::expression::RexType::Literal(literal) => {
-                Expression::Literal(self.decode_literal(literal))
-            }
-            proto::expression::RexType::Selection(field_reference) => {
-                Expression::FieldReference(self.decode_field_reference(field_reference.as_ref()))
+                Expression::Literal(self.decode_literal(literal, path)?)
             }
+            proto::expression::RexType::Selection(field_reference) => Expression::FieldReference(
+                self.decode_field_reference(field_reference.as_ref(), path)?,
+            ),
             proto::expression::RexType::ScalarFunction(scalar_function) => {
-                let args: Vec<FunctionArgument> = scalar_function
-                    .arguments
-                    .iter()
-                    .map(|fa| self.decode_function_argument(fa))
-                    .collect();
+                let args = self.decode_function_arguments(&scalar_function.arguments, path)?;
 
-                let function = self
-                    .function_map
-                    .get(&FunctionAnchor(scalar_function.function_reference))
-                    .expect("function was not in plan")
-                    .clone();
+                let function = self.lookup_function(scalar_function.function_reference, path)?;
 
-                let output_type =
-                    self.decode_type(scalar_function.output_type.as_ref().expect("no type"));
+                let output_type = self.decode_type(
+                    required(&scalar_function.output_type, path, "output type")?,
+                    &format!("{}.output_type", path),
+                )?;
 
                 Expression::ScalarFunction(ScalarFunctionInvocation {
                     function,
@@ -153,110 +744,291 @@ impl ProstPlanDecoder {
             // RexType::SwitchExpression(_) => {}
             // RexType::SingularOrList(_) => {}
             // RexType::MultiOrList(_) => {}
-            // RexType::Cast(_) => {}
+            proto::expression::RexType::Cast(cast) => {
+                let input = required(&cast.input, path, "input")?;
+                Expression::Cast(Cast {
+                    r#type: self.decode_type(
+                        required(&cast.r#type, path, "type")?,
+                        &format!("{}.type", path),
+                    )?,
+                    input: Box::new(self.decode_expression(input, &format!("{}.input", path))?),
+                    failure_behavior: decode_failure_behavior(cast.failure_behavior, path)?,
+                })
+            }
             // RexType::Subquery(_) => {}
             // RexType::Nested(_) => {}
             // RexType::Enum(_) => {}
-            _ => panic!("cannot handle expression {:?}", expr),
-        }
+            rex_type => return Err(unsupported(path, "expression", rex_type)),
+        })
     }
 
-    fn decode_function_argument(&self, fa: &proto::FunctionArgument) -> FunctionArgument {
-        let arg_type = fa.arg_type.as_ref().expect("function argument must be set");
-        match arg_type {
+    fn decode_function_arguments(
+        &self,
+        arguments: &[proto::FunctionArgument],
+        path: &str,
+    ) -> DecodeResult<Vec<FunctionArgument>> {
+        arguments
+            .iter()
+            .enumerate()
+            .map(|(i, fa)| self.decode_function_argument(fa, &format!("{}.args[{}]", path, i)))
+            .collect()
+    }
+
+    fn decode_function_argument(
+        &self,
+        fa: &proto::FunctionArgument,
+        path: &str,
+    ) -> DecodeResult<FunctionArgument> {
+        Ok(match required(&fa.arg_type, path, "function argument")? {
             proto::function_argument::ArgType::Enum(enu) => FunctionArgument::Enum(enu.clone()),
             proto::function_argument::ArgType::Type(typ) => {
-                FunctionArgument::Type(self.decode_type(typ))
+                FunctionArgument::Type(self.decode_type(typ, path)?)
             }
             proto::function_argument::ArgType::Value(expr) => {
-                FunctionArgument::Value(self.decode_expression(expr))
+                FunctionArgument::Value(self.decode_expression(expr, path)?)
             }
-        }
+        })
+    }
+
+    fn decode_type(&self, t: &proto::Type, path: &str) -> DecodeResult<Type> {
+        let kind = required(&t.kind, path, "kind")?;
+        let decoded = self.decode_type_kind(kind, path)?;
+        self.check_type_variation(type_variation_reference(kind), &decoded, path)?;
+        Ok(decoded)
     }
 
-    fn decode_type(&self, t: &proto::Type) -> Type {
-        let kind = t.kind.as_ref().expect("kind must be set");
-        match kind {
-            // Kind::Bool(_) => {}
-            // Kind::I8(_) => {}
-            // Kind::I16(_) => {}
+    fn decode_type_kind(&self, kind: &proto::r#type::Kind, path: &str) -> DecodeResult<Type> {
+        let nullable = |n: i32| decode_nullability(n, path);
+        Ok(match kind {
+            substrait::proto::r#type::Kind::Bool(v) => Type::Bool {
+                nullable: nullable(v.nullability)?,
+            },
+            substrait::proto::r#type::Kind::I8(v) => Type::I8 {
+                nullable: nullable(v.nullability)?,
+            },
+            substrait::proto::r#type::Kind::I16(v) => Type::I16 {
+                nullable: nullable(v.nullability)?,
+            },
             substrait::proto::r#type::Kind::I32(v) => Type::I32 {
-                nullable: types::nullability(v.nullability),
+                nullable: nullable(v.nullability)?,
             },
             substrait::proto::r#type::Kind::I64(v) => Type::I64 {
-                nullable: types::nullability(v.nullability),
+                nullable: nullable(v.nullability)?,
+            },
+            substrait::proto::r#type::Kind::Fp32(v) => Type::FP32 {
+                nullable: nullable(v.nullability)?,
             },
-            // Kind::Fp32(_) => {}
             substrait::proto::r#type::Kind::Fp64(v) => Type::FP64 {
-                nullable: types::nullability(v.nullability),
+                nullable: nullable(v.nullability)?,
             },
             substrait::proto::r#type::Kind::String(v) => Type::String {
-                nullable: types::nullability(v.nullability),
-            },
-            // Kind::Binary(_) => {}
-            // Kind::Timestamp(_) => {}
-            // Kind::Date(_) => {}
-            // Kind::Time(_) => {}
-            // Kind::IntervalYear(_) => {}
-            // Kind::IntervalDay(_) => {}
-            // Kind::TimestampTz(_) => {}
-            // Kind::Uuid(_) => {}
-            // Kind::FixedChar(_) => {}
-            // Kind::Varchar(_) => {}
-            // Kind::FixedBinary(_) => {}
-            // Kind::Decimal(_) => {}
-            // Kind::Struct(_) => {}
-            // Kind::List(_) => {}
-            // Kind::Map(_) => {}
-            // Kind::UserDefined(_) => {}
+                nullable: nullable(v.nullability)?,
+            },
+            substrait::proto::r#type::Kind::Binary(v) => Type::Binary {
+                nullable: nullable(v.nullability)?,
+            },
+            #[allow(deprecated)]
+            substrait::proto::r#type::Kind::Timestamp(v) => Type::Timestamp {
+                nullable: nullable(v.nullability)?,
+            },
+            substrait::proto::r#type::Kind::Date(v) => Type::Date {
+                nullable: nullable(v.nullability)?,
+            },
+            substrait::proto::r#type::Kind::Time(v) => Type::Time {
+                nullable: nullable(v.nullability)?,
+            },
+            substrait::proto::r#type::Kind::IntervalYear(v) => Type::IntervalYear {
+                nullable: nullable(v.nullability)?,
+            },
+            substrait::proto::r#type::Kind::IntervalDay(v) => Type::IntervalDay {
+                nullable: nullable(v.nullability)?,
+            },
+            #[allow(deprecated)]
+            substrait::proto::r#type::Kind::TimestampTz(v) => Type::TimestampTz {
+                nullable: nullable(v.nullability)?,
+            },
+            substrait::proto::r#type::Kind::Uuid(v) => Type::UUID {
+                nullable: nullable(v.nullability)?,
+            },
+            substrait::proto::r#type::Kind::FixedChar(v) => Type::FixedChar {
+                nullable: nullable(v.nullability)?,
+                length: v.length,
+            },
+            substrait::proto::r#type::Kind::Varchar(v) => Type::VarChar {
+                nullable: nullable(v.nullability)?,
+                length: v.length,
+            },
+            substrait::proto::r#type::Kind::FixedBinary(v) => Type::FixedBinary {
+                nullable: nullable(v.nullability)?,
+                length: v.length,
+            },
+            substrait::proto::r#type::Kind::Decimal(v) => Type::Decimal {
+                nullable: nullable(v.nullability)?,
+                precision: v.precision,
+                scale: v.scale,
+            },
+            substrait::proto::r#type::Kind::Struct(v) => Type::Struct {
+                nullable: nullable(v.nullability)?,
+                types: v
+                    .types
+                    .iter()
+                    .enumerate()
+                    .map(|(i, t)| self.decode_type(t, &format!("{}.struct.types[{}]", path, i)))
+                    .collect::<DecodeResult<_>>()?,
+            },
+            substrait::proto::r#type::Kind::List(v) => Type::List {
+                nullable: nullable(v.nullability)?,
+                r#type: Box::new(self.decode_type(
+                    required(&v.r#type, path, "list type")?,
+                    &format!("{}.list.type", path),
+                )?),
+            },
+            substrait::proto::r#type::Kind::Map(v) => Type::Map {
+                nullable: nullable(v.nullability)?,
+                key: Box::new(self.decode_type(
+                    required(&v.key, path, "map key")?,
+                    &format!("{}.map.key", path),
+                )?),
+                value: Box::new(self.decode_type(
+                    required(&v.value, path, "map value")?,
+                    &format!("{}.map.value", path),
+                )?),
+            },
+            substrait::proto::r#type::Kind::UserDefined(v) => {
+                let (extension, name) = self.lookup_type(v.type_reference, path)?;
+                let parameters = v
+                    .type_parameters
+                    .iter()
+                    .enumerate()
+                    .map(|(i, p)| {
+                        let path = format!("{}.user_defined.type_parameters[{}]", path, i);
+                        self.decode_type_parameter(p, &path)
+                    })
+                    .collect::<DecodeResult<Vec<_>>>()?;
+                if let Some(definition) = self
+                    .registry
+                    .and_then(|registry| registry.get(&extension.0))
+                    .and_then(|extensions| extensions.type_definition(&name))
+                {
+                    definition
+                        .check(&parameters)
+                        .map_err(|e| Diagnostic::error(path, e))?;
+                }
+                Type::UserDefined {
+                    nullable: nullable(v.nullability)?,
+                    extension,
+                    name,
+                    parameters,
+                }
+            }
             // Kind::UserDefinedTypeReference(_) => {}
-            _ => panic!("cannot handle type: {:?}", kind),
+            kind => return Err(unsupported(path, "type", kind)),
+        })
+    }
+
+    fn lookup_type(&self, type_reference: u32, path: &str) -> DecodeResult<(URI, String)> {
+        self.type_map
+            .get(&TypeAnchor(type_reference))
+            .cloned()
+            .ok_or_else(|| {
+                Diagnostic::error(
+                    path,
+                    format!("type anchor {} is not declared", type_reference),
+                )
+            })
+    }
+
+    // Variations refine the physical representation of a type, so the decoded type is the
+    // type without its variation. The variation must still be declared, and if its extension
+    // is registered, be a variation of the decoded type.
+    fn check_type_variation(&self, reference: u32, t: &Type, path: &str) -> DecodeResult<()> {
+        // 0 is the system-preferred variation and does not refer to a declaration
+        if reference == 0 {
+            return Ok(());
+        }
+        let (extension, name) = self
+            .type_variation_map
+            .get(&TypeVariationAnchor(reference))
+            .ok_or_else(|| {
+                Diagnostic::error(
+                    path,
+                    format!("type variation anchor {} is not declared", reference),
+                )
+            })?;
+        let variation = self
+            .registry
+            .and_then(|registry| registry.get(&extension.0))
+            .and_then(|extensions| extensions.type_variation(name));
+        match variation {
+            Some(variation) if !variation.parent.is_any() && variation.parent.name != t.name() => {
+                Err(Diagnostic::error(
+                    path,
+                    format!(
+                        "type variation {} applies to {}, not to {}",
+                        name, variation.parent.name, t
+                    ),
+                ))
+            }
+            _ => Ok(()),
         }
     }
 
-    fn decode_field_reference(&self, fr: &proto::expression::FieldReference) -> FieldReference {
-        let root_type = fr.root_type.as_ref().expect("root_type must be present");
-        match root_type {
+    fn decode_type_parameter(
+        &self,
+        p: &proto::r#type::Parameter,
+        path: &str,
+    ) -> DecodeResult<TypeParameter> {
+        Ok(match required(&p.parameter, path, "parameter")? {
+            proto::r#type::parameter::Parameter::Null(_) => TypeParameter::Null,
+            proto::r#type::parameter::Parameter::DataType(t) => {
+                TypeParameter::DataType(self.decode_type(t, path)?)
+            }
+            proto::r#type::parameter::Parameter::Boolean(b) => TypeParameter::Boolean(*b),
+            proto::r#type::parameter::Parameter::Integer(i) => TypeParameter::Integer(*i),
+            proto::r#type::parameter::Parameter::Enum(e) => TypeParameter::Enum(e.clone()),
+            proto::r#type::parameter::Parameter::String(s) => TypeParameter::String(s.clone()),
+        })
+    }
+
+    fn decode_field_reference(
+        &self,
+        fr: &proto::expression::FieldReference,
+        path: &str,
+    ) -> DecodeResult<FieldReference> {
+        match required(&fr.root_type, path, "root_type")? {
             // proto::expression::field_reference::RootType::Expression(_) => {}
             proto::expression::field_reference::RootType::RootReference(_) => {}
             // proto::expression::field_reference::RootType::OuterReference(_) => {}
-            _ => panic!("root_type must be root reference, found: {:?}", root_type),
+            root_type => return Err(unsupported(path, "field reference root", root_type)),
         }
 
-        match fr.reference_type.as_ref().expect("reference_type is set") {
+        match required(&fr.reference_type, path, "reference_type")? {
             proto::expression::field_reference::ReferenceType::DirectReference(
                 reference_segment,
-            ) => {
-                let ref_type = reference_segment
-                    .reference_type
-                    .as_ref()
-                    .expect("reference_type must be set");
-                match ref_type {
-                    proto::expression::reference_segment::ReferenceType::MapKey(_) => {
-                        panic!("cannot handle map key")
-                    }
-                    proto::expression::reference_segment::ReferenceType::StructField(sf) => {
-                        assert!(sf.child.is_none(), "cannot handle nested reference types");
-                        FieldReference { field: sf.field }
-                    }
-                    proto::expression::reference_segment::ReferenceType::ListElement(_) => {
-                        panic!("Cannot handle list element")
-                    }
+            ) => match required(&reference_segment.reference_type, path, "reference_type")? {
+                proto::expression::reference_segment::ReferenceType::StructField(sf)
+                    if sf.child.is_none() =>
+                {
+                    Ok(FieldReference { field: sf.field })
                 }
-            }
-            proto::expression::field_reference::ReferenceType::MaskedReference(_) => {
-                panic!("cannot handle mask references")
-            }
+                proto::expression::reference_segment::ReferenceType::StructField(_) => Err(
+                    Diagnostic::error(path, "cannot handle nested field references".to_string()),
+                ),
+                ref_type => Err(unsupported(path, "reference segment", ref_type)),
+            },
+            proto::expression::field_reference::ReferenceType::MaskedReference(_) => Err(
+                Diagnostic::error(path, "cannot handle mask references".to_string()),
+            ),
         }
     }
 
-    fn decode_literal(&self, value: &proto::expression::Literal) -> Literal {
+    fn decode_literal(
+        &self,
+        value: &proto::expression::Literal,
+        path: &str,
+    ) -> DecodeResult<Literal> {
         let nullable = value.nullable;
-        let literal_type = value
-            .literal_type
-            .as_ref()
-            .expect("literal_type must be set");
-        match literal_type {
+        Ok(match required(&value.literal_type, path, "literal_type")? {
             proto::expression::literal::LiteralType::Boolean(v) => Literal::Bool(Bool {
                 value: *v,
                 nullable,
@@ -271,77 +1043,762 @@ impl ProstPlanDecoder {
                 value: *v,
                 nullable,
             }),
-            // LiteralType::Fp32(_) => {}
-            // LiteralType::Fp64(_) => {}
-            // LiteralType::String(_) => {}
-            // LiteralType::Binary(_) => {}
-            // LiteralType::Timestamp(_) => {}
-            // LiteralType::Date(_) => {}
+            proto::expression::literal::LiteralType::Fp32(v) => Literal::FP32(FP32 {
+                value: *v,
+                nullable,
+            }),
+            proto::expression::literal::LiteralType::Fp64(v) => Literal::FP64(FP64 {
+                value: *v,
+                nullable,
+            }),
+            proto::expression::literal::LiteralType::String(v) => Literal::String(Str {
+                value: v.clone(),
+                nullable,
+            }),
+            proto::expression::literal::LiteralType::Binary(v) => Literal::Binary(Binary {
+                value: v.clone(),
+                nullable,
+            }),
+            #[allow(deprecated)]
+            proto::expression::literal::LiteralType::Timestamp(v) => {
+                Literal::Timestamp(Timestamp {
+                    value: *v,
+                    nullable,
+                })
+            }
+            proto::expression::literal::LiteralType::Date(v) => Literal::Date(Date {
+                value: *v,
+                nullable,
+            }),
             // LiteralType::Time(_) => {}
             // LiteralType::IntervalYearToMonth(_) => {}
             // LiteralType::IntervalDayToSecond(_) => {}
             // LiteralType::FixedChar(_) => {}
             // LiteralType::VarChar(_) => {}
             // LiteralType::FixedBinary(_) => {}
-            // LiteralType::Decimal(_) => {}
+            proto::expression::literal::LiteralType::Decimal(v) => {
+                // the value is a 128-bit little-endian two's complement integer
+                let Ok(bytes) = <[u8; 16]>::try_from(v.value.as_slice()) else {
+                    return Err(Diagnostic::error(
+                        path,
+                        format!("decimal value has {} bytes, not 16", v.value.len()),
+                    ));
+                };
+                // the scale bounds the digits displayed, so it is checked before any use
+                if !(1..=38).contains(&v.precision) || !(0..=v.precision).contains(&v.scale) {
+                    return Err(Diagnostic::error(
+                        path,
+                        format!(
+                            "decimal has precision {} and scale {}, but the precision must be \
+                             between 1 and 38 and the scale between 0 and the precision",
+                            v.precision, v.scale
+                        ),
+                    ));
+                }
+                Literal::Decimal(Decimal {
+                    value: i128::from_le_bytes(bytes),
+                    precision: v.precision,
+                    scale: v.scale,
+                    nullable,
+                })
+            }
             // LiteralType::Struct(_) => {}
             // LiteralType::Map(_) => {}
             // LiteralType::TimestampTz(_) => {}
             // LiteralType::Uuid(_) => {}
-            // LiteralType::Null(_) => {}
+            proto::expression::literal::LiteralType::Null(t) => {
+                let t = self.decode_type(t, &format!("{}.null", path))?;
+                if !t.nullable() {
+                    return Err(Diagnostic::error(
+                        path,
+                        format!("null literal has non-nullable type {}", t),
+                    ));
+                }
+                Literal::Null(t)
+            }
             // LiteralType::List(_) => {}
             // LiteralType::EmptyList(_) => {}
             // LiteralType::EmptyMap(_) => {}
             // LiteralType::UserDefined(_) => {}
-            _ => panic!("cannot handle literal_type: {:?}", literal_type),
-        }
+            literal_type => return Err(unsupported(path, "literal", literal_type)),
+        })
     }
 
-    fn gather_extensions(&mut self, p: &proto::Plan) {
-        p.extension_uris.iter().for_each(|extension_uri| {
+    fn gather_extensions(&mut self, p: &proto::Plan) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+        for (i, extension_uri) in p.extension_uris.iter().enumerate() {
+            let path = format!("extension_uris[{}]", i);
             let anchor = ExtensionAnchor(extension_uri.extension_uri_anchor);
             let uri = URI(extension_uri.uri.clone());
+            if let Some(registry) = self.registry {
+                if !registry.contains(&uri.0) {
+                    diagnostics.push(Diagnostic::error(
+                        &path,
+                        format!("extension {} is not in the registry", uri.0),
+                    ));
+                }
+            }
             if self.extension_uri_map.contains_key(&anchor) {
-                panic!("anchor {:?} is defined multiple times", anchor)
+                diagnostics.push(Diagnostic::error(
+                    &path,
+                    format!("anchor {} is defined multiple times", anchor.0),
+                ));
             }
             self.extension_uri_map.insert(anchor, uri);
-        });
+        }
 
-        p.extensions.iter().for_each(|extension| {
-            match extension
-                .mapping_type
-                .as_ref()
-                .expect("mapping type must be set")
-            {
-                MappingType::ExtensionType(_) => {
-                    panic!("cannot handle type extensions")
-                }
-                MappingType::ExtensionTypeVariation(_) => {
-                    panic!("cannot handle type variation extensions")
+        for (i, extension) in p.extensions.iter().enumerate() {
+            let path = format!("extensions[{}]", i);
+            let result = match required(&extension.mapping_type, &path, "mapping type") {
+                Ok(MappingType::ExtensionType(et)) => self.gather_type_mapping(et, &path),
+                Ok(MappingType::ExtensionTypeVariation(etv)) => {
+                    self.gather_type_variation_mapping(etv, &path)
                 }
-                MappingType::ExtensionFunction(ef) => {
-                    self.gather_function_mapping(&ef);
-                }
-            }
-        })
+                Ok(MappingType::ExtensionFunction(ef)) => self.gather_function_mapping(ef, &path),
+                Err(diagnostic) => Err(diagnostic),
+            };
+            diagnostics.extend(result.err());
+        }
+        diagnostics
     }
 
-    fn gather_function_mapping(&mut self, ef: &ExtensionFunction) {
-        let extension = self
-            .extension_uri_map
-            .get(&ExtensionAnchor(ef.extension_uri_reference))
-            .expect("missing extension mapping")
-            .clone();
+    fn gather_function_mapping(&mut self, ef: &ExtensionFunction, path: &str) -> DecodeResult<()> {
+        let extension = self.lookup_extension(ef.extension_uri_reference, path)?;
         let function_anchor = FunctionAnchor(ef.function_anchor);
         let signature = FunctionSignature(ef.name.clone());
         if self.function_map.contains_key(&function_anchor) {
-            panic!("function anchor {:?} is defined multiple times", signature)
+            return Err(Diagnostic::error(
+                path,
+                format!(
+                    "function anchor {} is defined multiple times",
+                    ef.function_anchor
+                ),
+            ));
         }
 
+        let variant = match self
+            .registry
+            .and_then(|registry| registry.get(&extension.0))
+        {
+            Some(extensions) => {
+                let resolved =
+                    Signature::parse(&signature.0).and_then(|parsed| extensions.resolve(&parsed));
+                resolved.map_err(|e| {
+                    Diagnostic::error(
+                        path,
+                        format!(
+                            "cannot resolve function {} in extension {}: {}",
+                            signature.0, extension.0, e
+                        ),
+                    )
+                })?
+            }
+            // an unregistered extension has already been reported with its URI
+            None => None,
+        };
+
         let function = Function {
             signature,
             extension,
+            variant,
         };
         self.function_map.insert(function_anchor, function);
+        Ok(())
+    }
+
+    fn gather_type_mapping(&mut self, et: &ExtensionType, path: &str) -> DecodeResult<()> {
+        let extension = self.lookup_extension(et.extension_uri_reference, path)?;
+        let type_anchor = TypeAnchor(et.type_anchor);
+        if self.type_map.contains_key(&type_anchor) {
+            return Err(Diagnostic::error(
+                path,
+                format!("type anchor {} is defined multiple times", et.type_anchor),
+            ));
+        }
+        if let Some(extensions) = self
+            .registry
+            .and_then(|registry| registry.get(&extension.0))
+        {
+            if extensions.type_definition(&et.name).is_none() {
+                return Err(Diagnostic::error(
+                    path,
+                    format!(
+                        "type {} is not defined in extension {}",
+                        et.name, extension.0
+                    ),
+                ));
+            }
+        }
+        self.type_map
+            .insert(type_anchor, (extension, et.name.clone()));
+        Ok(())
+    }
+
+    fn gather_type_variation_mapping(
+        &mut self,
+        etv: &ExtensionTypeVariation,
+        path: &str,
+    ) -> DecodeResult<()> {
+        let extension = self.lookup_extension(etv.extension_uri_reference, path)?;
+        let anchor = TypeVariationAnchor(etv.type_variation_anchor);
+        if self.type_variation_map.contains_key(&anchor) {
+            return Err(Diagnostic::error(
+                path,
+                format!(
+                    "type variation anchor {} is defined multiple times",
+                    etv.type_variation_anchor
+                ),
+            ));
+        }
+        if let Some(extensions) = self
+            .registry
+            .and_then(|registry| registry.get(&extension.0))
+        {
+            if extensions.type_variation(&etv.name).is_none() {
+                return Err(Diagnostic::error(
+                    path,
+                    format!(
+                        "type variation {} is not defined in extension {}",
+                        etv.name, extension.0
+                    ),
+                ));
+            }
+        }
+        self.type_variation_map
+            .insert(anchor, (extension, etv.name.clone()));
+        Ok(())
+    }
+
+    fn lookup_extension(&self, extension_uri_reference: u32, path: &str) -> DecodeResult<URI> {
+        self.extension_uri_map
+            .get(&ExtensionAnchor(extension_uri_reference))
+            .cloned()
+            .ok_or_else(|| {
+                Diagnostic::error(
+                    path,
+                    format!(
+                        "extension uri anchor {} is not declared",
+                        extension_uri_reference
+                    ),
+                )
+            })
+    }
+}
+
+fn required<'b, T>(value: &'b Option<T>, path: &str, what: &str) -> DecodeResult<&'b T> {
+    value
+        .as_ref()
+        .ok_or_else(|| Diagnostic::error(path, format!("{} must be set", what)))
+}
+
+fn decode_failure_behavior(failure_behavior: i32, path: &str) -> DecodeResult<FailureBehavior> {
+    use proto::expression::cast::FailureBehavior as ProtoFailureBehavior;
+    Ok(match ProtoFailureBehavior::try_from(failure_behavior) {
+        Ok(ProtoFailureBehavior::Unspecified) => FailureBehavior::Unspecified,
+        Ok(ProtoFailureBehavior::ReturnNull) => FailureBehavior::ReturnNull,
+        Ok(ProtoFailureBehavior::ThrowException) => FailureBehavior::ThrowException,
+        Err(_) => {
+            return Err(Diagnostic::error(
+                path,
+                format!("cannot handle cast failure behavior {}", failure_behavior),
+            ))
+        }
+    })
+}
+
+// Reports a oneof case the decoder has no representation for, named by its variant
+fn unsupported(path: &str, what: &str, value: &impl fmt::Debug) -> Diagnostic {
+    let debug = format!("{:?}", value);
+    let variant = debug.split(['(', ' ', '{']).next().unwrap_or_default();
+    Diagnostic::error(path, format!("cannot handle {} {}", what, variant))
+}
+
+fn type_variation_reference(kind: &proto::r#type::Kind) -> u32 {
+    use proto::r#type::Kind;
+    match kind {
+        Kind::Bool(v) => v.type_variation_reference,
+        Kind::I8(v) => v.type_variation_reference,
+        Kind::I16(v) => v.type_variation_reference,
+        Kind::I32(v) => v.type_variation_reference,
+        Kind::I64(v) => v.type_variation_reference,
+        Kind::Fp32(v) => v.type_variation_reference,
+        Kind::Fp64(v) => v.type_variation_reference,
+        Kind::String(v) => v.type_variation_reference,
+        Kind::Binary(v) => v.type_variation_reference,
+        #[allow(deprecated)]
+        Kind::Timestamp(v) => v.type_variation_reference,
+        Kind::Date(v) => v.type_variation_reference,
+        Kind::Time(v) => v.type_variation_reference,
+        Kind::IntervalYear(v) => v.type_variation_reference,
+        Kind::IntervalDay(v) => v.type_variation_reference,
+        #[allow(deprecated)]
+        Kind::TimestampTz(v) => v.type_variation_reference,
+        Kind::Uuid(v) => v.type_variation_reference,
+        Kind::FixedChar(v) => v.type_variation_reference,
+        Kind::Varchar(v) => v.type_variation_reference,
+        Kind::FixedBinary(v) => v.type_variation_reference,
+        Kind::Decimal(v) => v.type_variation_reference,
+        Kind::Struct(v) => v.type_variation_reference,
+        Kind::List(v) => v.type_variation_reference,
+        Kind::Map(v) => v.type_variation_reference,
+        Kind::UserDefined(v) => v.type_variation_reference,
+        // the remaining kinds are not decoded
+        _ => 0,
+    }
+}
+
+fn decode_nullability(nullability: i32, path: &str) -> DecodeResult<bool> {
+    match nullability {
+        n if n == types::proto_nullability(true) => Ok(true),
+        n if n == types::proto_nullability(false) => Ok(false),
+        0 => Err(Diagnostic::error(
+            path,
+            "nullability must be specified".to_string(),
+        )),
+        n => Err(Diagnostic::error(
+            path,
+            format!("unknown nullability {}", n),
+        )),
+    }
+}
+
+fn decode_join_type(join_type: i32, path: &str) -> DecodeResult<JoinType> {
+    use proto::join_rel::JoinType as ProtoJoinType;
+    Ok(match ProtoJoinType::try_from(join_type) {
+        Ok(ProtoJoinType::Inner) => JoinType::Inner,
+        Ok(ProtoJoinType::Outer) => JoinType::Outer,
+        Ok(ProtoJoinType::Left) => JoinType::Left,
+        Ok(ProtoJoinType::Right) => JoinType::Right,
+        Ok(ProtoJoinType::Semi) => JoinType::LeftSemi,
+        Ok(ProtoJoinType::Anti) => JoinType::LeftAnti,
+        Ok(ProtoJoinType::Single) => JoinType::LeftSingle,
+        Ok(ProtoJoinType::Unspecified) | Err(_) => match join_type {
+            JOIN_TYPE_RIGHT_SEMI => JoinType::RightSemi,
+            JOIN_TYPE_RIGHT_ANTI => JoinType::RightAnti,
+            JOIN_TYPE_RIGHT_SINGLE => JoinType::RightSingle,
+            JOIN_TYPE_LEFT_MARK => JoinType::LeftMark,
+            JOIN_TYPE_RIGHT_MARK => JoinType::RightMark,
+            t => {
+                return Err(Diagnostic::error(
+                    path,
+                    format!("cannot handle join type {}", t),
+                ))
+            }
+        },
+    })
+}
+
+// MergeJoinRel numbers its join types like HashJoinRel
+fn decode_keyed_join_type(join_type: i32, path: &str) -> DecodeResult<JoinType> {
+    use proto::hash_join_rel::JoinType as ProtoJoinType;
+    Ok(match ProtoJoinType::try_from(join_type) {
+        Ok(ProtoJoinType::Inner) => JoinType::Inner,
+        Ok(ProtoJoinType::Outer) => JoinType::Outer,
+        Ok(ProtoJoinType::Left) => JoinType::Left,
+        Ok(ProtoJoinType::Right) => JoinType::Right,
+        Ok(ProtoJoinType::LeftSemi) => JoinType::LeftSemi,
+        Ok(ProtoJoinType::RightSemi) => JoinType::RightSemi,
+        Ok(ProtoJoinType::LeftAnti) => JoinType::LeftAnti,
+        Ok(ProtoJoinType::RightAnti) => JoinType::RightAnti,
+        Ok(ProtoJoinType::Unspecified) | Err(_) => match join_type {
+            KEYED_JOIN_TYPE_LEFT_SINGLE => JoinType::LeftSingle,
+            KEYED_JOIN_TYPE_RIGHT_SINGLE => JoinType::RightSingle,
+            KEYED_JOIN_TYPE_LEFT_MARK => JoinType::LeftMark,
+            KEYED_JOIN_TYPE_RIGHT_MARK => JoinType::RightMark,
+            t => {
+                return Err(Diagnostic::error(
+                    path,
+                    format!("cannot handle keyed join type {}", t),
+                ))
+            }
+        },
+    })
+}
+
+fn decode_file_or_files(item: &local_files::FileOrFiles, path: &str) -> DecodeResult<FileOrFiles> {
+    Ok(FileOrFiles {
+        path: match required(&item.path_type, path, "path type")? {
+            file_or_files::PathType::UriPath(uri) => FilePath::Uri(uri.clone()),
+            file_or_files::PathType::UriPathGlob(glob) => FilePath::Glob(glob.clone()),
+            file_or_files::PathType::UriFile(uri) => FilePath::File(uri.clone()),
+            file_or_files::PathType::UriFolder(uri) => FilePath::Folder(uri.clone()),
+        },
+        format: match required(&item.file_format, path, "file format")? {
+            file_or_files::FileFormat::Parquet(_) => FileFormat::Parquet,
+            file_or_files::FileFormat::Arrow(_) => FileFormat::Arrow,
+            file_or_files::FileFormat::Orc(_) => FileFormat::Orc,
+            file_or_files::FileFormat::Dwrf(_) => FileFormat::Dwrf,
+            file_or_files::FileFormat::Extension(any)
+                if any.type_url == DELIMITED_TEXT_TYPE_URL =>
+            {
+                let options =
+                    DelimiterSeparatedTextReadOptions::decode(&any.value[..]).map_err(|e| {
+                        Diagnostic::error(path, format!("invalid delimited text options: {}", e))
+                    })?;
+                FileFormat::DelimitedText(DelimitedTextOptions {
+                    field_delimiter: options.field_delimiter,
+                    max_line_size: options.max_line_size,
+                    quote: options.quote,
+                    header_lines_to_skip: options.header_lines_to_skip,
+                    escape: options.escape,
+                    value_treated_as_null: options.value_treated_as_null,
+                })
+            }
+            ff => return Err(unsupported(path, "file format", ff)),
+        },
+        partition_index: item.partition_index,
+        start: item.start,
+        length: item.length,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder::encode_prost_plan;
+    use crate::extensions::signature::VariantReference;
+
+    const ARITHMETIC: &str = "/functions_arithmetic.yaml";
+
+    // A project of `<signature>($0, $1)` over a read of two i32 columns
+    fn plan(signature: &str) -> proto::Plan {
+        let field =
+            |field| FunctionArgument::Value(Expression::FieldReference(FieldReference { field }));
+        let plan = Plan {
+            root: Box::new(Rel::Project(Project {
+                emit: Emit::Direct,
+                input: Box::new(Rel::Read(Read {
+                    emit: Emit::Direct,
+                    base_schema: NamedStruct {
+                        names: vec!["a".to_string(), "b".to_string()],
+                        types: vec![Type::I32 { nullable: false }, Type::I32 { nullable: false }],
+                    },
+                    read_type: ReadType::NamedTable {
+                        names: vec!["t".to_string()],
+                    },
+                })),
+                expressions: vec![Expression::ScalarFunction(ScalarFunctionInvocation {
+                    function: Function {
+                        signature: FunctionSignature(signature.to_string()),
+                        extension: URI(ARITHMETIC.to_string()),
+                        variant: None,
+                    },
+                    args: vec![field(0), field(1)],
+                    output_type: Type::I32 { nullable: false },
+                })],
+            })),
+            names: vec![],
+            subtrees: vec![],
+        };
+        encode_prost_plan(&plan)
+    }
+
+    fn messages(diagnostics: &[Diagnostic]) -> Vec<String> {
+        diagnostics.iter().map(|d| d.to_string()).collect()
+    }
+
+    #[test]
+    fn resolves_declared_functions() {
+        let registry = ExtensionRegistry::standard();
+        let decoded = decode_prost_plan_with_registry(&plan("add:i32_i32"), &registry).unwrap();
+        let function = match decoded.root.as_ref() {
+            Rel::Project(project) => match &project.expressions[0] {
+                Expression::ScalarFunction(invocation) => &invocation.function,
+                e => panic!("expected a scalar function, found {:?}", e),
+            },
+            rel => panic!("expected a project, found {:?}", rel),
+        };
+        assert!(matches!(
+            function.variant,
+            Some(VariantReference::Scalar(_))
+        ));
+
+        // without a registry functions are kept unresolved
+        let decoded = decode_prost_plan(&plan("add:i32_i32")).unwrap();
+        match decoded.root.as_ref() {
+            Rel::Project(project) => assert!(matches!(
+                &project.expressions[0],
+                Expression::ScalarFunction(ScalarFunctionInvocation {
+                    function: Function { variant: None, .. },
+                    ..
+                })
+            )),
+            rel => panic!("expected a project, found {:?}", rel),
+        }
+    }
+
+    #[test]
+    fn reports_unknown_signatures() {
+        let registry = ExtensionRegistry::standard();
+        let diagnostics =
+            decode_prost_plan_with_registry(&plan("add:int_int"), &registry).unwrap_err();
+        assert_eq!(
+            messages(&diagnostics),
+            vec![
+                "error at extensions[0]: cannot resolve function add:int_int in extension \
+                 /functions_arithmetic.yaml: unknown argument type int in function signature \
+                 add:int_int"
+            ]
+        );
+
+        let diagnostics =
+            decode_prost_plan_with_registry(&plan("frobnicate:i32_i32"), &registry).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(
+            diagnostics[0]
+                .message
+                .ends_with("function frobnicate is not defined"),
+            "{}",
+            diagnostics[0]
+        );
+    }
+
+    #[test]
+    fn reports_signatures_without_matching_variant() {
+        let registry = ExtensionRegistry::standard();
+        let diagnostics =
+            decode_prost_plan_with_registry(&plan("add:bool_bool"), &registry).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].path, "extensions[0]");
+        assert!(
+            diagnostics[0]
+                .message
+                .contains("no variant matches add:bool_bool"),
+            "{}",
+            diagnostics[0]
+        );
+    }
+
+    #[test]
+    fn reports_unregistered_extensions() {
+        let mut proto_plan = plan("add:i32_i32");
+        proto_plan.extension_uris[0].uri = "/functions_unknown.yaml".to_string();
+        let diagnostics =
+            decode_prost_plan_with_registry(&proto_plan, &ExtensionRegistry::standard())
+                .unwrap_err();
+        assert_eq!(
+            messages(&diagnostics),
+            vec!["error at extension_uris[0]: extension /functions_unknown.yaml is not in the registry"]
+        );
+    }
+
+    const TYPES: &str = "/types.yaml";
+
+    const TYPE_EXTENSIONS: &str = r#"
+    types:
+      - name: point
+        parameters:
+          - { name: dimensions, type: integer, min: 2, max: 3 }
+    type_variations:
+      - name: dictionary
+        parent: string
+    "#;
+
+    fn types_registry() -> ExtensionRegistry {
+        let mut registry = ExtensionRegistry::new();
+        registry.register_yaml(TYPES, TYPE_EXTENSIONS).unwrap();
+        registry
+    }
+
+    // A read of a point column with the given dimensions and a string column
+    fn point_plan(dimensions: i64) -> proto::Plan {
+        encode_prost_plan(&Plan {
+            root: Box::new(Rel::Read(Read {
+                emit: Emit::Direct,
+                base_schema: NamedStruct {
+                    names: vec!["p".to_string(), "s".to_string()],
+                    types: vec![
+                        Type::UserDefined {
+                            nullable: false,
+                            extension: URI(TYPES.to_string()),
+                            name: "point".to_string(),
+                            parameters: vec![TypeParameter::Integer(dimensions)],
+                        },
+                        Type::String { nullable: false },
+                    ],
+                },
+                read_type: ReadType::NamedTable {
+                    names: vec!["t".to_string()],
+                },
+            })),
+            names: vec![],
+            subtrees: vec![],
+        })
+    }
+
+    // Declares the named type variation and applies it to the column of the point plan
+    fn with_variation(mut proto_plan: proto::Plan, name: &str, column: usize) -> proto::Plan {
+        proto_plan
+            .extensions
+            .push(proto::extensions::SimpleExtensionDeclaration {
+                mapping_type: Some(MappingType::ExtensionTypeVariation(
+                    ExtensionTypeVariation {
+                        extension_uri_reference: proto_plan.extension_uris[0].extension_uri_anchor,
+                        type_variation_anchor: 1,
+                        name: name.to_string(),
+                    },
+                )),
+            });
+        let read = match &mut proto_plan.relations[0].rel_type {
+            Some(proto::plan_rel::RelType::Rel(proto::Rel {
+                rel_type: Some(proto::rel::RelType::Read(read)),
+            })) => read,
+            rel => panic!("expected a read, found {:?}", rel),
+        };
+        let types = &mut read
+            .base_schema
+            .as_mut()
+            .unwrap()
+            .r#struct
+            .as_mut()
+            .unwrap()
+            .types;
+        match &mut types[column].kind {
+            Some(proto::r#type::Kind::String(s)) => s.type_variation_reference = 1,
+            Some(proto::r#type::Kind::UserDefined(u)) => u.type_variation_reference = 1,
+            kind => panic!("unexpected type {:?}", kind),
+        }
+        proto_plan
+    }
+
+    #[test]
+    fn checks_user_defined_type_parameters() {
+        let registry = types_registry();
+        assert!(decode_prost_plan_with_registry(&point_plan(3), &registry).is_ok());
+
+        let diagnostics = decode_prost_plan_with_registry(&point_plan(4), &registry).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "parameter 0 of type point: 4 is outside of the range [Some(2), Some(3)]"
+        );
+
+        // without a registry the parameters cannot be checked
+        assert!(decode_prost_plan(&point_plan(4)).is_ok());
+    }
+
+    #[test]
+    fn reports_undefined_types() {
+        let mut proto_plan = point_plan(2);
+        match &mut proto_plan.extensions[0].mapping_type {
+            Some(MappingType::ExtensionType(et)) => et.name = "polygon".to_string(),
+            mapping => panic!("expected a type declaration, found {:?}", mapping),
+        }
+        assert_eq!(
+            messages(&decode_prost_plan_with_registry(&proto_plan, &types_registry()).unwrap_err()),
+            vec!["error at extensions[0]: type polygon is not defined in extension /types.yaml"]
+        );
+    }
+
+    #[test]
+    fn decodes_type_variations() {
+        let registry = types_registry();
+        let decoded = decode_prost_plan_with_registry(
+            &with_variation(point_plan(2), "dictionary", 1),
+            &registry,
+        )
+        .unwrap();
+        match decoded.root.as_ref() {
+            Rel::Read(read) => {
+                assert_eq!(read.base_schema.types[1], Type::String { nullable: false })
+            }
+            rel => panic!("expected a read, found {:?}", rel),
+        }
+
+        // the variation is declared for strings only
+        let diagnostics = decode_prost_plan_with_registry(
+            &with_variation(point_plan(2), "dictionary", 0),
+            &registry,
+        )
+        .unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "type variation dictionary applies to string, not to u!point<2>"
+        );
+
+        let diagnostics = decode_prost_plan_with_registry(
+            &with_variation(point_plan(2), "compressed", 1),
+            &registry,
+        )
+        .unwrap_err();
+        assert_eq!(
+            messages(&diagnostics),
+            vec!["error at extensions[1]: type variation compressed is not defined in extension /types.yaml"]
+        );
+    }
+
+    #[test]
+    fn reports_undeclared_type_variations() {
+        let mut proto_plan = with_variation(point_plan(2), "dictionary", 1);
+        proto_plan.extensions.pop();
+        let diagnostics = decode_prost_plan(&proto_plan).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "type variation anchor 1 is not declared"
+        );
+    }
+
+    #[test]
+    fn reports_malformed_relations() {
+        let mut proto_plan = plan("add:i32_i32");
+        match &mut proto_plan.relations[0].rel_type {
+            Some(proto::plan_rel::RelType::Rel(proto::Rel {
+                rel_type: Some(proto::rel::RelType::Project(project)),
+            })) => project.input = None,
+            rel => panic!("expected a project, found {:?}", rel),
+        }
+        assert_eq!(
+            messages(&decode_prost_plan(&proto_plan).unwrap_err()),
+            vec!["error at relations[0].project.input: input must be set"]
+        );
+    }
+
+    #[test]
+    fn reports_out_of_range_decimals() {
+        let decode_decimal = |precision, scale| {
+            let mut proto_plan = plan("add:i32_i32");
+            match &mut proto_plan.relations[0].rel_type {
+                Some(proto::plan_rel::RelType::Rel(proto::Rel {
+                    rel_type: Some(proto::rel::RelType::Project(project)),
+                })) => {
+                    project.expressions = vec![proto::Expression {
+                        rex_type: Some(proto::expression::RexType::Literal(
+                            proto::expression::Literal {
+                                nullable: false,
+                                type_variation_reference: 0,
+                                literal_type: Some(
+                                    proto::expression::literal::LiteralType::Decimal(
+                                        proto::expression::literal::Decimal {
+                                            value: 1234i128.to_le_bytes().to_vec(),
+                                            precision,
+                                            scale,
+                                        },
+                                    ),
+                                ),
+                            },
+                        )),
+                    }]
+                }
+                rel => panic!("expected a project, found {:?}", rel),
+            }
+            decode_prost_plan(&proto_plan).map_err(|diagnostics| messages(&diagnostics))
+        };
+
+        assert!(decode_decimal(4, 2).is_ok());
+        for (precision, scale) in [(38, 2_000_000_000), (0, 0), (39, 2), (4, -1), (4, 5)] {
+            let messages = decode_decimal(precision, scale).unwrap_err();
+            assert_eq!(messages.len(), 1);
+            assert!(
+                messages[0].ends_with(&format!(
+                    "decimal has precision {} and scale {}, but the precision must be between 1 \
+                     and 38 and the scale between 0 and the precision",
+                    precision, scale
+                )),
+                "{}",
+                messages[0]
+            );
+        }
     }
 }
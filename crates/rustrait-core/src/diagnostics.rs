@@ -0,0 +1,57 @@
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+// A finding about a plan, located by the path of the plan node it concerns
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub path: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn warning(path: &str, message: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            path: path.to_string(),
+            message,
+        }
+    }
+
+    pub fn error(path: &str, message: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            path: path.to_string(),
+            message,
+        }
+    }
+
+    // e.g. {"severity": "error", "path": "relations[0].root.input", "message": "..."}
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "severity": self.severity.to_string(),
+            "path": self.path,
+            "message": self.message,
+        })
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}: {}", self.severity, self.path, self.message)
+    }
+}
@@ -0,0 +1,1153 @@
+use crate::decoder::{
+    DelimiterSeparatedTextReadOptions, DELIMITED_TEXT_TYPE_URL, JOIN_TYPE_LEFT_MARK,
+    JOIN_TYPE_RIGHT_ANTI, JOIN_TYPE_RIGHT_MARK, JOIN_TYPE_RIGHT_SEMI, JOIN_TYPE_RIGHT_SINGLE,
+    KEYED_JOIN_TYPE_LEFT_MARK, KEYED_JOIN_TYPE_LEFT_SINGLE, KEYED_JOIN_TYPE_RIGHT_MARK,
+    KEYED_JOIN_TYPE_RIGHT_SINGLE,
+};
+use crate::plans::expressions::aggregate_function::{
+    AggregateFunctionInvocation, AggregationInvocation, AggregationPhase,
+};
+use crate::plans::expressions::cast::FailureBehavior;
+use crate::plans::expressions::field_reference::FieldReference;
+use crate::plans::expressions::literal::Literal;
+use crate::plans::expressions::sort_field::{SortDirection, SortField};
+use crate::plans::expressions::{Expression, Function, FunctionArgument, FunctionSignature, URI};
+use crate::plans::{
+    Aggregate, Cross, Emit, Exchange, ExchangeKind, Fetch, FileFormat, FileOrFiles, FilePath,
+    Filter, Join, JoinType, KeyedJoin, Plan, Project, Read, ReadType, Rel, Set, SetOp, Sort,
+};
+use crate::types::{proto_nullability, NamedStruct, Type, TypeParameter};
+use prost::Message;
+use std::collections::HashMap;
+use substrait::proto;
+use substrait::proto::extensions::simple_extension_declaration::{
+    ExtensionFunction, ExtensionType, MappingType,
+};
+use substrait::proto::extensions::{SimpleExtensionDeclaration, SimpleExtensionUri};
+use substrait::proto::read_rel::local_files;
+use substrait::proto::read_rel::local_files::file_or_files;
+
+const PRODUCER: &str = "rustrait";
+
+trait PlanEncoder<PlanFormat> {
+    fn encode(&mut self, plan: &Plan) -> PlanFormat;
+}
+
+pub fn encode_prost_plan(plan: &Plan) -> proto::Plan {
+    let mut encoder = ProstPlanEncoder::new();
+    encoder.encode(plan)
+}
+
+// Anchors are allocated from 1 in the order extensions are first used, so encoding the same
+// plan always produces the same declarations.
+pub struct ProstPlanEncoder {
+    extension_uris: Vec<URI>,
+    extension_uri_anchors: HashMap<URI, u32>,
+    declarations: Vec<SimpleExtensionDeclaration>,
+    function_anchors: HashMap<(URI, FunctionSignature), u32>,
+    type_anchors: HashMap<(URI, String), u32>,
+}
+
+impl PlanEncoder<proto::Plan> for ProstPlanEncoder {
+    fn encode(&mut self, plan: &Plan) -> proto::Plan {
+        let root = self.encode_relation(&plan.root);
+        let rel_type = if plan.names.is_empty() {
+            proto::plan_rel::RelType::Rel(root)
+        } else {
+            proto::plan_rel::RelType::Root(proto::RelRoot {
+                input: Some(root),
+                names: plan.names.clone(),
+            })
+        };
+        // subtrees come first, so that their ordinals are their indices
+        let mut relations: Vec<proto::PlanRel> = plan
+            .subtrees
+            .iter()
+            .map(|subtree| proto::PlanRel {
+                rel_type: Some(proto::plan_rel::RelType::Rel(self.encode_relation(subtree))),
+            })
+            .collect();
+        relations.push(proto::PlanRel {
+            rel_type: Some(rel_type),
+        });
+        proto::Plan {
+            version: Some(proto::Version {
+                producer: PRODUCER.to_string(),
+                ..substrait::version::version()
+            }),
+            extension_uris: self
+                .extension_uris
+                .iter()
+                .map(|uri| SimpleExtensionUri {
+                    extension_uri_anchor: self.extension_uri_anchors[uri],
+                    uri: uri.0.clone(),
+                })
+                .collect(),
+            extensions: std::mem::take(&mut self.declarations),
+            relations,
+            ..Default::default()
+        }
+    }
+}
+
+impl ProstPlanEncoder {
+    fn new() -> ProstPlanEncoder {
+        ProstPlanEncoder {
+            extension_uris: vec![],
+            extension_uri_anchors: HashMap::new(),
+            declarations: vec![],
+            function_anchors: HashMap::new(),
+            type_anchors: HashMap::new(),
+        }
+    }
+
+    fn encode_relation(&mut self, rel: &Rel) -> proto::Rel {
+        let rel_type = match rel {
+            Rel::Read(read) => proto::rel::RelType::Read(Box::new(self.encode_read(read))),
+            Rel::Filter(filter) => {
+                proto::rel::RelType::Filter(Box::new(self.encode_filter(filter)))
+            }
+            Rel::Fetch(fetch) => proto::rel::RelType::Fetch(Box::new(self.encode_fetch(fetch))),
+            Rel::Aggregate(aggregate) => {
+                proto::rel::RelType::Aggregate(Box::new(self.encode_aggregate(aggregate)))
+            }
+            Rel::Sort(sort) => proto::rel::RelType::Sort(Box::new(self.encode_sort(sort))),
+            Rel::Join(join) => proto::rel::RelType::Join(Box::new(self.encode_join(join))),
+            Rel::Project(project) => {
+                proto::rel::RelType::Project(Box::new(self.encode_project(project)))
+            }
+            Rel::Set(set) => proto::rel::RelType::Set(self.encode_set(set)),
+            Rel::Cross(cross) => proto::rel::RelType::Cross(Box::new(self.encode_cross(cross))),
+            Rel::Reference(reference) => proto::rel::RelType::Reference(proto::ReferenceRel {
+                subtree_ordinal: reference.subtree as i32,
+            }),
+            Rel::HashJoin(join) => {
+                proto::rel::RelType::HashJoin(Box::new(self.encode_hash_join(join)))
+            }
+            Rel::MergeJoin(join) => {
+                proto::rel::RelType::MergeJoin(Box::new(self.encode_merge_join(join)))
+            }
+            Rel::Exchange(exchange) => {
+                proto::rel::RelType::Exchange(Box::new(self.encode_exchange(exchange)))
+            }
+        };
+        proto::Rel {
+            rel_type: Some(rel_type),
+        }
+    }
+
+    fn encode_common(&self, emit: &Emit) -> Option<proto::RelCommon> {
+        let emit_kind = match emit {
+            Emit::Direct => proto::rel_common::EmitKind::Direct(proto::rel_common::Direct {}),
+            Emit::Remap(mapping) => proto::rel_common::EmitKind::Emit(proto::rel_common::Emit {
+                output_mapping: mapping.clone(),
+            }),
+        };
+        Some(proto::RelCommon {
+            emit_kind: Some(emit_kind),
+            ..Default::default()
+        })
+    }
+
+    fn encode_input(&mut self, input: &Rel) -> Option<Box<proto::Rel>> {
+        Some(Box::new(self.encode_relation(input)))
+    }
+
+    fn encode_read(&mut self, read: &Read) -> proto::ReadRel {
+        proto::ReadRel {
+            common: self.encode_common(&read.emit),
+            base_schema: Some(self.encode_named_struct(&read.base_schema)),
+            read_type: Some(match &read.read_type {
+                ReadType::NamedTable { names } => {
+                    proto::read_rel::ReadType::NamedTable(proto::read_rel::NamedTable {
+                        names: names.clone(),
+                        ..Default::default()
+                    })
+                }
+                ReadType::VirtualTable { rows } => {
+                    proto::read_rel::ReadType::VirtualTable(proto::read_rel::VirtualTable {
+                        values: rows
+                            .iter()
+                            .map(|row| proto::expression::literal::Struct {
+                                fields: row.iter().map(|l| self.encode_literal(l)).collect(),
+                            })
+                            .collect(),
+                    })
+                }
+                ReadType::LocalFiles { items } => {
+                    proto::read_rel::ReadType::LocalFiles(proto::read_rel::LocalFiles {
+                        items: items.iter().map(encode_file_or_files).collect(),
+                        ..Default::default()
+                    })
+                }
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn encode_filter(&mut self, filter: &Filter) -> proto::FilterRel {
+        proto::FilterRel {
+            common: self.encode_common(&filter.emit),
+            input: self.encode_input(&filter.input),
+            condition: Some(Box::new(self.encode_expression(&filter.condition))),
+            ..Default::default()
+        }
+    }
+
+    fn encode_fetch(&mut self, fetch: &Fetch) -> proto::FetchRel {
+        proto::FetchRel {
+            common: self.encode_common(&fetch.emit),
+            input: self.encode_input(&fetch.input),
+            offset: fetch.offset,
+            count: fetch.count,
+            ..Default::default()
+        }
+    }
+
+    fn encode_aggregate(&mut self, aggregate: &Aggregate) -> proto::AggregateRel {
+        proto::AggregateRel {
+            common: self.encode_common(&aggregate.emit),
+            input: self.encode_input(&aggregate.input),
+            groupings: aggregate
+                .groupings
+                .iter()
+                .map(|grouping| proto::aggregate_rel::Grouping {
+                    grouping_expressions: grouping
+                        .iter()
+                        .map(|expr| self.encode_expression(expr))
+                        .collect(),
+                })
+                .collect(),
+            measures: aggregate
+                .measures
+                .iter()
+                .map(|measure| proto::aggregate_rel::Measure {
+                    measure: Some(self.encode_aggregate_function(&measure.function)),
+                    filter: measure.filter.as_ref().map(|f| self.encode_expression(f)),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn encode_sort(&mut self, sort: &Sort) -> proto::SortRel {
+        proto::SortRel {
+            common: self.encode_common(&sort.emit),
+            input: self.encode_input(&sort.input),
+            sorts: sort
+                .sorts
+                .iter()
+                .map(|sf| self.encode_sort_field(sf))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn encode_join(&mut self, join: &Join) -> proto::JoinRel {
+        proto::JoinRel {
+            common: self.encode_common(&join.emit),
+            left: self.encode_input(&join.left),
+            right: self.encode_input(&join.right),
+            expression: join
+                .expression
+                .as_ref()
+                .map(|e| Box::new(self.encode_expression(e))),
+            post_join_filter: join
+                .post_join_filter
+                .as_ref()
+                .map(|e| Box::new(self.encode_expression(e))),
+            r#type: encode_join_type(join.join_type),
+            ..Default::default()
+        }
+    }
+
+    fn encode_hash_join(&mut self, join: &KeyedJoin) -> proto::HashJoinRel {
+        proto::HashJoinRel {
+            common: self.encode_common(&join.emit),
+            left: self.encode_input(&join.left),
+            right: self.encode_input(&join.right),
+            keys: self.encode_join_keys(join),
+            post_join_filter: join
+                .post_join_filter
+                .as_ref()
+                .map(|e| Box::new(self.encode_expression(e))),
+            r#type: encode_keyed_join_type(join.join_type),
+            ..Default::default()
+        }
+    }
+
+    fn encode_merge_join(&mut self, join: &KeyedJoin) -> proto::MergeJoinRel {
+        proto::MergeJoinRel {
+            common: self.encode_common(&join.emit),
+            left: self.encode_input(&join.left),
+            right: self.encode_input(&join.right),
+            keys: self.encode_join_keys(join),
+            post_join_filter: join
+                .post_join_filter
+                .as_ref()
+                .map(|e| Box::new(self.encode_expression(e))),
+            r#type: encode_keyed_join_type(join.join_type),
+            ..Default::default()
+        }
+    }
+
+    // The keys of the IR are compared for equality
+    fn encode_join_keys(&self, join: &KeyedJoin) -> Vec<proto::ComparisonJoinKey> {
+        use proto::comparison_join_key::comparison_type::InnerType;
+        use proto::comparison_join_key::{ComparisonType, SimpleComparisonType};
+        join.left_keys
+            .iter()
+            .zip(&join.right_keys)
+            .map(|(left, right)| proto::ComparisonJoinKey {
+                left: Some(self.encode_field_reference(left)),
+                right: Some(self.encode_field_reference(right)),
+                comparison: Some(ComparisonType {
+                    inner_type: Some(InnerType::Simple(SimpleComparisonType::Eq as i32)),
+                }),
+            })
+            .collect()
+    }
+
+    fn encode_keys(&self, keys: &[FieldReference]) -> Vec<proto::expression::FieldReference> {
+        keys.iter()
+            .map(|k| self.encode_field_reference(k))
+            .collect()
+    }
+
+    fn encode_exchange(&mut self, exchange: &Exchange) -> proto::ExchangeRel {
+        use proto::exchange_rel;
+        proto::ExchangeRel {
+            common: self.encode_common(&exchange.emit),
+            input: self.encode_input(&exchange.input),
+            partition_count: exchange.partition_count,
+            exchange_kind: Some(match &exchange.kind {
+                ExchangeKind::ScatterByFields(fields) => {
+                    exchange_rel::ExchangeKind::ScatterByFields(exchange_rel::ScatterFields {
+                        fields: self.encode_keys(fields),
+                    })
+                }
+                ExchangeKind::SingleTarget(expression) => exchange_rel::ExchangeKind::SingleTarget(
+                    Box::new(exchange_rel::SingleBucketExpression {
+                        expression: Some(Box::new(self.encode_expression(expression))),
+                    }),
+                ),
+                ExchangeKind::MultiTarget {
+                    expression,
+                    constrained_to_count,
+                } => exchange_rel::ExchangeKind::MultiTarget(Box::new(
+                    exchange_rel::MultiBucketExpression {
+                        expression: Some(Box::new(self.encode_expression(expression))),
+                        constrained_to_count: *constrained_to_count,
+                    },
+                )),
+                ExchangeKind::RoundRobin { exact } => {
+                    exchange_rel::ExchangeKind::RoundRobin(exchange_rel::RoundRobin {
+                        exact: *exact,
+                    })
+                }
+                ExchangeKind::Broadcast => {
+                    exchange_rel::ExchangeKind::Broadcast(exchange_rel::Broadcast {})
+                }
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn encode_project(&mut self, project: &Project) -> proto::ProjectRel {
+        proto::ProjectRel {
+            common: self.encode_common(&project.emit),
+            input: self.encode_input(&project.input),
+            expressions: project
+                .expressions
+                .iter()
+                .map(|expr| self.encode_expression(expr))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn encode_set(&mut self, set: &Set) -> proto::SetRel {
+        proto::SetRel {
+            common: self.encode_common(&set.emit),
+            inputs: set
+                .inputs
+                .iter()
+                .map(|input| self.encode_relation(input))
+                .collect(),
+            op: match set.op {
+                SetOp::MinusPrimary => proto::set_rel::SetOp::MinusPrimary,
+                SetOp::MinusMultiset => proto::set_rel::SetOp::MinusMultiset,
+                SetOp::IntersectionPrimary => proto::set_rel::SetOp::IntersectionPrimary,
+                SetOp::IntersectionMultiset => proto::set_rel::SetOp::IntersectionMultiset,
+                SetOp::UnionDistinct => proto::set_rel::SetOp::UnionDistinct,
+                SetOp::UnionAll => proto::set_rel::SetOp::UnionAll,
+            } as i32,
+            ..Default::default()
+        }
+    }
+
+    fn encode_cross(&mut self, cross: &Cross) -> proto::CrossRel {
+        proto::CrossRel {
+            common: self.encode_common(&cross.emit),
+            left: self.encode_input(&cross.left),
+            right: self.encode_input(&cross.right),
+            ..Default::default()
+        }
+    }
+
+    fn encode_sort_field(&mut self, sf: &SortField) -> proto::SortField {
+        let direction = match sf.direction {
+            SortDirection::AscNullsFirst => proto::sort_field::SortDirection::AscNullsFirst,
+            SortDirection::AscNullsLast => proto::sort_field::SortDirection::AscNullsLast,
+            SortDirection::DescNullsFirst => proto::sort_field::SortDirection::DescNullsFirst,
+            SortDirection::DescNullsLast => proto::sort_field::SortDirection::DescNullsLast,
+            SortDirection::Clustered => proto::sort_field::SortDirection::Clustered,
+        } as i32;
+        proto::SortField {
+            expr: Some(self.encode_expression(&sf.expression)),
+            sort_kind: Some(proto::sort_field::SortKind::Direction(direction)),
+        }
+    }
+
+    fn encode_aggregate_function(
+        &mut self,
+        af: &AggregateFunctionInvocation,
+    ) -> proto::AggregateFunction {
+        proto::AggregateFunction {
+            function_reference: self.function_anchor(&af.function),
+            arguments: af
+                .args
+                .iter()
+                .map(|fa| self.encode_function_argument(fa))
+                .collect(),
+            output_type: Some(self.encode_type(&af.output_type)),
+            phase: match af.phase {
+                AggregationPhase::InitialToIntermediate => {
+                    proto::AggregationPhase::InitialToIntermediate
+                }
+                AggregationPhase::IntermediateToIntermediate => {
+                    proto::AggregationPhase::IntermediateToIntermediate
+                }
+                AggregationPhase::InitialToResult => proto::AggregationPhase::InitialToResult,
+                AggregationPhase::IntermediateToResult => {
+                    proto::AggregationPhase::IntermediateToResult
+                }
+            } as i32,
+            invocation: match af.invocation {
+                AggregationInvocation::All => proto::aggregate_function::AggregationInvocation::All,
+                AggregationInvocation::Distinct => {
+                    proto::aggregate_function::AggregationInvocation::Distinct
+                }
+            } as i32,
+            sorts: af
+                .sorts
+                .iter()
+                .map(|sf| self.encode_sort_field(sf))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn encode_named_struct(&mut self, ns: &NamedStruct) -> proto::NamedStruct {
+        proto::NamedStruct {
+            names: ns.names.clone(),
+            r#struct: Some(proto::r#type::Struct {
+                types: ns.types.iter().map(|t| self.encode_type(t)).collect(),
+                type_variation_reference: 0,
+                nullability: proto_nullability(false),
+            }),
+        }
+    }
+
+    fn encode_expression(&mut self, expr: &Expression) -> proto::Expression {
+        let rex_type = match expr {
+            Expression::Literal(literal) => {
+                proto::expression::RexType::Literal(self.encode_literal(literal))
+            }
+            Expression::FieldReference(field_reference) => proto::expression::RexType::Selection(
+                Box::new(self.encode_field_reference(field_reference)),
+            ),
+            Expression::ScalarFunction(invocation) => {
+                proto::expression::RexType::ScalarFunction(proto::expression::ScalarFunction {
+                    function_reference: self.function_anchor(&invocation.function),
+                    arguments: invocation
+                        .args
+                        .iter()
+                        .map(|fa| self.encode_function_argument(fa))
+                        .collect(),
+                    output_type: Some(self.encode_type(&invocation.output_type)),
+                    ..Default::default()
+                })
+            }
+            Expression::Cast(cast) => {
+                use proto::expression::cast::FailureBehavior as ProtoFailureBehavior;
+                let failure_behavior = match cast.failure_behavior {
+                    FailureBehavior::Unspecified => ProtoFailureBehavior::Unspecified,
+                    FailureBehavior::ReturnNull => ProtoFailureBehavior::ReturnNull,
+                    FailureBehavior::ThrowException => ProtoFailureBehavior::ThrowException,
+                } as i32;
+                proto::expression::RexType::Cast(Box::new(proto::expression::Cast {
+                    r#type: Some(self.encode_type(&cast.r#type)),
+                    input: Some(Box::new(self.encode_expression(&cast.input))),
+                    failure_behavior,
+                }))
+            }
+        };
+        proto::Expression {
+            rex_type: Some(rex_type),
+        }
+    }
+
+    fn encode_function_argument(&mut self, fa: &FunctionArgument) -> proto::FunctionArgument {
+        let arg_type = match fa {
+            FunctionArgument::Value(expr) => {
+                proto::function_argument::ArgType::Value(self.encode_expression(expr))
+            }
+            FunctionArgument::Enum(enu) => proto::function_argument::ArgType::Enum(enu.clone()),
+            FunctionArgument::Type(typ) => {
+                proto::function_argument::ArgType::Type(self.encode_type(typ))
+            }
+        };
+        proto::FunctionArgument {
+            arg_type: Some(arg_type),
+        }
+    }
+
+    fn encode_field_reference(&self, fr: &FieldReference) -> proto::expression::FieldReference {
+        proto::expression::FieldReference {
+            reference_type: Some(
+                proto::expression::field_reference::ReferenceType::DirectReference(
+                    proto::expression::ReferenceSegment {
+                        reference_type: Some(
+                            proto::expression::reference_segment::ReferenceType::StructField(
+                                Box::new(proto::expression::reference_segment::StructField {
+                                    field: fr.field,
+                                    child: None,
+                                }),
+                            ),
+                        ),
+                    },
+                ),
+            ),
+            root_type: Some(proto::expression::field_reference::RootType::RootReference(
+                proto::expression::field_reference::RootReference {},
+            )),
+        }
+    }
+
+    fn encode_literal(&mut self, literal: &Literal) -> proto::expression::Literal {
+        use proto::expression::literal::LiteralType;
+        let (literal_type, nullable) = match literal {
+            Literal::Null(t) => (LiteralType::Null(self.encode_type(t)), true),
+            Literal::Bool(l) => (LiteralType::Boolean(l.value), l.nullable),
+            Literal::I32(l) => (LiteralType::I32(l.value), l.nullable),
+            Literal::I64(l) => (LiteralType::I64(l.value), l.nullable),
+            Literal::FP32(l) => (LiteralType::Fp32(l.value), l.nullable),
+            Literal::FP64(l) => (LiteralType::Fp64(l.value), l.nullable),
+            Literal::String(l) => (LiteralType::String(l.value.clone()), l.nullable),
+            Literal::Binary(l) => (LiteralType::Binary(l.value.clone()), l.nullable),
+            Literal::Date(l) => (LiteralType::Date(l.value), l.nullable),
+            #[allow(deprecated)]
+            Literal::Timestamp(l) => (LiteralType::Timestamp(l.value), l.nullable),
+            Literal::Decimal(l) => (
+                LiteralType::Decimal(proto::expression::literal::Decimal {
+                    value: l.value.to_le_bytes().to_vec(),
+                    precision: l.precision,
+                    scale: l.scale,
+                }),
+                l.nullable,
+            ),
+        };
+        proto::expression::Literal {
+            nullable,
+            type_variation_reference: 0,
+            literal_type: Some(literal_type),
+        }
+    }
+
+    fn encode_type(&mut self, t: &Type) -> proto::Type {
+        use proto::r#type::Kind;
+        let kind = match t {
+            Type::Bool { nullable } => Kind::Bool(proto::r#type::Boolean {
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            }),
+            Type::I8 { nullable } => Kind::I8(proto::r#type::I8 {
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            }),
+            Type::I16 { nullable } => Kind::I16(proto::r#type::I16 {
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            }),
+            Type::I32 { nullable } => Kind::I32(proto::r#type::I32 {
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            }),
+            Type::I64 { nullable } => Kind::I64(proto::r#type::I64 {
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            }),
+            Type::FP32 { nullable } => Kind::Fp32(proto::r#type::Fp32 {
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            }),
+            Type::FP64 { nullable } => Kind::Fp64(proto::r#type::Fp64 {
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            }),
+            Type::String { nullable } => Kind::String(proto::r#type::String {
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            }),
+            Type::Binary { nullable } => Kind::Binary(proto::r#type::Binary {
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            }),
+            Type::Timestamp { nullable } => Kind::Timestamp(proto::r#type::Timestamp {
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            }),
+            Type::TimestampTz { nullable } => Kind::TimestampTz(proto::r#type::TimestampTz {
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            }),
+            Type::Date { nullable } => Kind::Date(proto::r#type::Date {
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            }),
+            Type::Time { nullable } => Kind::Time(proto::r#type::Time {
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            }),
+            Type::IntervalYear { nullable } => Kind::IntervalYear(proto::r#type::IntervalYear {
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            }),
+            Type::IntervalDay { nullable } => Kind::IntervalDay(proto::r#type::IntervalDay {
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            }),
+            Type::UUID { nullable } => Kind::Uuid(proto::r#type::Uuid {
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            }),
+            Type::FixedChar { nullable, length } => Kind::FixedChar(proto::r#type::FixedChar {
+                length: *length,
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            }),
+            Type::VarChar { nullable, length } => Kind::Varchar(proto::r#type::VarChar {
+                length: *length,
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            }),
+            Type::FixedBinary { nullable, length } => {
+                Kind::FixedBinary(proto::r#type::FixedBinary {
+                    length: *length,
+                    nullability: proto_nullability(*nullable),
+                    ..Default::default()
+                })
+            }
+            Type::Decimal {
+                nullable,
+                precision,
+                scale,
+            } => Kind::Decimal(proto::r#type::Decimal {
+                precision: *precision,
+                scale: *scale,
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            }),
+            Type::Struct { nullable, types } => Kind::Struct(proto::r#type::Struct {
+                types: types.iter().map(|t| self.encode_type(t)).collect(),
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            }),
+            Type::List { nullable, r#type } => Kind::List(Box::new(proto::r#type::List {
+                r#type: Some(Box::new(self.encode_type(r#type))),
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            })),
+            Type::Map {
+                nullable,
+                key,
+                value,
+            } => Kind::Map(Box::new(proto::r#type::Map {
+                key: Some(Box::new(self.encode_type(key))),
+                value: Some(Box::new(self.encode_type(value))),
+                nullability: proto_nullability(*nullable),
+                ..Default::default()
+            })),
+            Type::UserDefined {
+                nullable,
+                extension,
+                name,
+                parameters,
+            } => Kind::UserDefined(proto::r#type::UserDefined {
+                type_reference: self.type_anchor(extension, name),
+                nullability: proto_nullability(*nullable),
+                type_parameters: parameters
+                    .iter()
+                    .map(|p| self.encode_type_parameter(p))
+                    .collect(),
+                ..Default::default()
+            }),
+        };
+        proto::Type { kind: Some(kind) }
+    }
+
+    fn encode_type_parameter(&mut self, p: &TypeParameter) -> proto::r#type::Parameter {
+        use proto::r#type::parameter::Parameter;
+        let parameter = match p {
+            TypeParameter::Null => Parameter::Null(Default::default()),
+            TypeParameter::DataType(t) => Parameter::DataType(self.encode_type(t)),
+            TypeParameter::Boolean(b) => Parameter::Boolean(*b),
+            TypeParameter::Integer(i) => Parameter::Integer(*i),
+            TypeParameter::Enum(e) => Parameter::Enum(e.clone()),
+            TypeParameter::String(s) => Parameter::String(s.clone()),
+        };
+        proto::r#type::Parameter {
+            parameter: Some(parameter),
+        }
+    }
+
+    fn extension_uri_anchor(&mut self, uri: &URI) -> u32 {
+        if let Some(anchor) = self.extension_uri_anchors.get(uri) {
+            return *anchor;
+        }
+        let anchor = self.extension_uris.len() as u32 + 1;
+        self.extension_uris.push(uri.clone());
+        self.extension_uri_anchors.insert(uri.clone(), anchor);
+        anchor
+    }
+
+    fn function_anchor(&mut self, function: &Function) -> u32 {
+        let key = (function.extension.clone(), function.signature.clone());
+        if let Some(anchor) = self.function_anchors.get(&key) {
+            return *anchor;
+        }
+        let anchor = self.function_anchors.len() as u32 + 1;
+        let extension_uri_reference = self.extension_uri_anchor(&function.extension);
+        self.declarations.push(SimpleExtensionDeclaration {
+            mapping_type: Some(MappingType::ExtensionFunction(ExtensionFunction {
+                extension_uri_reference,
+                function_anchor: anchor,
+                name: function.signature.0.clone(),
+            })),
+        });
+        self.function_anchors.insert(key, anchor);
+        anchor
+    }
+
+    fn type_anchor(&mut self, extension: &URI, name: &str) -> u32 {
+        let key = (extension.clone(), name.to_string());
+        if let Some(anchor) = self.type_anchors.get(&key) {
+            return *anchor;
+        }
+        let anchor = self.type_anchors.len() as u32 + 1;
+        let extension_uri_reference = self.extension_uri_anchor(extension);
+        self.declarations.push(SimpleExtensionDeclaration {
+            mapping_type: Some(MappingType::ExtensionType(ExtensionType {
+                extension_uri_reference,
+                type_anchor: anchor,
+                name: name.to_string(),
+            })),
+        });
+        self.type_anchors.insert(key, anchor);
+        anchor
+    }
+}
+
+fn encode_join_type(join_type: JoinType) -> i32 {
+    use proto::join_rel::JoinType as ProtoJoinType;
+    match join_type {
+        JoinType::Inner => ProtoJoinType::Inner as i32,
+        JoinType::Outer => ProtoJoinType::Outer as i32,
+        JoinType::Left => ProtoJoinType::Left as i32,
+        JoinType::Right => ProtoJoinType::Right as i32,
+        JoinType::LeftSemi => ProtoJoinType::Semi as i32,
+        JoinType::LeftAnti => ProtoJoinType::Anti as i32,
+        JoinType::LeftSingle => ProtoJoinType::Single as i32,
+        JoinType::RightSemi => JOIN_TYPE_RIGHT_SEMI,
+        JoinType::RightAnti => JOIN_TYPE_RIGHT_ANTI,
+        JoinType::RightSingle => JOIN_TYPE_RIGHT_SINGLE,
+        JoinType::LeftMark => JOIN_TYPE_LEFT_MARK,
+        JoinType::RightMark => JOIN_TYPE_RIGHT_MARK,
+    }
+}
+
+// MergeJoinRel numbers its join types like HashJoinRel
+fn encode_keyed_join_type(join_type: JoinType) -> i32 {
+    use proto::hash_join_rel::JoinType as ProtoJoinType;
+    match join_type {
+        JoinType::Inner => ProtoJoinType::Inner as i32,
+        JoinType::Outer => ProtoJoinType::Outer as i32,
+        JoinType::Left => ProtoJoinType::Left as i32,
+        JoinType::Right => ProtoJoinType::Right as i32,
+        JoinType::LeftSemi => ProtoJoinType::LeftSemi as i32,
+        JoinType::RightSemi => ProtoJoinType::RightSemi as i32,
+        JoinType::LeftAnti => ProtoJoinType::LeftAnti as i32,
+        JoinType::RightAnti => ProtoJoinType::RightAnti as i32,
+        JoinType::LeftSingle => KEYED_JOIN_TYPE_LEFT_SINGLE,
+        JoinType::RightSingle => KEYED_JOIN_TYPE_RIGHT_SINGLE,
+        JoinType::LeftMark => KEYED_JOIN_TYPE_LEFT_MARK,
+        JoinType::RightMark => KEYED_JOIN_TYPE_RIGHT_MARK,
+    }
+}
+
+fn encode_file_or_files(item: &FileOrFiles) -> local_files::FileOrFiles {
+    local_files::FileOrFiles {
+        path_type: Some(match &item.path {
+            FilePath::Uri(uri) => file_or_files::PathType::UriPath(uri.clone()),
+            FilePath::Glob(glob) => file_or_files::PathType::UriPathGlob(glob.clone()),
+            FilePath::File(uri) => file_or_files::PathType::UriFile(uri.clone()),
+            FilePath::Folder(uri) => file_or_files::PathType::UriFolder(uri.clone()),
+        }),
+        file_format: Some(match &item.format {
+            FileFormat::Parquet => file_or_files::FileFormat::Parquet(Default::default()),
+            FileFormat::Arrow => file_or_files::FileFormat::Arrow(Default::default()),
+            FileFormat::Orc => file_or_files::FileFormat::Orc(Default::default()),
+            FileFormat::Dwrf => file_or_files::FileFormat::Dwrf(Default::default()),
+            FileFormat::DelimitedText(options) => {
+                let options = DelimiterSeparatedTextReadOptions {
+                    field_delimiter: options.field_delimiter.clone(),
+                    max_line_size: options.max_line_size,
+                    quote: options.quote.clone(),
+                    header_lines_to_skip: options.header_lines_to_skip,
+                    escape: options.escape.clone(),
+                    value_treated_as_null: options.value_treated_as_null.clone(),
+                };
+                file_or_files::FileFormat::Extension(pbjson_types::Any {
+                    type_url: DELIMITED_TEXT_TYPE_URL.to_string(),
+                    value: options.encode_to_vec().into(),
+                })
+            }
+        }),
+        partition_index: item.partition_index,
+        start: item.start,
+        length: item.length,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::decode_prost_plan;
+    use crate::plans::expressions::literal::{
+        Binary, Bool, Date, Decimal, Str, Timestamp, FP32, FP64, I32, I64,
+    };
+    use crate::plans::Measure;
+
+    fn read() -> Rel {
+        Rel::Read(Read {
+            emit: Emit::Direct,
+            base_schema: NamedStruct {
+                names: vec!["a".to_string()],
+                types: vec![Type::I32 { nullable: false }],
+            },
+            read_type: ReadType::NamedTable {
+                names: vec!["t".to_string()],
+            },
+        })
+    }
+
+    fn field(field: i32) -> Expression {
+        Expression::FieldReference(FieldReference { field })
+    }
+
+    fn plan(root: Rel) -> Plan {
+        Plan {
+            root: Box::new(root),
+            names: vec![],
+            subtrees: vec![],
+        }
+    }
+
+    fn root(proto_plan: &proto::Plan) -> &proto::rel::RelType {
+        match &proto_plan.relations.last().unwrap().rel_type {
+            Some(proto::plan_rel::RelType::Rel(proto::Rel {
+                rel_type: Some(rel_type),
+            })) => rel_type,
+            rel => panic!("expected a relation, found {:?}", rel),
+        }
+    }
+
+    const JOIN_TYPES: [JoinType; 12] = [
+        JoinType::Inner,
+        JoinType::Outer,
+        JoinType::Left,
+        JoinType::Right,
+        JoinType::LeftSemi,
+        JoinType::LeftAnti,
+        JoinType::LeftSingle,
+        JoinType::RightSemi,
+        JoinType::RightAnti,
+        JoinType::RightSingle,
+        JoinType::LeftMark,
+        JoinType::RightMark,
+    ];
+
+    #[test]
+    fn encodes_join_types() {
+        let join = |join_type| {
+            plan(Rel::Join(Join {
+                emit: Emit::Direct,
+                left: Box::new(read()),
+                right: Box::new(read()),
+                expression: None,
+                post_join_filter: None,
+                join_type,
+            }))
+        };
+        let encoded = encode_prost_plan(&join(JoinType::LeftSemi));
+        match root(&encoded) {
+            proto::rel::RelType::Join(join) => {
+                assert_eq!(join.r#type(), proto::join_rel::JoinType::Semi)
+            }
+            rel => panic!("expected a join, found {:?}", rel),
+        }
+
+        for join_type in JOIN_TYPES {
+            let plan = join(join_type);
+            assert_eq!(decode_prost_plan(&encode_prost_plan(&plan)).unwrap(), plan);
+        }
+    }
+
+    #[test]
+    fn encodes_keyed_join_types() {
+        let join = |join_type| KeyedJoin {
+            emit: Emit::Direct,
+            left: Box::new(read()),
+            right: Box::new(read()),
+            left_keys: vec![FieldReference { field: 0 }],
+            right_keys: vec![FieldReference { field: 0 }],
+            post_join_filter: None,
+            join_type,
+        };
+        let encoded = encode_prost_plan(&plan(Rel::HashJoin(join(JoinType::RightAnti))));
+        match root(&encoded) {
+            proto::rel::RelType::HashJoin(join) => {
+                assert_eq!(join.r#type(), proto::hash_join_rel::JoinType::RightAnti)
+            }
+            rel => panic!("expected a hash join, found {:?}", rel),
+        }
+
+        for join_type in JOIN_TYPES {
+            for rel in [
+                Rel::HashJoin(join(join_type)),
+                Rel::MergeJoin(join(join_type)),
+            ] {
+                let plan = plan(rel);
+                assert_eq!(decode_prost_plan(&encode_prost_plan(&plan)).unwrap(), plan);
+            }
+        }
+    }
+
+    #[test]
+    fn encodes_set_ops() {
+        let set = |op| {
+            plan(Rel::Set(Set {
+                emit: Emit::Direct,
+                inputs: vec![read(), read()],
+                op,
+            }))
+        };
+        let encoded = encode_prost_plan(&set(SetOp::IntersectionMultiset));
+        match root(&encoded) {
+            proto::rel::RelType::Set(set) => {
+                assert_eq!(set.op(), proto::set_rel::SetOp::IntersectionMultiset)
+            }
+            rel => panic!("expected a set, found {:?}", rel),
+        }
+
+        for op in [
+            SetOp::MinusPrimary,
+            SetOp::MinusMultiset,
+            SetOp::IntersectionPrimary,
+            SetOp::IntersectionMultiset,
+            SetOp::UnionDistinct,
+            SetOp::UnionAll,
+        ] {
+            let plan = set(op);
+            assert_eq!(decode_prost_plan(&encode_prost_plan(&plan)).unwrap(), plan);
+        }
+    }
+
+    #[test]
+    fn encodes_sort_directions() {
+        let directions = [
+            SortDirection::AscNullsFirst,
+            SortDirection::AscNullsLast,
+            SortDirection::DescNullsFirst,
+            SortDirection::DescNullsLast,
+            SortDirection::Clustered,
+        ];
+        let plan = plan(Rel::Sort(Sort {
+            emit: Emit::Direct,
+            input: Box::new(read()),
+            sorts: directions
+                .iter()
+                .map(|direction| SortField {
+                    expression: field(0),
+                    direction: *direction,
+                })
+                .collect(),
+        }));
+        let encoded = encode_prost_plan(&plan);
+        match root(&encoded) {
+            proto::rel::RelType::Sort(sort) => assert_eq!(
+                sort.sorts[2].sort_kind,
+                Some(proto::sort_field::SortKind::Direction(
+                    proto::sort_field::SortDirection::DescNullsFirst as i32
+                ))
+            ),
+            rel => panic!("expected a sort, found {:?}", rel),
+        }
+        assert_eq!(decode_prost_plan(&encoded).unwrap(), plan);
+    }
+
+    #[test]
+    fn encodes_aggregate_functions() {
+        let function = Function {
+            signature: FunctionSignature("count:any".to_string()),
+            extension: URI("/functions_aggregate_generic.yaml".to_string()),
+            variant: None,
+        };
+        let measure = |phase, invocation| Measure {
+            function: AggregateFunctionInvocation {
+                function: function.clone(),
+                args: vec![FunctionArgument::Value(field(0))],
+                output_type: Type::I64 { nullable: false },
+                phase,
+                invocation,
+                sorts: vec![],
+            },
+            filter: None,
+        };
+        let plan = plan(Rel::Aggregate(Aggregate {
+            emit: Emit::Direct,
+            input: Box::new(read()),
+            groupings: vec![vec![field(0)]],
+            measures: vec![
+                measure(
+                    AggregationPhase::InitialToIntermediate,
+                    AggregationInvocation::All,
+                ),
+                measure(
+                    AggregationPhase::IntermediateToIntermediate,
+                    AggregationInvocation::Distinct,
+                ),
+                measure(
+                    AggregationPhase::InitialToResult,
+                    AggregationInvocation::All,
+                ),
+                measure(
+                    AggregationPhase::IntermediateToResult,
+                    AggregationInvocation::Distinct,
+                ),
+            ],
+        }));
+        let encoded = encode_prost_plan(&plan);
+        match root(&encoded) {
+            proto::rel::RelType::Aggregate(aggregate) => {
+                let measure = aggregate.measures[1].measure.as_ref().unwrap();
+                assert_eq!(
+                    measure.phase(),
+                    proto::AggregationPhase::IntermediateToIntermediate
+                );
+                assert_eq!(
+                    measure.invocation(),
+                    proto::aggregate_function::AggregationInvocation::Distinct
+                );
+            }
+            rel => panic!("expected an aggregate, found {:?}", rel),
+        }
+        // the function is declared once for all measures
+        assert_eq!(encoded.extension_uris.len(), 1);
+        assert_eq!(encoded.extensions.len(), 1);
+        assert_eq!(decode_prost_plan(&encoded).unwrap(), plan);
+    }
+
+    #[test]
+    fn encodes_emit() {
+        let plan = plan(Rel::Filter(Filter {
+            emit: Emit::Remap(vec![0, 0]),
+            input: Box::new(read()),
+            condition: field(0),
+        }));
+        let encoded = encode_prost_plan(&plan);
+        match root(&encoded) {
+            proto::rel::RelType::Filter(filter) => assert_eq!(
+                filter.common.as_ref().unwrap().emit_kind,
+                Some(proto::rel_common::EmitKind::Emit(proto::rel_common::Emit {
+                    output_mapping: vec![0, 0]
+                }))
+            ),
+            rel => panic!("expected a filter, found {:?}", rel),
+        }
+        assert_eq!(decode_prost_plan(&encoded).unwrap(), plan);
+    }
+
+    #[test]
+    fn encodes_literals() {
+        let row = vec![
+            Literal::Null(Type::Decimal {
+                nullable: true,
+                precision: 10,
+                scale: 2,
+            }),
+            Literal::Bool(Bool {
+                value: true,
+                nullable: false,
+            }),
+            Literal::I32(I32 {
+                value: -7,
+                nullable: false,
+            }),
+            Literal::I64(I64 {
+                value: 1 << 40,
+                nullable: true,
+            }),
+            Literal::FP32(FP32 {
+                value: 0.5,
+                nullable: false,
+            }),
+            Literal::FP64(FP64 {
+                value: -2.25,
+                nullable: false,
+            }),
+            Literal::String(Str {
+                value: "text".to_string(),
+                nullable: false,
+            }),
+            Literal::Binary(Binary {
+                value: vec![0, 255],
+                nullable: false,
+            }),
+            Literal::Date(Date {
+                value: 19_000,
+                nullable: false,
+            }),
+            Literal::Timestamp(Timestamp {
+                value: 1_641_600_000_000_000,
+                nullable: false,
+            }),
+            Literal::Decimal(Decimal {
+                value: -123_456_789_012_345_678_901_234,
+                precision: 38,
+                scale: 4,
+                nullable: false,
+            }),
+        ];
+        let plan = plan(Rel::Read(Read {
+            emit: Emit::Direct,
+            base_schema: NamedStruct {
+                names: (0..row.len()).map(|i| format!("c{}", i)).collect(),
+                types: row.iter().map(|l| l.output_type()).collect(),
+            },
+            read_type: ReadType::VirtualTable { rows: vec![row] },
+        }));
+        assert_eq!(decode_prost_plan(&encode_prost_plan(&plan)).unwrap(), plan);
+    }
+}
@@ -0,0 +1,446 @@
+use crate::checker::{plan_schemas, rel_schemas, RelSchemas, Schema};
+use crate::plans::expressions::aggregate_function::AggregationInvocation;
+use crate::plans::expressions::field_reference::FieldReference;
+use crate::plans::expressions::sort_field::{SortDirection, SortField};
+use crate::plans::expressions::{column_name, display_arguments};
+use crate::plans::{
+    Emit, ExchangeKind, FileFormat, FileOrFiles, FilePath, JoinType, Plan, ReadType, Rel, SetOp,
+};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ExplainOptions {
+    // annotate every relation with the columns it outputs
+    pub schemas: bool,
+}
+
+// Renders the plan as an indented operator tree, one relation per line with its inputs below
+// it, e.g.
+//
+//   Root[EXPR$0]
+//     Project[expressions=[add(BAR, 1:i32) -> i32?], emit=[1]]
+//       Read[table=FOO, columns=[BAR: i32?]]
+pub fn explain(plan: &Plan, options: ExplainOptions) -> String {
+    let schemas = plan_schemas(plan);
+    let mut out = String::new();
+    for (i, subtree) in plan.subtrees.iter().enumerate() {
+        out.push_str(&format!("Subtree[{}]\n", i));
+        explain_node(subtree, 1, options, &schemas, &mut out);
+    }
+    if plan.names.is_empty() {
+        explain_node(&plan.root, 0, options, &schemas, &mut out);
+    } else {
+        out.push_str(&format!("Root[{}]\n", plan.names.join(", ")));
+        explain_node(&plan.root, 1, options, &schemas, &mut out);
+    }
+    out
+}
+
+pub fn explain_rel(rel: &Rel, indent: usize, options: ExplainOptions, out: &mut String) {
+    explain_node(rel, indent, options, &rel_schemas(rel), out);
+}
+
+fn explain_node(
+    rel: &Rel,
+    indent: usize,
+    options: ExplainOptions,
+    schemas: &RelSchemas,
+    out: &mut String,
+) {
+    let (name, attributes) = describe(rel, schemas);
+    out.push_str(&"  ".repeat(indent));
+    out.push_str(name);
+    if !attributes.is_empty() {
+        out.push_str(&format!("[{}]", attributes.join(", ")));
+    }
+    if options.schemas {
+        match schemas.get(rel) {
+            Some(schema) => out.push_str(&format!(" => ({})", display_schema(schema))),
+            None => out.push_str(" => (unknown)"),
+        }
+    }
+    out.push('\n');
+
+    for input in rel.inputs() {
+        explain_node(input, indent + 1, options, schemas, out);
+    }
+}
+
+// The name of the relation and its key properties, naming columns with the schemas of its inputs
+pub(crate) fn describe(rel: &Rel, schemas: &RelSchemas) -> (&'static str, Vec<String>) {
+    // expressions are evaluated against the concatenated columns of the inputs
+    let inputs = rel.inputs();
+    let input_columns: Vec<Option<String>> = match rel {
+        Rel::Set(_) => vec![],
+        // columns of later inputs cannot be named if an earlier input's schema is unknown
+        _ => inputs
+            .iter()
+            .map(|input| schemas.get(input))
+            .collect::<Option<Vec<&Schema>>>()
+            .map(|schemas| {
+                schemas
+                    .into_iter()
+                    .flat_map(|schema| &schema.columns)
+                    .map(|column| column.name.clone())
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+    let columns = &input_columns[..];
+
+    let mut attributes = vec![];
+    let name = match rel {
+        Rel::Read(read) => {
+            match &read.read_type {
+                ReadType::NamedTable { names } => {
+                    attributes.push(format!("table={}", names.join(".")))
+                }
+                ReadType::VirtualTable { rows } => {
+                    attributes.push(format!("virtual_table={} rows", rows.len()))
+                }
+                ReadType::LocalFiles { items } => {
+                    let files = items.iter().map(describe_file).collect::<Vec<_>>();
+                    attributes.push(format!("files=[{}]", files.join(", ")))
+                }
+            }
+            let column_types = read
+                .base_schema
+                .column_names()
+                .iter()
+                .zip(&read.base_schema.types)
+                .map(|(name, t)| format!("{}: {}", name, t))
+                .collect::<Vec<_>>();
+            attributes.push(format!("columns=[{}]", column_types.join(", ")));
+            "Read"
+        }
+        Rel::Filter(filter) => {
+            attributes.push(format!("condition={}", filter.condition.display(columns)));
+            "Filter"
+        }
+        Rel::Fetch(fetch) => {
+            attributes.push(format!("offset={}", fetch.offset));
+            if fetch.count >= 0 {
+                attributes.push(format!("count={}", fetch.count));
+            }
+            "Fetch"
+        }
+        Rel::Aggregate(aggregate) => {
+            let groupings = aggregate
+                .groupings
+                .iter()
+                .map(|grouping| {
+                    let keys = grouping
+                        .iter()
+                        .map(|e| e.display(columns).to_string())
+                        .collect::<Vec<_>>();
+                    format!("[{}]", keys.join(", "))
+                })
+                .collect::<Vec<_>>();
+            attributes.push(format!("groupings=[{}]", groupings.join(", ")));
+            let measures = aggregate
+                .measures
+                .iter()
+                .map(|measure| {
+                    let function = &measure.function;
+                    let mut arguments = display_arguments(&function.args, columns);
+                    if function.invocation == AggregationInvocation::Distinct {
+                        arguments = format!("DISTINCT {}", arguments);
+                    }
+                    if !function.sorts.is_empty() {
+                        arguments = format!(
+                            "{} ORDER BY {}",
+                            arguments,
+                            display_sorts(&function.sorts, columns)
+                        );
+                    }
+                    let mut measure_string = format!(
+                        "{}({}) -> {}",
+                        function.function.name(),
+                        arguments,
+                        function.output_type
+                    );
+                    if let Some(filter) = &measure.filter {
+                        measure_string =
+                            format!("{} FILTER ({})", measure_string, filter.display(columns));
+                    }
+                    measure_string
+                })
+                .collect::<Vec<_>>();
+            attributes.push(format!("measures=[{}]", measures.join(", ")));
+            "Aggregate"
+        }
+        Rel::Sort(sort) => {
+            attributes.push(format!("sorts=[{}]", display_sorts(&sort.sorts, columns)));
+            "Sort"
+        }
+        Rel::Join(join) => {
+            attributes.push(format!("type={}", join_type_name(&join.join_type)));
+            if let Some(expression) = &join.expression {
+                attributes.push(format!("on={}", expression.display(columns)));
+            }
+            if let Some(filter) = &join.post_join_filter {
+                attributes.push(format!("post_join_filter={}", filter.display(columns)));
+            }
+            "Join"
+        }
+        Rel::Project(project) => {
+            let expressions = project
+                .expressions
+                .iter()
+                .map(|e| e.display(columns).to_string())
+                .collect::<Vec<_>>();
+            attributes.push(format!("expressions=[{}]", expressions.join(", ")));
+            "Project"
+        }
+        Rel::Set(set) => {
+            let op = match set.op {
+                SetOp::MinusPrimary => "minus primary",
+                SetOp::MinusMultiset => "minus multiset",
+                SetOp::IntersectionPrimary => "intersection primary",
+                SetOp::IntersectionMultiset => "intersection multiset",
+                SetOp::UnionDistinct => "union distinct",
+                SetOp::UnionAll => "union all",
+            };
+            attributes.push(format!("op={}", op));
+            "Set"
+        }
+        Rel::HashJoin(join) | Rel::MergeJoin(join) => {
+            attributes.push(format!("type={}", join_type_name(&join.join_type)));
+            // right keys refer to the columns of the right input, which follow the left's
+            let left_count = schemas.get(&join.left).map_or(0, |s| s.columns.len());
+            let (left_columns, right_columns) = columns.split_at(left_count.min(columns.len()));
+            let keys = join
+                .left_keys
+                .iter()
+                .zip(&join.right_keys)
+                .map(|(l, r)| {
+                    format!(
+                        "{} = {}",
+                        display_field(l, left_columns),
+                        display_field(r, right_columns)
+                    )
+                })
+                .collect::<Vec<_>>();
+            attributes.push(format!("keys=[{}]", keys.join(", ")));
+            if let Some(filter) = &join.post_join_filter {
+                attributes.push(format!("post_join_filter={}", filter.display(columns)));
+            }
+            match rel {
+                Rel::HashJoin(_) => "HashJoin",
+                _ => "MergeJoin",
+            }
+        }
+        Rel::Exchange(exchange) => {
+            let kind = match &exchange.kind {
+                ExchangeKind::ScatterByFields(fields) => {
+                    let fields = fields
+                        .iter()
+                        .map(|f| display_field(f, columns))
+                        .collect::<Vec<_>>();
+                    format!("scatter({})", fields.join(", "))
+                }
+                ExchangeKind::SingleTarget(expression) => {
+                    format!("single target({})", expression.display(columns))
+                }
+                ExchangeKind::MultiTarget { expression, .. } => {
+                    format!("multi target({})", expression.display(columns))
+                }
+                ExchangeKind::RoundRobin { .. } => "round robin".to_string(),
+                ExchangeKind::Broadcast => "broadcast".to_string(),
+            };
+            attributes.push(format!("kind={}", kind));
+            attributes.push(format!("partitions={}", exchange.partition_count));
+            "Exchange"
+        }
+        Rel::Cross(_) => "Cross",
+        Rel::Reference(reference) => {
+            attributes.push(format!("subtree={}", reference.subtree));
+            "Reference"
+        }
+    };
+    if let Emit::Remap(mapping) = rel.emit() {
+        let mapping = mapping.iter().map(|i| i.to_string()).collect::<Vec<_>>();
+        attributes.push(format!("emit=[{}]", mapping.join(", ")));
+    }
+    (name, attributes)
+}
+
+fn join_type_name(join_type: &JoinType) -> &'static str {
+    match join_type {
+        JoinType::Inner => "inner",
+        JoinType::Outer => "outer",
+        JoinType::Left => "left",
+        JoinType::Right => "right",
+        JoinType::LeftSemi => "left semi",
+        JoinType::LeftAnti => "left anti",
+        JoinType::LeftSingle => "left single",
+        JoinType::RightSemi => "right semi",
+        JoinType::RightAnti => "right anti",
+        JoinType::RightSingle => "right single",
+        JoinType::LeftMark => "left mark",
+        JoinType::RightMark => "right mark",
+    }
+}
+
+fn display_field(field_reference: &FieldReference, columns: &[Option<String>]) -> String {
+    match column_name(field_reference.field, columns) {
+        Some(name) => name.to_string(),
+        None => format!("${}", field_reference.field),
+    }
+}
+
+fn display_sorts(sorts: &[SortField], columns: &[Option<String>]) -> String {
+    sorts
+        .iter()
+        .map(|sf| {
+            let direction = match sf.direction {
+                SortDirection::AscNullsFirst => "ASC NULLS FIRST",
+                SortDirection::AscNullsLast => "ASC NULLS LAST",
+                SortDirection::DescNullsFirst => "DESC NULLS FIRST",
+                SortDirection::DescNullsLast => "DESC NULLS LAST",
+                SortDirection::Clustered => "CLUSTERED",
+            };
+            format!("{} {}", sf.expression.display(columns), direction)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn display_schema(schema: &Schema) -> String {
+    schema
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, c)| match &c.name {
+            Some(name) => format!("{}: {}", name, c.r#type),
+            None => format!("${}: {}", i, c.r#type),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", explain(self, ExplainOptions::default()))
+    }
+}
+
+impl fmt::Display for Rel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        explain_rel(self, 0, ExplainOptions::default(), &mut out);
+        write!(f, "{}", out)
+    }
+}
+
+// The path followed by the format, and the partition and byte range if they are set, e.g.
+// `/data/a.csv (csv, partition 1, bytes 0..1024)`
+fn describe_file(item: &FileOrFiles) -> String {
+    let path = match &item.path {
+        FilePath::Uri(uri) | FilePath::File(uri) | FilePath::Folder(uri) => uri,
+        FilePath::Glob(glob) => glob,
+    };
+    let mut details = vec![match &item.format {
+        FileFormat::Parquet => "parquet",
+        FileFormat::Arrow => "arrow",
+        FileFormat::Orc => "orc",
+        FileFormat::Dwrf => "dwrf",
+        FileFormat::DelimitedText(_) => "csv",
+    }
+    .to_string()];
+    if item.partition_index != 0 {
+        details.push(format!("partition {}", item.partition_index));
+    }
+    if item.length != 0 {
+        // widened, as the end of a range the plan gives may not fit in 64 bits
+        let end = u128::from(item.start) + u128::from(item.length);
+        details.push(format!("bytes {}..{}", item.start, end));
+    }
+    format!("{} ({})", path, details.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::decode_prost_plan;
+    use crate::format::load_plan;
+    use crate::plans::{Cross, Exchange, Read, Reference};
+    use crate::types::{NamedStruct, Type};
+
+    fn read(table: &str, column: &str) -> Rel {
+        Rel::Read(Read {
+            emit: Emit::Direct,
+            base_schema: NamedStruct {
+                names: vec![column.to_string()],
+                types: vec![Type::I32 { nullable: false }],
+            },
+            read_type: ReadType::NamedTable {
+                names: vec![table.to_string()],
+            },
+        })
+    }
+
+    #[test]
+    fn explains_simple_select() {
+        let proto_plan = load_plan(include_bytes!("../simple-select.substrait")).unwrap();
+        let plan = decode_prost_plan(&proto_plan).unwrap();
+        assert_eq!(
+            plan.to_string(),
+            "Root[EXPR$0]\n  \
+             Project[expressions=[add(BAR, 1:i32) -> i32?], emit=[1]]\n    \
+             Read[table=FOO, columns=[BAR: i32?]]\n"
+        );
+        assert_eq!(
+            explain(&plan, ExplainOptions { schemas: true }),
+            "Root[EXPR$0]\n  \
+             Project[expressions=[add(BAR, 1:i32) -> i32?], emit=[1]] => ($0: i32?)\n    \
+             Read[table=FOO, columns=[BAR: i32?]] => (BAR: i32?)\n"
+        );
+    }
+
+    #[test]
+    fn shows_positions_of_ambiguous_columns() {
+        let plan = Plan {
+            root: Box::new(Rel::Exchange(Exchange {
+                emit: Emit::Direct,
+                input: Box::new(Rel::Cross(Cross {
+                    emit: Emit::Direct,
+                    left: Box::new(read("FOO", "A")),
+                    right: Box::new(read("BAR", "A")),
+                })),
+                partition_count: 2,
+                kind: ExchangeKind::ScatterByFields(vec![FieldReference { field: 1 }]),
+            })),
+            names: vec![],
+            subtrees: vec![],
+        };
+        assert_eq!(
+            plan.to_string(),
+            "Exchange[kind=scatter($1), partitions=2]\n  \
+             Cross\n    \
+             Read[table=FOO, columns=[A: i32]]\n    \
+             Read[table=BAR, columns=[A: i32]]\n"
+        );
+    }
+
+    #[test]
+    fn shows_schemas_of_subtree_references() {
+        let plan = Plan {
+            root: Box::new(Rel::Exchange(Exchange {
+                emit: Emit::Direct,
+                input: Box::new(Rel::Reference(Reference { subtree: 0 })),
+                partition_count: 2,
+                kind: ExchangeKind::ScatterByFields(vec![FieldReference { field: 0 }]),
+            })),
+            names: vec![],
+            subtrees: vec![read("FOO", "A")],
+        };
+        assert_eq!(
+            explain(&plan, ExplainOptions { schemas: true }),
+            "Subtree[0]\n  \
+             Read[table=FOO, columns=[A: i32]] => (A: i32)\n\
+             Exchange[kind=scatter(A), partitions=2] => (A: i32)\n  \
+             Reference[subtree=0] => (A: i32)\n"
+        );
+    }
+}
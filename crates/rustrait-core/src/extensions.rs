@@ -1,25 +1,104 @@
 use substrait::text::simple_extensions;
 
-mod arguments;
-mod scalar_function;
+pub mod aggregate_function;
+pub mod arguments;
+pub mod evaluator;
+pub mod overload;
+pub mod properties;
+pub mod registry;
+pub mod scalar_function;
+pub mod signature;
+pub mod type_definition;
+pub mod type_expression;
+pub mod type_variation;
+pub mod window_function;
 
-// TODO:
-// * types
-// * aggregate functions
-// * window functions
 #[derive(Debug)]
 pub struct Extensions {
+    pub types: Vec<type_definition::TypeDefinition>,
+    pub type_variations: Vec<type_variation::TypeVariation>,
     pub scalar_functions: Vec<scalar_function::ScalarFunction>,
+    pub aggregate_functions: Vec<aggregate_function::AggregateFunction>,
+    pub window_functions: Vec<window_function::WindowFunction>,
 }
 
-impl From<simple_extensions::SimpleExtensions> for Extensions {
-    fn from(se: simple_extensions::SimpleExtensions) -> Self {
-        return Extensions {
+impl TryFrom<simple_extensions::SimpleExtensions> for Extensions {
+    type Error = String;
+
+    fn try_from(se: simple_extensions::SimpleExtensions) -> Result<Self, Self::Error> {
+        Ok(Extensions {
+            types: se
+                .types
+                .into_iter()
+                .map(type_definition::TypeDefinition::try_from)
+                .collect::<Result<_, _>>()?,
+            type_variations: se
+                .type_variations
+                .into_iter()
+                .map(type_variation::TypeVariation::try_from)
+                .collect::<Result<_, _>>()?,
             scalar_functions: se
                 .scalar_functions
                 .into_iter()
-                .map(|sf| scalar_function::ScalarFunction::from(sf))
-                .collect(),
-        };
+                .map(scalar_function::ScalarFunction::try_from)
+                .collect::<Result<_, _>>()?,
+            aggregate_functions: se
+                .aggregate_functions
+                .into_iter()
+                .map(aggregate_function::AggregateFunction::try_from)
+                .collect::<Result<_, _>>()?,
+            window_functions: se
+                .window_functions
+                .into_iter()
+                .map(window_function::WindowFunction::try_from)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl Extensions {
+    pub fn type_definition(&self, name: &str) -> Option<&type_definition::TypeDefinition> {
+        self.types
+            .iter()
+            .find(|t| t.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn type_variation(&self, name: &str) -> Option<&type_variation::TypeVariation> {
+        self.type_variations
+            .iter()
+            .find(|tv| tv.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn scalar_variant(
+        &self,
+        name: &str,
+        index: usize,
+    ) -> Option<&scalar_function::ScalarFunctionVariant> {
+        self.scalar_functions
+            .iter()
+            .find(|sf| sf.name == name)
+            .and_then(|sf| sf.variants.get(index))
+    }
+
+    pub fn aggregate_variant(
+        &self,
+        name: &str,
+        index: usize,
+    ) -> Option<&aggregate_function::AggregateFunctionVariant> {
+        self.aggregate_functions
+            .iter()
+            .find(|af| af.name == name)
+            .and_then(|af| af.variants.get(index))
+    }
+
+    pub fn window_variant(
+        &self,
+        name: &str,
+        index: usize,
+    ) -> Option<&window_function::WindowFunctionVariant> {
+        self.window_functions
+            .iter()
+            .find(|wf| wf.name == name)
+            .and_then(|wf| wf.variants.get(index))
     }
 }
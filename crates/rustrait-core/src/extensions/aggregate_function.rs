@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+use substrait::text::simple_extensions;
+
+use crate::extensions::arguments::Argument;
+use crate::extensions::properties::{
+    function_options, FunctionOption, NullabilityHandling, VariadicBehaviour,
+};
+use crate::extensions::type_expression::{ReturnType, TypeExpression};
+
+#[derive(Debug)]
+pub struct AggregateFunction {
+    pub name: String,
+    pub description: Option<String>,
+    pub variants: Vec<AggregateFunctionVariant>,
+}
+
+impl TryFrom<simple_extensions::AggregateFunction> for AggregateFunction {
+    type Error = String;
+
+    fn try_from(af: simple_extensions::AggregateFunction) -> Result<Self, Self::Error> {
+        let variants = af
+            .impls
+            .into_iter()
+            .map(AggregateFunctionVariant::try_from)
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("function {}: {}", af.name, e))?;
+        Ok(AggregateFunction {
+            name: af.name,
+            description: af.description,
+            variants,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Decomposable {
+    // the aggregate cannot be split into partial and final phases
+    None,
+    // each partial aggregation produces at most one intermediate value
+    One,
+    // partial aggregations may produce multiple intermediate values
+    Many,
+}
+
+impl From<Option<simple_extensions::Decomposable>> for Decomposable {
+    fn from(d: Option<simple_extensions::Decomposable>) -> Self {
+        match d {
+            None | Some(simple_extensions::Decomposable::None) => Decomposable::None,
+            Some(simple_extensions::Decomposable::One) => Decomposable::One,
+            Some(simple_extensions::Decomposable::Many) => Decomposable::Many,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AggregateFunctionVariant {
+    pub arguments: Vec<Argument>,
+    pub options: BTreeMap<String, FunctionOption>,
+    pub nullability: NullabilityHandling,
+    pub variadic: Option<VariadicBehaviour>,
+    pub deterministic: bool,
+    pub session_dependent: bool,
+    pub decomposable: Decomposable,
+    pub intermediate: Option<TypeExpression>,
+    pub ordered: bool,
+    pub maxset: Option<u64>,
+    pub return_type: ReturnType,
+}
+
+impl TryFrom<simple_extensions::AggregateFunctionImplsItem> for AggregateFunctionVariant {
+    type Error = String;
+
+    fn try_from(afii: simple_extensions::AggregateFunctionImplsItem) -> Result<Self, Self::Error> {
+        Ok(AggregateFunctionVariant {
+            arguments: afii
+                .args
+                .map(|args| args.0)
+                .unwrap_or_default()
+                .into_iter()
+                .map(Argument::try_from)
+                .collect::<Result<_, _>>()?,
+            options: function_options(afii.options),
+            nullability: NullabilityHandling::from(afii.nullability),
+            variadic: afii.variadic.map(VariadicBehaviour::from),
+            deterministic: afii.deterministic.map(|d| d.0).unwrap_or(true),
+            session_dependent: afii.session_dependent.map(|sd| sd.0).unwrap_or(false),
+            decomposable: Decomposable::from(afii.decomposable),
+            intermediate: afii
+                .intermediate
+                .map(|i| TypeExpression::try_from(i.0))
+                .transpose()?,
+            ordered: afii.ordered.map(|o| o.0).unwrap_or(false),
+            maxset: afii.maxset.map(|m| m.0 as u64),
+            return_type: ReturnType::try_from(afii.return_.0)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FUNCTIONS: &str = r#"
+    aggregate_functions:
+      - name: "count"
+        impls:
+          - args:
+              - { name: x, value: any }
+            decomposable: MANY
+            intermediate: i64
+            return: i64
+      - name: "first"
+        impls:
+          - args:
+              - { name: x, value: any1 }
+            ordered: true
+            maxset: 1
+            return: any1?
+    "#;
+
+    fn aggregate_functions() -> Vec<AggregateFunction> {
+        let simple_extensions: simple_extensions::SimpleExtensions =
+            serde_yaml::from_str(FUNCTIONS).unwrap();
+        simple_extensions
+            .aggregate_functions
+            .into_iter()
+            .map(AggregateFunction::try_from)
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn converts_aggregate_properties() {
+        let functions = aggregate_functions();
+        let count = &functions[0].variants[0];
+        assert_eq!(count.decomposable, Decomposable::Many);
+        assert_eq!(
+            count.intermediate,
+            Some(TypeExpression::parse("i64").unwrap())
+        );
+        assert!(!count.ordered);
+        assert_eq!(count.maxset, None);
+
+        let first = &functions[1].variants[0];
+        assert_eq!(first.decomposable, Decomposable::None);
+        assert_eq!(first.intermediate, None);
+        assert!(first.ordered);
+        assert_eq!(first.maxset, Some(1));
+    }
+}
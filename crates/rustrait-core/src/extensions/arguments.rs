@@ -1,21 +1,36 @@
 use substrait::text::simple_extensions;
 
-use crate::types::Type;
+use crate::extensions::type_expression::TypeExpression;
 
+#[derive(Clone, Debug)]
 pub enum Argument {
     Value {
         name: Option<String>,
-        value: Type,
+        value: TypeExpression,
         is_constant: bool,
     },
+    Enum {
+        name: Option<String>,
+        options: Vec<String>,
+    },
+    Type {
+        name: Option<String>,
+        value: TypeExpression,
+    },
 }
 
-impl From<simple_extensions::ArgumentsItem> for Argument {
-    fn from(ai: simple_extensions::ArgumentsItem) -> Self {
-        match ai {
-            simple_extensions::ArgumentsItem::EnumerationArg { .. } => {
-                panic!("cannot handle enum argument")
-            }
+impl TryFrom<simple_extensions::ArgumentsItem> for Argument {
+    type Error = String;
+
+    fn try_from(ai: simple_extensions::ArgumentsItem) -> Result<Self, Self::Error> {
+        Ok(match ai {
+            // enum
+            simple_extensions::ArgumentsItem::EnumerationArg(
+                simple_extensions::EnumerationArg { name, options, .. },
+            ) => Argument::Enum {
+                name,
+                options: options.0,
+            },
             // value
             simple_extensions::ArgumentsItem::ValueArg(simple_extensions::ValueArg {
                 constant,
@@ -23,13 +38,19 @@ impl From<simple_extensions::ArgumentsItem> for Argument {
                 value,
                 ..
             }) => Argument::Value {
-                name: name,
-                value: Type::from(value),
+                name,
+                value: TypeExpression::try_from(value)?,
                 is_constant: constant.unwrap_or(false),
             },
-            simple_extensions::ArgumentsItem::TypeArg { .. } => {
-                panic!("cannot handle type argument")
-            }
-        }
+            // type
+            simple_extensions::ArgumentsItem::TypeArg(simple_extensions::TypeArg {
+                name,
+                type_,
+                ..
+            }) => Argument::Type {
+                name,
+                value: TypeExpression::parse(&type_)?,
+            },
+        })
     }
 }
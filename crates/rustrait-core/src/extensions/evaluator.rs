@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+
+// Evaluates the integer expressions used in type parameters and return type programs, e.g.
+// `init_prec > 38 ? scale_after_borrow : init_scale`. Booleans are represented as 0 and 1.
+pub fn evaluate(expression: &str, variables: &HashMap<String, i64>) -> Result<i64, String> {
+    let tokens = tokenize(expression)?;
+    let mut parser = Parser {
+        tokens: &tokens,
+        position: 0,
+        variables,
+    };
+    let value = parser.ternary()?;
+    if parser.position != tokens.len() {
+        return Err(format!("unexpected input in expression: {}", expression));
+    }
+    Ok(value)
+}
+
+// Runs the assignments of a return type program, adding each result to the variables
+pub fn run_program(
+    program: &[(String, String)],
+    variables: &mut HashMap<String, i64>,
+) -> Result<(), String> {
+    for (name, expression) in program {
+        let value = evaluate(expression, variables)?;
+        variables.insert(name.clone(), value);
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(i64),
+    Identifier(String),
+    Operator(&'static str),
+}
+
+const OPERATORS: [&str; 19] = [
+    "<=", ">=", "==", "!=", "&&", "||", "<", ">", "+", "-", "*", "/", "(", ")", ",", "?", ":", "!",
+    "=",
+];
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut rest = expression.trim_start();
+    while !rest.is_empty() {
+        let c = rest.chars().next().expect("rest is not empty");
+        if c.is_ascii_digit() {
+            let end = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let number = rest[..end]
+                .parse()
+                .map_err(|_| format!("invalid number in expression: {}", expression))?;
+            tokens.push(Token::Number(number));
+            rest = &rest[end..];
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Identifier(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else {
+            let operator = OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(*op))
+                .ok_or_else(|| {
+                    format!("unexpected character {} in expression: {}", c, expression)
+                })?;
+            tokens.push(Token::Operator(operator));
+            rest = &rest[operator.len()..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    variables: &'a HashMap<String, i64>,
+}
+
+impl Parser<'_> {
+    fn peek_operator(&self) -> Option<&'static str> {
+        match self.tokens.get(self.position) {
+            Some(Token::Operator(op)) => Some(op),
+            Some(Token::Identifier(id)) if id.eq_ignore_ascii_case("and") => Some("&&"),
+            Some(Token::Identifier(id)) if id.eq_ignore_ascii_case("or") => Some("||"),
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, operator: &str) -> Result<(), String> {
+        if self.peek_operator() != Some(operator) {
+            return Err(format!("expected {} at token {}", operator, self.position));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn ternary(&mut self) -> Result<i64, String> {
+        let condition = self.or()?;
+        if self.peek_operator() != Some("?") {
+            return Ok(condition);
+        }
+        self.position += 1;
+        let if_true = self.ternary()?;
+        self.expect(":")?;
+        let if_false = self.ternary()?;
+        Ok(if condition != 0 { if_true } else { if_false })
+    }
+
+    fn or(&mut self) -> Result<i64, String> {
+        let mut value = self.and()?;
+        while self.peek_operator() == Some("||") {
+            self.position += 1;
+            let rhs = self.and()?;
+            value = (value != 0 || rhs != 0) as i64;
+        }
+        Ok(value)
+    }
+
+    fn and(&mut self) -> Result<i64, String> {
+        let mut value = self.comparison()?;
+        while self.peek_operator() == Some("&&") {
+            self.position += 1;
+            let rhs = self.comparison()?;
+            value = (value != 0 && rhs != 0) as i64;
+        }
+        Ok(value)
+    }
+
+    fn comparison(&mut self) -> Result<i64, String> {
+        let lhs = self.additive()?;
+        let operator = match self.peek_operator() {
+            Some(op @ ("<" | ">" | "<=" | ">=" | "==" | "!=")) => op,
+            _ => return Ok(lhs),
+        };
+        self.position += 1;
+        let rhs = self.additive()?;
+        let result = match operator {
+            "<" => lhs < rhs,
+            ">" => lhs > rhs,
+            "<=" => lhs <= rhs,
+            ">=" => lhs >= rhs,
+            "==" => lhs == rhs,
+            _ => lhs != rhs,
+        };
+        Ok(result as i64)
+    }
+
+    fn additive(&mut self) -> Result<i64, String> {
+        let mut value = self.multiplicative()?;
+        loop {
+            match self.peek_operator() {
+                Some("+") => {
+                    self.position += 1;
+                    let rhs = self.multiplicative()?;
+                    value = value.checked_add(rhs).ok_or_else(overflow)?;
+                }
+                Some("-") => {
+                    self.position += 1;
+                    let rhs = self.multiplicative()?;
+                    value = value.checked_sub(rhs).ok_or_else(overflow)?;
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<i64, String> {
+        let mut value = self.unary()?;
+        loop {
+            match self.peek_operator() {
+                Some("*") => {
+                    self.position += 1;
+                    let rhs = self.unary()?;
+                    value = value.checked_mul(rhs).ok_or_else(overflow)?;
+                }
+                Some("/") => {
+                    self.position += 1;
+                    let divisor = self.unary()?;
+                    if divisor == 0 {
+                        return Err("division by zero".to_string());
+                    }
+                    value = value.checked_div(divisor).ok_or_else(overflow)?;
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<i64, String> {
+        match self.tokens.get(self.position) {
+            Some(Token::Operator("-")) => {
+                self.position += 1;
+                self.unary()?.checked_neg().ok_or_else(overflow)
+            }
+            Some(Token::Operator("!")) => {
+                self.position += 1;
+                Ok((self.unary()? == 0) as i64)
+            }
+            Some(Token::Identifier(id)) if id.eq_ignore_ascii_case("not") => {
+                self.position += 1;
+                Ok((self.unary()? == 0) as i64)
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<i64, String> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| "unexpected end of expression".to_string())?;
+        self.position += 1;
+        match token {
+            Token::Number(n) => Ok(n),
+            Token::Operator("(") => {
+                let value = self.ternary()?;
+                self.expect(")")?;
+                Ok(value)
+            }
+            Token::Identifier(id) if self.peek_operator() == Some("(") => {
+                self.position += 1;
+                let mut arguments = vec![self.ternary()?];
+                while self.peek_operator() == Some(",") {
+                    self.position += 1;
+                    arguments.push(self.ternary()?);
+                }
+                self.expect(")")?;
+                match id.to_lowercase().as_str() {
+                    "min" => Ok(*arguments.iter().min().expect("at least one argument")),
+                    "max" => Ok(*arguments.iter().max().expect("at least one argument")),
+                    "abs" if arguments.len() == 1 => {
+                        arguments[0].checked_abs().ok_or_else(overflow)
+                    }
+                    _ => Err(format!("unknown function {}", id)),
+                }
+            }
+            Token::Identifier(id) if id.eq_ignore_ascii_case("true") => Ok(1),
+            Token::Identifier(id) if id.eq_ignore_ascii_case("false") => Ok(0),
+            Token::Identifier(id) => self
+                .variables
+                .get(&id)
+                .copied()
+                .ok_or_else(|| format!("unbound parameter {}", id)),
+            Token::Operator(op) => Err(format!("unexpected {} in expression", op)),
+        }
+    }
+}
+
+fn overflow() -> String {
+    "integer overflow".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluates_decimal_add_program() {
+        let program: Vec<(String, String)> = [
+            ("init_scale", "max(S1,S2)"),
+            ("init_prec", "init_scale + max(P1 - S1, P2 - S2) + 1"),
+            ("min_scale", "min(init_scale, 6)"),
+            ("delta", "init_prec - 38"),
+            ("prec", "min(init_prec, 38)"),
+            ("scale_after_borrow", "max(init_scale - delta, min_scale)"),
+            ("scale", "init_prec > 38 ? scale_after_borrow : init_scale"),
+        ]
+        .iter()
+        .map(|(n, e)| (n.to_string(), e.to_string()))
+        .collect();
+
+        let mut variables: HashMap<String, i64> = [("P1", 38), ("S1", 10), ("P2", 38), ("S2", 10)]
+            .iter()
+            .map(|(n, v)| (n.to_string(), *v))
+            .collect();
+        run_program(&program, &mut variables).unwrap();
+        assert_eq!(variables["prec"], 38);
+        assert_eq!(variables["scale"], 9);
+    }
+
+    #[test]
+    fn reports_overflow() {
+        let variables: HashMap<String, i64> = [("P".to_string(), i64::MAX)].into_iter().collect();
+        for expression in [
+            "P + 1",
+            "-P - 2",
+            "P * 2",
+            "abs(-P - 1)",
+            "-(-P - 1)",
+            "(-P - 1) / -1",
+        ] {
+            assert_eq!(
+                evaluate(expression, &variables),
+                Err("integer overflow".to_string()),
+                "{}",
+                expression
+            );
+        }
+        assert_eq!(evaluate("P - 1", &variables), Ok(i64::MAX - 1));
+    }
+}
@@ -0,0 +1,591 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::extensions::arguments::Argument;
+use crate::extensions::evaluator;
+use crate::extensions::properties::{NullabilityHandling, ParameterConsistency};
+use crate::extensions::scalar_function::{ScalarFunction, ScalarFunctionVariant};
+use crate::extensions::signature::Signature;
+use crate::extensions::type_expression::{TypeExpression, TypeParameter};
+use crate::types;
+use crate::types::Type;
+
+// An argument at a call site, as far as overload resolution is concerned
+#[derive(Clone, Copy, Debug)]
+pub enum BoundArgument<'a> {
+    Value(&'a Type),
+    Enum(&'a str),
+    Type(&'a Type),
+}
+
+impl fmt::Display for BoundArgument<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoundArgument::Value(t) => write!(f, "{}", t),
+            BoundArgument::Enum(e) => write!(f, "{}", e),
+            BoundArgument::Type(t) => write!(f, "type {}", t),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Resolution<'a> {
+    pub index: usize,
+    pub variant: &'a ScalarFunctionVariant,
+    pub output_type: Type,
+}
+
+#[derive(Debug)]
+pub enum ResolutionError {
+    NoMatch {
+        function: String,
+        arguments: Vec<String>,
+        candidates: Vec<String>,
+    },
+    Ambiguous {
+        function: String,
+        arguments: Vec<String>,
+        candidates: Vec<String>,
+    },
+    OutputType {
+        function: String,
+        candidate: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for ResolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolutionError::NoMatch {
+                function,
+                arguments,
+                candidates,
+            } => write!(
+                f,
+                "no variant of {} accepts ({}), candidates are: {}",
+                function,
+                arguments.join(", "),
+                candidates.join(", ")
+            ),
+            ResolutionError::Ambiguous {
+                function,
+                arguments,
+                candidates,
+            } => write!(
+                f,
+                "call to {} with ({}) is ambiguous between: {}",
+                function,
+                arguments.join(", "),
+                candidates.join(", ")
+            ),
+            ResolutionError::OutputType {
+                function,
+                candidate,
+                reason,
+            } => write!(
+                f,
+                "cannot derive the output type of {} ({}): {}",
+                function, candidate, reason
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ResolutionError {}
+
+// Values bound to the type variables (any1, T) and integer parameters (P1, L1) of a variant
+#[derive(Clone, Debug, Default)]
+pub struct Bindings {
+    pub types: HashMap<String, Type>,
+    pub integers: HashMap<String, i64>,
+}
+
+impl ScalarFunction {
+    pub fn resolve_overload(
+        &self,
+        arguments: &[BoundArgument],
+    ) -> Result<Resolution<'_>, ResolutionError> {
+        let described_arguments = || arguments.iter().map(|a| a.to_string()).collect();
+
+        let matches: Vec<(usize, &ScalarFunctionVariant, Bindings)> = self
+            .variants
+            .iter()
+            .enumerate()
+            .filter_map(|(i, v)| bind_arguments(v, arguments).map(|b| (i, v, b)))
+            .collect();
+        if matches.is_empty() {
+            return Err(ResolutionError::NoMatch {
+                function: self.name.clone(),
+                arguments: described_arguments(),
+                candidates: candidate_signatures(&self.name, self.variants.iter()),
+            });
+        }
+
+        // prefer the variants with the fewest type variables
+        let best = matches
+            .iter()
+            .map(|(_, v, _)| specificity(v))
+            .max()
+            .expect("matches is not empty");
+        let mut best_matches: Vec<_> = matches
+            .into_iter()
+            .filter(|(_, v, _)| specificity(v) == best)
+            .collect();
+        if best_matches.len() > 1 {
+            return Err(ResolutionError::Ambiguous {
+                function: self.name.clone(),
+                arguments: described_arguments(),
+                candidates: candidate_signatures(
+                    &self.name,
+                    best_matches.iter().map(|(_, v, _)| *v),
+                ),
+            });
+        }
+
+        let (index, variant, bindings) = best_matches.remove(0);
+        let output_type = derive_output_type(variant, arguments, &bindings).map_err(|reason| {
+            ResolutionError::OutputType {
+                function: self.name.clone(),
+                candidate: Signature::from_arguments(&self.name, &variant.arguments).encode(),
+                reason,
+            }
+        })?;
+        Ok(Resolution {
+            index,
+            variant,
+            output_type,
+        })
+    }
+}
+
+fn candidate_signatures<'a>(
+    name: &str,
+    variants: impl Iterator<Item = &'a ScalarFunctionVariant>,
+) -> Vec<String> {
+    variants
+        .map(|v| Signature::from_arguments(name, &v.arguments).encode())
+        .collect()
+}
+
+fn specificity(variant: &ScalarFunctionVariant) -> usize {
+    variant
+        .arguments
+        .iter()
+        .filter(|a| !matches!(a, Argument::Value { value, .. } if value.is_any()))
+        .count()
+}
+
+// Matches the call site arguments against a variant, returning the resulting bindings
+pub fn bind_arguments(
+    variant: &ScalarFunctionVariant,
+    arguments: &[BoundArgument],
+) -> Option<Bindings> {
+    let discrete = variant.nullability == NullabilityHandling::Discrete;
+    let mut bindings = Bindings::default();
+    let mut position = 0;
+
+    for (i, parameter) in variant.arguments.iter().enumerate() {
+        let is_last = i + 1 == variant.arguments.len();
+        if let (true, Some(variadic)) = (is_last, &variant.variadic) {
+            let repeated = &arguments[position.min(arguments.len())..];
+            let count = repeated.len() as u32;
+            if count < variadic.min || variadic.max.is_some_and(|max| count > max) {
+                return None;
+            }
+            for argument in repeated {
+                let bound = match variadic.parameter_consistency {
+                    ParameterConsistency::Consistent => {
+                        bind_argument(parameter, argument, &mut bindings, discrete)
+                    }
+                    // each repetition may bind the type variables differently
+                    ParameterConsistency::Inconsistent => {
+                        bind_argument(parameter, argument, &mut bindings.clone(), discrete)
+                    }
+                };
+                if !bound {
+                    return None;
+                }
+            }
+            return Some(bindings);
+        }
+
+        match arguments.get(position) {
+            Some(argument) if bind_argument(parameter, argument, &mut bindings, discrete) => {
+                position += 1
+            }
+            // enumeration arguments may be left out, as an `opt` code in a signature does
+            _ if matches!(parameter, Argument::Enum { .. }) => {}
+            _ => return None,
+        }
+    }
+
+    if position != arguments.len() {
+        return None;
+    }
+    Some(bindings)
+}
+
+fn bind_argument(
+    parameter: &Argument,
+    argument: &BoundArgument,
+    bindings: &mut Bindings,
+    discrete: bool,
+) -> bool {
+    match (parameter, argument) {
+        (Argument::Value { value, .. }, BoundArgument::Value(t)) => {
+            bind_type(value, t, bindings, discrete)
+        }
+        (Argument::Enum { options, .. }, BoundArgument::Enum(e)) => {
+            options.iter().any(|o| o.eq_ignore_ascii_case(e))
+        }
+        (Argument::Type { value, .. }, BoundArgument::Type(t)) => {
+            bind_type(value, t, bindings, false)
+        }
+        _ => false,
+    }
+}
+
+enum ConcreteParameter<'a> {
+    Integer(i64),
+    Type(&'a Type),
+}
+
+fn concrete_parameters(t: &Type) -> Vec<ConcreteParameter<'_>> {
+    match t {
+        Type::FixedChar { length, .. }
+        | Type::VarChar { length, .. }
+        | Type::FixedBinary { length, .. } => vec![ConcreteParameter::Integer(*length as i64)],
+        Type::Decimal {
+            precision, scale, ..
+        } => vec![
+            ConcreteParameter::Integer(*precision as i64),
+            ConcreteParameter::Integer(*scale as i64),
+        ],
+        Type::Struct { types, .. } => types.iter().map(ConcreteParameter::Type).collect(),
+        Type::List { r#type, .. } => vec![ConcreteParameter::Type(r#type)],
+        Type::Map { key, value, .. } => {
+            vec![ConcreteParameter::Type(key), ConcreteParameter::Type(value)]
+        }
+        Type::UserDefined { parameters, .. } => parameters
+            .iter()
+            .filter_map(|p| match p {
+                types::TypeParameter::DataType(t) => Some(ConcreteParameter::Type(t)),
+                types::TypeParameter::Integer(i) => Some(ConcreteParameter::Integer(*i)),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn bind_type(
+    expression: &TypeExpression,
+    t: &Type,
+    bindings: &mut Bindings,
+    discrete: bool,
+) -> bool {
+    if discrete && expression.nullable != t.nullable() {
+        return false;
+    }
+
+    if expression.is_any() {
+        if expression.name == "any" {
+            return true;
+        }
+        let t = t.clone().with_nullable(false);
+        return match bindings.types.get(&expression.name) {
+            Some(bound) => *bound == t,
+            None => {
+                bindings.types.insert(expression.name.clone(), t);
+                true
+            }
+        };
+    }
+
+    if expression.name != t.name() {
+        return false;
+    }
+    // unparameterized expressions such as `decimal` accept any parameters
+    if expression.parameters.is_empty() {
+        return true;
+    }
+
+    let parameters = concrete_parameters(t);
+    parameters.len() == expression.parameters.len()
+        && expression
+            .parameters
+            .iter()
+            .zip(parameters)
+            .all(|(expected, actual)| match (expected, actual) {
+                (TypeParameter::Integer(e), ConcreteParameter::Integer(v)) => {
+                    bind_integer(e, v, bindings)
+                }
+                (TypeParameter::Type(e), ConcreteParameter::Type(t)) => {
+                    bind_type(e, t, bindings, discrete)
+                }
+                _ => false,
+            })
+}
+
+fn bind_integer(expression: &str, value: i64, bindings: &mut Bindings) -> bool {
+    let expression = expression.trim();
+    if let Ok(literal) = expression.parse::<i64>() {
+        return literal == value;
+    }
+    if expression
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return match bindings.integers.get(expression) {
+            Some(bound) => *bound == value,
+            None => {
+                bindings.integers.insert(expression.to_string(), value);
+                true
+            }
+        };
+    }
+    // expressions over other parameters do not constrain the argument
+    true
+}
+
+pub fn derive_output_type(
+    variant: &ScalarFunctionVariant,
+    arguments: &[BoundArgument],
+    bindings: &Bindings,
+) -> Result<Type, String> {
+    let mut bindings = bindings.clone();
+    evaluator::run_program(&variant.return_type.program, &mut bindings.integers)?;
+    let output = instantiate(&variant.return_type.output, &bindings)?;
+    let nullable = match variant.nullability {
+        NullabilityHandling::Mirror => {
+            output.nullable()
+                || arguments
+                    .iter()
+                    .any(|a| matches!(a, BoundArgument::Value(t) if t.nullable()))
+        }
+        NullabilityHandling::DeclaredOutput | NullabilityHandling::Discrete => output.nullable(),
+    };
+    Ok(output.with_nullable(nullable))
+}
+
+// Produces a concrete type from a type expression by substituting the bound variables
+pub fn instantiate(expression: &TypeExpression, bindings: &Bindings) -> Result<Type, String> {
+    let nullable = expression.nullable;
+    if expression.is_any() {
+        return bindings
+            .types
+            .get(&expression.name)
+            .map(|t| t.clone().with_nullable(nullable))
+            .ok_or_else(|| format!("type variable {} is not bound", expression.name));
+    }
+
+    let integer = |i: usize| -> Result<i32, String> {
+        match expression.parameters.get(i) {
+            Some(TypeParameter::Integer(e)) => {
+                let value = evaluator::evaluate(e, &bindings.integers)?;
+                i32::try_from(value).map_err(|_| {
+                    format!(
+                        "integer parameter {} of {} is out of range: {}",
+                        i, expression.name, value
+                    )
+                })
+            }
+            _ => Err(format!(
+                "missing integer parameter {} of {}",
+                i, expression.name
+            )),
+        }
+    };
+    let nested = |i: usize| -> Result<Box<Type>, String> {
+        match expression.parameters.get(i) {
+            Some(TypeParameter::Type(t)) => instantiate(t, bindings).map(Box::new),
+            _ => Err(format!(
+                "missing type parameter {} of {}",
+                i, expression.name
+            )),
+        }
+    };
+
+    let t = match expression.name.as_str() {
+        "boolean" => Type::Bool { nullable },
+        "i8" => Type::I8 { nullable },
+        "i16" => Type::I16 { nullable },
+        "i32" => Type::I32 { nullable },
+        "i64" => Type::I64 { nullable },
+        "fp32" => Type::FP32 { nullable },
+        "fp64" => Type::FP64 { nullable },
+        "string" => Type::String { nullable },
+        "binary" => Type::Binary { nullable },
+        "timestamp" => Type::Timestamp { nullable },
+        "timestamp_tz" => Type::TimestampTz { nullable },
+        "date" => Type::Date { nullable },
+        "time" => Type::Time { nullable },
+        "interval_year" => Type::IntervalYear { nullable },
+        "interval_day" => Type::IntervalDay { nullable },
+        "uuid" => Type::UUID { nullable },
+        "fixedchar" => Type::FixedChar {
+            nullable,
+            length: integer(0)?,
+        },
+        "varchar" => Type::VarChar {
+            nullable,
+            length: integer(0)?,
+        },
+        "fixedbinary" => Type::FixedBinary {
+            nullable,
+            length: integer(0)?,
+        },
+        "decimal" => Type::Decimal {
+            nullable,
+            precision: integer(0)?,
+            scale: integer(1)?,
+        },
+        "struct" => Type::Struct {
+            nullable,
+            types: (0..expression.parameters.len())
+                .map(|i| nested(i).map(|t| *t))
+                .collect::<Result<Vec<_>, _>>()?,
+        },
+        "list" => Type::List {
+            nullable,
+            r#type: nested(0)?,
+        },
+        "map" => Type::Map {
+            nullable,
+            key: nested(0)?,
+            value: nested(1)?,
+        },
+        name => return Err(format!("cannot derive a concrete type for {}", name)),
+    };
+    Ok(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extensions::Extensions;
+    use substrait::text::simple_extensions::SimpleExtensions;
+
+    const FUNCTIONS: &str = r#"
+    scalar_functions:
+      - name: "add"
+        impls:
+          - args:
+              - { name: x, value: i32 }
+              - { name: y, value: i32 }
+            return: i32
+          - args:
+              - { name: x, value: i64 }
+              - { name: y, value: i64 }
+            return: i64
+      - name: "coalesce"
+        impls:
+          - args:
+              - { value: any1 }
+            variadic: { min: 2 }
+            return: any1
+      - name: "round"
+        impls:
+          - args:
+              - { name: x, value: fp64 }
+              - { name: rounding, options: [ TIE_TO_EVEN, FLOOR ] }
+            return: fp64
+    "#;
+
+    fn extensions() -> Extensions {
+        Extensions::try_from(serde_yaml::from_str::<SimpleExtensions>(FUNCTIONS).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn resolves_by_argument_types() {
+        let extensions = extensions();
+        let add = &extensions.scalar_functions[0];
+        let i64_nullable = Type::I64 { nullable: true };
+        let i64_required = Type::I64 { nullable: false };
+
+        let resolution = add
+            .resolve_overload(&[
+                BoundArgument::Value(&i64_nullable),
+                BoundArgument::Value(&i64_required),
+            ])
+            .unwrap();
+        assert_eq!(resolution.index, 1);
+        assert_eq!(resolution.output_type, Type::I64 { nullable: true });
+
+        let fp64 = Type::FP64 { nullable: false };
+        let error = add
+            .resolve_overload(&[BoundArgument::Value(&fp64), BoundArgument::Value(&fp64)])
+            .unwrap_err();
+        assert!(matches!(error, ResolutionError::NoMatch { .. }));
+    }
+
+    #[test]
+    fn binds_type_variables_across_variadic_arguments() {
+        let extensions = extensions();
+        let coalesce = &extensions.scalar_functions[1];
+        let i32_nullable = Type::I32 { nullable: true };
+        let i32_required = Type::I32 { nullable: false };
+        let string = Type::String { nullable: false };
+
+        let resolution = coalesce
+            .resolve_overload(&[
+                BoundArgument::Value(&i32_nullable),
+                BoundArgument::Value(&i32_required),
+            ])
+            .unwrap();
+        assert_eq!(resolution.output_type, Type::I32 { nullable: true });
+
+        assert!(coalesce
+            .resolve_overload(&[
+                BoundArgument::Value(&i32_required),
+                BoundArgument::Value(&string),
+            ])
+            .is_err());
+        assert!(coalesce
+            .resolve_overload(&[BoundArgument::Value(&i32_required)])
+            .is_err());
+    }
+
+    #[test]
+    fn allows_leaving_out_enumeration_arguments() {
+        let extensions = extensions();
+        let round = &extensions.scalar_functions[2];
+        let fp64 = Type::FP64 { nullable: false };
+
+        let resolution = round
+            .resolve_overload(&[BoundArgument::Value(&fp64), BoundArgument::Enum("floor")])
+            .unwrap();
+        assert_eq!(resolution.output_type, fp64);
+
+        let resolution = round
+            .resolve_overload(&[BoundArgument::Value(&fp64)])
+            .unwrap();
+        assert_eq!(resolution.output_type, fp64);
+
+        // given enumeration arguments must still be one of the options
+        let error = round
+            .resolve_overload(&[BoundArgument::Value(&fp64), BoundArgument::Enum("CEILING")])
+            .unwrap_err();
+        assert!(
+            matches!(error, ResolutionError::NoMatch { .. }),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn reports_out_of_range_parameters() {
+        let expression = TypeExpression::parse("decimal<P1 * 2, S1>").unwrap();
+        let bindings = Bindings {
+            types: HashMap::new(),
+            integers: [("P1".to_string(), 1 << 31), ("S1".to_string(), 2)]
+                .into_iter()
+                .collect(),
+        };
+        assert_eq!(
+            instantiate(&expression, &bindings).unwrap_err(),
+            "integer parameter 0 of decimal is out of range: 4294967296"
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+use substrait::text::simple_extensions;
+
+// Properties shared by the scalar, aggregate and window function variants
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NullabilityHandling {
+    // output is nullable if any input is nullable
+    Mirror,
+    // output nullability is exactly the declared return type
+    DeclaredOutput,
+    // nullability of arguments and return type is part of the signature
+    Discrete,
+}
+
+impl From<Option<simple_extensions::NullabilityHandling>> for NullabilityHandling {
+    fn from(nh: Option<simple_extensions::NullabilityHandling>) -> Self {
+        match nh {
+            None | Some(simple_extensions::NullabilityHandling::Mirror) => {
+                NullabilityHandling::Mirror
+            }
+            Some(simple_extensions::NullabilityHandling::DeclaredOutput) => {
+                NullabilityHandling::DeclaredOutput
+            }
+            Some(simple_extensions::NullabilityHandling::Discrete) => NullabilityHandling::Discrete,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ParameterConsistency {
+    // all variadic arguments must have the same type
+    Consistent,
+    // variadic arguments may bind to different types
+    Inconsistent,
+}
+
+// Describes how many times the last argument of a variant may be repeated
+#[derive(Clone, Debug)]
+pub struct VariadicBehaviour {
+    pub min: u32,
+    pub max: Option<u32>,
+    pub parameter_consistency: ParameterConsistency,
+}
+
+impl From<simple_extensions::VariadicBehavior> for VariadicBehaviour {
+    fn from(vb: simple_extensions::VariadicBehavior) -> Self {
+        VariadicBehaviour {
+            min: vb.min.map(|min| min as u32).unwrap_or(0),
+            max: vb.max.map(|max| max as u32),
+            parameter_consistency: match vb.parameter_consistency {
+                None
+                | Some(simple_extensions::VariadicBehaviorParameterConsistency::Consistent) => {
+                    ParameterConsistency::Consistent
+                }
+                Some(simple_extensions::VariadicBehaviorParameterConsistency::Inconsistent) => {
+                    ParameterConsistency::Inconsistent
+                }
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FunctionOption {
+    pub description: Option<String>,
+    pub values: Vec<String>,
+}
+
+pub fn function_options(
+    options: Option<simple_extensions::Options>,
+) -> BTreeMap<String, FunctionOption> {
+    options
+        .map(|o| o.0)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, value)| {
+            (
+                name,
+                FunctionOption {
+                    description: value.description,
+                    values: value.values,
+                },
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,376 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use substrait::text::simple_extensions::SimpleExtensions;
+
+use crate::extensions::scalar_function::ScalarFunctionVariant;
+use crate::extensions::signature::VariantReference;
+use crate::extensions::Extensions;
+use crate::plans::expressions::Function;
+
+#[derive(Debug)]
+pub enum RegistryError {
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    Yaml {
+        uri: String,
+        error: serde_yaml::Error,
+    },
+    // the YAML parsed, but contains type expressions or return types that cannot be modelled
+    Extension {
+        uri: String,
+        error: String,
+    },
+    UnknownUri(String),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::Io { path, error } => {
+                write!(f, "cannot read {}: {}", path.display(), error)
+            }
+            RegistryError::Yaml { uri, error } => {
+                write!(f, "cannot parse extension {}: {}", uri, error)
+            }
+            RegistryError::Extension { uri, error } => {
+                write!(f, "invalid extension {}: {}", uri, error)
+            }
+            RegistryError::UnknownUri(uri) => write!(f, "extension {} is not registered", uri),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+const STANDARD_BLOB_PREFIX: &str =
+    "https://github.com/substrait-io/substrait/blob/main/extensions/";
+const STANDARD_RAW_PREFIX: &str = "https://github.com/substrait-io/substrait/raw/main/extensions/";
+
+// The URIs producers commonly refer to a standard extension file by
+pub fn standard_uris(file_name: &str) -> [String; 3] {
+    [
+        format!("/{}", file_name),
+        format!("{}{}", STANDARD_BLOB_PREFIX, file_name),
+        format!("{}{}", STANDARD_RAW_PREFIX, file_name),
+    ]
+}
+
+// Maps extension URIs to loaded extension files. Plans refer to the same file by different
+// URIs (e.g. `/functions_arithmetic.yaml` or its full GitHub URL), so additional URIs can be
+// registered as aliases of an already loaded one.
+#[derive(Debug, Default)]
+pub struct ExtensionRegistry {
+    extensions: HashMap<String, Extensions>,
+    aliases: HashMap<String, String>,
+}
+
+impl ExtensionRegistry {
+    pub fn new() -> ExtensionRegistry {
+        ExtensionRegistry::default()
+    }
+
+    // The core extension files shipped with the pinned substrait crate. Each file is registered
+    // under its versioned URL and aliased to `/<file name>` as well as the URLs of the file on
+    // the main branch, so it works with the URIs emitted by most producers.
+    pub fn standard() -> ExtensionRegistry {
+        let mut registry = ExtensionRegistry::new();
+        for (url, simple_extensions) in substrait::extensions::EXTENSIONS.iter() {
+            let uri = url.as_str();
+            let extensions = Extensions::try_from(simple_extensions.clone())
+                .unwrap_or_else(|e| panic!("standard extension {} is invalid: {}", uri, e));
+            registry.register(uri, extensions);
+
+            let file_name = uri.rsplit('/').next().expect("extension url has a path");
+            for alias in standard_uris(file_name) {
+                registry
+                    .alias(&alias, uri)
+                    .expect("standard extension was just registered");
+            }
+        }
+        registry
+    }
+
+    pub fn register(&mut self, uri: &str, extensions: Extensions) {
+        self.aliases.remove(uri);
+        self.extensions.insert(uri.to_string(), extensions);
+    }
+
+    pub fn register_yaml(&mut self, uri: &str, yaml: &str) -> Result<(), RegistryError> {
+        let simple_extensions =
+            serde_yaml::from_str::<SimpleExtensions>(yaml).map_err(|error| {
+                RegistryError::Yaml {
+                    uri: uri.to_string(),
+                    error,
+                }
+            })?;
+        let extensions =
+            Extensions::try_from(simple_extensions).map_err(|error| RegistryError::Extension {
+                uri: uri.to_string(),
+                error,
+            })?;
+        self.register(uri, extensions);
+        Ok(())
+    }
+
+    // Registers the file under `/<file name>`, the URI most producers use for local files
+    pub fn load_file(&mut self, path: &Path) -> Result<String, RegistryError> {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let uri = format!("/{}", file_name);
+        self.load_file_as(&uri, path)?;
+        Ok(uri)
+    }
+
+    pub fn load_file_as(&mut self, uri: &str, path: &Path) -> Result<(), RegistryError> {
+        let yaml = fs::read_to_string(path).map_err(|error| RegistryError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        self.register_yaml(uri, &yaml)
+    }
+
+    // Loads every .yaml/.yml file in the directory, returning the registered URIs
+    pub fn load_directory(&mut self, dir: &Path) -> Result<Vec<String>, RegistryError> {
+        let io_error = |error| RegistryError::Io {
+            path: dir.to_path_buf(),
+            error,
+        };
+        let mut paths = fs::read_dir(dir)
+            .map_err(io_error)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(io_error)?;
+        paths.sort();
+
+        paths
+            .iter()
+            .filter(|path| {
+                path.is_file()
+                    && matches!(
+                        path.extension().and_then(|e| e.to_str()),
+                        Some("yaml") | Some("yml")
+                    )
+            })
+            .map(|path| self.load_file(path))
+            .collect()
+    }
+
+    pub fn alias(&mut self, alias: &str, uri: &str) -> Result<(), RegistryError> {
+        let canonical = self
+            .canonical_uri(uri)
+            .ok_or_else(|| RegistryError::UnknownUri(uri.to_string()))?
+            .to_string();
+        if alias != canonical {
+            self.aliases.insert(alias.to_string(), canonical);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, uri: &str) -> Option<&Extensions> {
+        self.canonical_uri(uri)
+            .and_then(|canonical| self.extensions.get(canonical))
+    }
+
+    // The scalar function variant a decoded function was resolved to
+    pub fn scalar_variant(&self, function: &Function) -> Option<&ScalarFunctionVariant> {
+        match function.variant {
+            Some(VariantReference::Scalar(index)) => self
+                .get(&function.extension.0)
+                .and_then(|extensions| extensions.scalar_variant(function.name(), index)),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, uri: &str) -> bool {
+        self.get(uri).is_some()
+    }
+
+    // Registered URIs, excluding aliases
+    pub fn uris(&self) -> impl Iterator<Item = &String> {
+        self.extensions.keys()
+    }
+
+    fn canonical_uri<'a>(&'a self, uri: &'a str) -> Option<&'a str> {
+        if self.extensions.contains_key(uri) {
+            return Some(uri);
+        }
+        self.aliases.get(uri).map(|canonical| canonical.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extensions::signature::{argument_code, Signature};
+    use crate::plans::expressions::{FunctionSignature, URI};
+
+    const FUNCTIONS: &str = r#"
+    scalar_functions:
+      - name: "add"
+        impls:
+          - args:
+              - { name: x, value: i32 }
+              - { name: y, value: i32 }
+            return: i32
+    "#;
+
+    // A directory of its own under the system temp directory, so tests can run in parallel
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rustrait-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn registers_yaml() {
+        let mut registry = ExtensionRegistry::new();
+        registry.register_yaml("/custom.yaml", FUNCTIONS).unwrap();
+        assert!(registry.contains("/custom.yaml"));
+        assert!(!registry.contains("/other.yaml"));
+        let extensions = registry.get("/custom.yaml").unwrap();
+        assert_eq!(extensions.scalar_functions[0].name, "add");
+        assert_eq!(registry.uris().collect::<Vec<_>>(), vec!["/custom.yaml"]);
+    }
+
+    #[test]
+    fn reports_invalid_extensions() {
+        let mut registry = ExtensionRegistry::new();
+        let error = registry
+            .register_yaml("/broken.yaml", "scalar_functions: [")
+            .unwrap_err();
+        assert!(matches!(error, RegistryError::Yaml { .. }), "{}", error);
+
+        let malformed = r#"
+        scalar_functions:
+          - name: "add"
+            impls:
+              - args:
+                  - { value: "list<i32" }
+                return: i32
+        "#;
+        let error = registry
+            .register_yaml("/malformed.yaml", malformed)
+            .unwrap_err();
+        assert!(
+            matches!(error, RegistryError::Extension { .. }),
+            "{}",
+            error
+        );
+        assert!(error.to_string().contains("function add"), "{}", error);
+
+        let object_return = r#"
+        scalar_functions:
+          - name: "point"
+            impls:
+              - args:
+                  - { value: i32 }
+                return: { x: i32, y: i32 }
+        "#;
+        let error = registry
+            .register_yaml("/object.yaml", object_return)
+            .unwrap_err();
+        assert!(
+            matches!(error, RegistryError::Extension { .. }),
+            "{}",
+            error
+        );
+        assert!(!registry.contains("/malformed.yaml"));
+        assert!(!registry.contains("/object.yaml"));
+    }
+
+    #[test]
+    fn loads_files_by_file_name() {
+        let dir = temp_dir("load-file");
+        let path = dir.join("custom_functions.yaml");
+        fs::write(&path, FUNCTIONS).unwrap();
+
+        let mut registry = ExtensionRegistry::new();
+        assert_eq!(registry.load_file(&path).unwrap(), "/custom_functions.yaml");
+        assert!(registry.contains("/custom_functions.yaml"));
+
+        registry
+            .load_file_as("https://example.com/custom.yaml", &path)
+            .unwrap();
+        assert!(registry.contains("https://example.com/custom.yaml"));
+
+        let error = registry.load_file(&dir.join("missing.yaml")).unwrap_err();
+        assert!(matches!(error, RegistryError::Io { .. }), "{}", error);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn loads_yaml_files_of_a_directory() {
+        let dir = temp_dir("load-directory");
+        fs::write(dir.join("b.yml"), FUNCTIONS).unwrap();
+        fs::write(dir.join("a.yaml"), FUNCTIONS).unwrap();
+        fs::write(dir.join("notes.txt"), "not an extension").unwrap();
+        fs::create_dir_all(dir.join("nested.yaml")).unwrap();
+
+        let mut registry = ExtensionRegistry::new();
+        let uris = registry.load_directory(&dir).unwrap();
+        assert_eq!(uris, vec!["/a.yaml", "/b.yml"]);
+        assert!(registry.contains("/a.yaml"));
+        assert!(registry.contains("/b.yml"));
+        assert!(!registry.contains("/notes.txt"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn aliases_registered_uris() {
+        let mut registry = ExtensionRegistry::new();
+        registry.register_yaml("/custom.yaml", FUNCTIONS).unwrap();
+        registry
+            .alias("https://example.com/custom.yaml", "/custom.yaml")
+            .unwrap();
+        // aliases of aliases resolve to the registered URI
+        registry
+            .alias("urn:custom", "https://example.com/custom.yaml")
+            .unwrap();
+        for uri in ["https://example.com/custom.yaml", "urn:custom"] {
+            let extensions = registry.get(uri).unwrap();
+            assert_eq!(extensions.scalar_functions[0].name, "add");
+        }
+        assert_eq!(registry.uris().count(), 1);
+
+        let error = registry.alias("urn:other", "/unknown.yaml").unwrap_err();
+        assert!(matches!(error, RegistryError::UnknownUri(_)), "{}", error);
+
+        // registering under an alias replaces the alias
+        registry.register_yaml("urn:custom", FUNCTIONS).unwrap();
+        assert_eq!(registry.uris().count(), 2);
+    }
+
+    #[test]
+    fn standard_registry_resolves_common_uris() {
+        let registry = ExtensionRegistry::standard();
+        let versioned = registry
+            .uris()
+            .find(|uri| uri.ends_with("/extensions/functions_arithmetic.yaml"))
+            .expect("arithmetic functions are a standard extension")
+            .clone();
+        let signature = Signature::parse("add:i32_i32").unwrap();
+        for uri in [
+            versioned.as_str(),
+            "/functions_arithmetic.yaml",
+            "https://github.com/substrait-io/substrait/blob/main/extensions/functions_arithmetic.yaml",
+            "https://github.com/substrait-io/substrait/raw/main/extensions/functions_arithmetic.yaml",
+        ] {
+            let extensions = registry.get(uri).unwrap();
+            let reference = extensions.resolve(&signature).unwrap();
+            let function = Function {
+                signature: FunctionSignature(signature.encode()),
+                extension: URI(uri.to_string()),
+                variant: reference,
+            };
+            let variant = registry.scalar_variant(&function).unwrap();
+            let arguments: Vec<String> = variant.arguments.iter().map(argument_code).collect();
+            assert_eq!(arguments, vec!["i32", "i32"], "resolved through {}", uri);
+        }
+    }
+}
@@ -1,45 +1,66 @@
+use std::collections::BTreeMap;
 use substrait::text::simple_extensions;
 
-use crate::types::Type;
+use crate::extensions::arguments::Argument;
+use crate::extensions::properties::{
+    function_options, FunctionOption, NullabilityHandling, VariadicBehaviour,
+};
+use crate::extensions::type_expression::ReturnType;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ScalarFunction {
     pub name: String,
+    pub description: Option<String>,
     pub variants: Vec<ScalarFunctionVariant>,
 }
 
-impl From<simple_extensions::ScalarFunction> for ScalarFunction {
-    fn from(sf: simple_extensions::ScalarFunction) -> Self {
-        return ScalarFunction {
+impl TryFrom<simple_extensions::ScalarFunction> for ScalarFunction {
+    type Error = String;
+
+    fn try_from(sf: simple_extensions::ScalarFunction) -> Result<Self, Self::Error> {
+        let variants = sf
+            .impls
+            .into_iter()
+            .map(ScalarFunctionVariant::try_from)
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("function {}: {}", sf.name, e))?;
+        Ok(ScalarFunction {
             name: sf.name,
-            variants: sf
-                .impls
-                .into_iter()
-                .map(|sfii| ScalarFunctionVariant::from(sfii))
-                .collect(),
-        };
+            description: sf.description,
+            variants,
+        })
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ScalarFunctionVariant {
-    pub arguments: Vec<String>,
-    // pub options: Vec<Option>,
-    // pub nullability,
-    // pub variadicBehaviour,
-    // TODO: moar
-    pub return_type: Type,
+    pub arguments: Vec<Argument>,
+    pub options: BTreeMap<String, FunctionOption>,
+    pub nullability: NullabilityHandling,
+    pub variadic: Option<VariadicBehaviour>,
+    pub deterministic: bool,
+    pub session_dependent: bool,
+    pub return_type: ReturnType,
 }
 
-impl From<simple_extensions::ScalarFunctionImplsItem> for ScalarFunctionVariant {
-    fn from(sfii: simple_extensions::ScalarFunctionImplsItem) -> Self {
-        let return_type = match sfii.return_.0 {
-            simple_extensions::Type::Variant0(str) => Type::from(str.as_str()),
-            simple_extensions::Type::Variant1(_) => panic!("what even is this?!?!?"),
-        };
-        ScalarFunctionVariant {
-            arguments: vec![],
-            return_type: return_type,
-        }
+impl TryFrom<simple_extensions::ScalarFunctionImplsItem> for ScalarFunctionVariant {
+    type Error = String;
+
+    fn try_from(sfii: simple_extensions::ScalarFunctionImplsItem) -> Result<Self, Self::Error> {
+        Ok(ScalarFunctionVariant {
+            arguments: sfii
+                .args
+                .map(|args| args.0)
+                .unwrap_or_default()
+                .into_iter()
+                .map(Argument::try_from)
+                .collect::<Result<_, _>>()?,
+            options: function_options(sfii.options),
+            nullability: NullabilityHandling::from(sfii.nullability),
+            variadic: sfii.variadic.map(VariadicBehaviour::from),
+            deterministic: sfii.deterministic.map(|d| d.0).unwrap_or(true),
+            session_dependent: sfii.session_dependent.map(|sd| sd.0).unwrap_or(false),
+            return_type: ReturnType::try_from(sfii.return_.0)?,
+        })
     }
 }
@@ -0,0 +1,334 @@
+use std::fmt;
+
+use crate::extensions::arguments::Argument;
+use crate::extensions::type_expression::TypeExpression;
+use crate::extensions::Extensions;
+use crate::types::Type;
+
+// Short codes used for argument types in compound function names such as `add:i32_i32`
+const SHORT_CODES: [(&str, &str); 28] = [
+    ("boolean", "bool"),
+    ("i8", "i8"),
+    ("i16", "i16"),
+    ("i32", "i32"),
+    ("i64", "i64"),
+    ("fp32", "fp32"),
+    ("fp64", "fp64"),
+    ("string", "str"),
+    ("binary", "vbin"),
+    ("timestamp", "ts"),
+    ("timestamp_tz", "tstz"),
+    ("date", "date"),
+    ("time", "time"),
+    ("interval_year", "iyear"),
+    ("interval_day", "iday"),
+    ("interval_compound", "icompound"),
+    ("uuid", "uuid"),
+    ("fixedchar", "fchar"),
+    ("varchar", "vchar"),
+    ("fixedbinary", "fbin"),
+    ("decimal", "dec"),
+    ("precision_time", "pt"),
+    ("precision_timestamp", "pts"),
+    ("precision_timestamp_tz", "ptstz"),
+    ("struct", "struct"),
+    ("list", "list"),
+    ("map", "map"),
+    ("any", "any"),
+];
+
+// Enum arguments are encoded as `req` or `opt` rather than by their type
+const REQUIRED_ENUM: &str = "req";
+const OPTIONAL_ENUM: &str = "opt";
+
+// A function name as referenced by an extension function declaration. Compound names list the
+// short codes of the argument types after a colon, simple names only carry the function name.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Signature {
+    pub name: String,
+    pub arguments: Option<Vec<String>>,
+}
+
+impl Signature {
+    pub fn parse(s: &str) -> Result<Signature, String> {
+        let (name, arguments) = match s.split_once(':') {
+            None => (s, None),
+            Some((name, arguments)) => (name, Some(arguments)),
+        };
+        if name.is_empty() {
+            return Err(format!("function signature {} has no name", s));
+        }
+
+        let arguments = match arguments {
+            None => None,
+            Some("") => Some(vec![]),
+            Some(arguments) => Some(parse_argument_codes(arguments, s)?),
+        };
+
+        Ok(Signature {
+            name: name.to_string(),
+            arguments,
+        })
+    }
+
+    pub fn encode(&self) -> String {
+        match &self.arguments {
+            None => self.name.clone(),
+            Some(arguments) => format!("{}:{}", self.name, arguments.join("_")),
+        }
+    }
+
+    pub fn from_arguments(name: &str, arguments: &[Argument]) -> Signature {
+        Signature {
+            name: name.to_string(),
+            arguments: Some(arguments.iter().map(argument_code).collect()),
+        }
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.encode())
+    }
+}
+
+fn parse_argument_codes(arguments: &str, signature: &str) -> Result<Vec<String>, String> {
+    let mut codes: Vec<String> = vec![];
+    // whether the last code is a user-defined type name, which may itself contain underscores
+    let mut in_user_defined = false;
+    for segment in arguments.split('_') {
+        // parameters of compound types are not part of the signature
+        let segment = match segment.split_once('<') {
+            Some((base, _)) => base,
+            None => segment,
+        }
+        .trim_end_matches('?');
+        if segment.starts_with("u!") {
+            codes.push(segment.to_string());
+            in_user_defined = true;
+            continue;
+        }
+
+        // user-defined names are kept as written, built-in codes are case insensitive
+        let code = segment.to_lowercase();
+        if is_short_code(&code) {
+            codes.push(code);
+            in_user_defined = false;
+        } else if let (true, Some(name)) = (in_user_defined, codes.last_mut()) {
+            name.push('_');
+            name.push_str(segment);
+        } else {
+            return Err(format!(
+                "unknown argument type {} in function signature {}",
+                code, signature
+            ));
+        }
+    }
+    Ok(codes)
+}
+
+fn is_short_code(code: &str) -> bool {
+    code == REQUIRED_ENUM
+        || code == OPTIONAL_ENUM
+        || code.starts_with("u!")
+        || SHORT_CODES.iter().any(|(_, short)| *short == code)
+}
+
+pub fn argument_code(argument: &Argument) -> String {
+    match argument {
+        Argument::Value { value, .. } => type_expression_code(value),
+        Argument::Enum { .. } => REQUIRED_ENUM.to_string(),
+        Argument::Type { .. } => "any".to_string(),
+    }
+}
+
+pub fn type_expression_code(t: &TypeExpression) -> String {
+    if t.name.starts_with("u!") {
+        return t.name.clone();
+    }
+    if t.is_any() {
+        return "any".to_string();
+    }
+    type_name_code(&t.name)
+}
+
+pub fn type_code(t: &Type) -> String {
+    match t {
+        Type::UserDefined { name, .. } => format!("u!{}", name),
+        _ => type_name_code(&t.name()),
+    }
+}
+
+fn type_name_code(name: &str) -> String {
+    SHORT_CODES
+        .iter()
+        .find(|(type_name, _)| *type_name == name)
+        .map(|(_, short)| short.to_string())
+        .unwrap_or_else(|| name.to_string())
+}
+
+// The kind of function a signature resolved to and the index of the matching variant
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VariantReference {
+    Scalar(usize),
+    Aggregate(usize),
+    Window(usize),
+}
+
+impl Extensions {
+    // Finds the variant a signature refers to. Simple names only resolve when the function has
+    // a single variant, otherwise None is returned and the variant has to be picked by type.
+    pub fn resolve(&self, signature: &Signature) -> Result<Option<VariantReference>, String> {
+        let candidates: Vec<(VariantReference, Signature)> = self
+            .scalar_functions
+            .iter()
+            .filter(|f| f.name == signature.name)
+            .flat_map(|f| {
+                f.variants.iter().enumerate().map(|(i, v)| {
+                    (
+                        VariantReference::Scalar(i),
+                        Signature::from_arguments(&f.name, &v.arguments),
+                    )
+                })
+            })
+            .chain(
+                self.aggregate_functions
+                    .iter()
+                    .filter(|f| f.name == signature.name)
+                    .flat_map(|f| {
+                        f.variants.iter().enumerate().map(|(i, v)| {
+                            (
+                                VariantReference::Aggregate(i),
+                                Signature::from_arguments(&f.name, &v.arguments),
+                            )
+                        })
+                    }),
+            )
+            .chain(
+                self.window_functions
+                    .iter()
+                    .filter(|f| f.name == signature.name)
+                    .flat_map(|f| {
+                        f.variants.iter().enumerate().map(|(i, v)| {
+                            (
+                                VariantReference::Window(i),
+                                Signature::from_arguments(&f.name, &v.arguments),
+                            )
+                        })
+                    }),
+            )
+            .collect();
+
+        if candidates.is_empty() {
+            return Err(format!("function {} is not defined", signature.name));
+        }
+
+        match &signature.arguments {
+            None if candidates.len() == 1 => Ok(Some(candidates[0].0)),
+            None => Ok(None),
+            Some(arguments) => candidates
+                .iter()
+                .find(|(_, candidate)| {
+                    candidate
+                        .arguments
+                        .as_ref()
+                        .is_some_and(|candidate_arguments| {
+                            candidate_arguments.len() == arguments.len()
+                                && candidate_arguments.iter().zip(arguments).all(|(c, a)| {
+                                    c == a || (c == REQUIRED_ENUM && a == OPTIONAL_ENUM)
+                                })
+                        })
+                })
+                .map(|(reference, _)| Some(*reference))
+                .ok_or_else(|| {
+                    format!(
+                        "no variant matches {}, candidates are: {}",
+                        signature,
+                        candidates
+                            .iter()
+                            .map(|(_, candidate)| candidate.encode())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use substrait::text::simple_extensions::SimpleExtensions;
+
+    #[test]
+    fn parses_compound_names() {
+        let signature = Signature::parse("add:i32_i32").unwrap();
+        assert_eq!(signature.name, "add");
+        assert_eq!(
+            signature.arguments,
+            Some(vec!["i32".to_string(), "i32".to_string()])
+        );
+        assert_eq!(signature.encode(), "add:i32_i32");
+
+        let signature = Signature::parse("extract:req_pts").unwrap();
+        assert_eq!(
+            signature.arguments,
+            Some(vec!["req".to_string(), "pts".to_string()])
+        );
+
+        assert_eq!(Signature::parse("count").unwrap().arguments, None);
+        assert!(Signature::parse("add:int_int").is_err());
+    }
+
+    #[test]
+    fn keeps_user_defined_names_whole() {
+        let signature = Signature::parse("distance:u!Point_u!Point").unwrap();
+        assert_eq!(
+            signature.arguments,
+            Some(vec!["u!Point".to_string(), "u!Point".to_string()])
+        );
+
+        let signature = Signature::parse("scale:u!my_type_I64_u!My_Other_type").unwrap();
+        assert_eq!(
+            signature.arguments,
+            Some(vec![
+                "u!my_type".to_string(),
+                "i64".to_string(),
+                "u!My_Other_type".to_string()
+            ])
+        );
+        assert_eq!(signature.encode(), "scale:u!my_type_i64_u!My_Other_type");
+    }
+
+    #[test]
+    fn resolves_user_defined_arguments() {
+        let yaml = r#"
+        scalar_functions:
+          - name: "distance"
+            impls:
+              - args:
+                  - value: u!Point
+                  - value: u!geo_point
+                return: fp64
+        "#;
+        let extensions =
+            Extensions::try_from(serde_yaml::from_str::<SimpleExtensions>(yaml).unwrap()).unwrap();
+        let signature = Signature::parse("distance:u!Point_u!geo_point").unwrap();
+        assert_eq!(
+            extensions.resolve(&signature).unwrap(),
+            Some(VariantReference::Scalar(0))
+        );
+        assert!(extensions
+            .resolve(&Signature::parse("distance:u!point_u!geo_point").unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn encodes_type_expressions() {
+        let codes: Vec<String> = ["decimal<P1,S1>", "varchar<L1>?", "any1", "list<any1>", "T"]
+            .iter()
+            .map(|t| type_expression_code(&TypeExpression::parse(t).unwrap()))
+            .collect();
+        assert_eq!(codes, vec!["dec", "vchar", "any", "list", "any"]);
+    }
+}
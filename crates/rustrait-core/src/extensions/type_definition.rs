@@ -0,0 +1,305 @@
+use substrait::text::simple_extensions;
+
+use crate::extensions::type_expression::TypeExpression;
+use crate::types::TypeParameter;
+
+#[derive(Debug)]
+pub struct TypeDefinition {
+    pub name: String,
+    pub structure: Option<TypeStructure>,
+    pub parameters: Vec<TypeParameterDefinition>,
+    // when set, the last parameter may be repeated
+    pub variadic: bool,
+}
+
+// The physical representation of a user-defined type in terms of other types
+#[derive(Debug)]
+pub enum TypeStructure {
+    Type(TypeExpression),
+    // field names mapped to their structure, in declaration order
+    Struct(Vec<(String, TypeStructure)>),
+}
+
+#[derive(Debug)]
+pub struct TypeParameterDefinition {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub kind: TypeParameterKind,
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+    pub options: Vec<String>,
+    pub optional: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TypeParameterKind {
+    DataType,
+    Boolean,
+    Integer,
+    Enumeration,
+    String,
+}
+
+impl TryFrom<simple_extensions::SimpleExtensionsTypesItem> for TypeDefinition {
+    type Error = String;
+
+    fn try_from(ti: simple_extensions::SimpleExtensionsTypesItem) -> Result<Self, Self::Error> {
+        let structure = ti
+            .structure
+            .map(TypeStructure::try_from)
+            .transpose()
+            .map_err(|e| format!("type {}: {}", ti.name, e))?;
+        Ok(TypeDefinition {
+            name: ti.name,
+            structure,
+            parameters: ti
+                .parameters
+                .map(|p| p.0)
+                .unwrap_or_default()
+                .into_iter()
+                .map(TypeParameterDefinition::from)
+                .collect(),
+            variadic: ti.variadic.unwrap_or(false),
+        })
+    }
+}
+
+impl TryFrom<simple_extensions::Type> for TypeStructure {
+    type Error = String;
+
+    fn try_from(t: simple_extensions::Type) -> Result<Self, Self::Error> {
+        match t {
+            simple_extensions::Type::Variant0(str) => {
+                TypeExpression::parse(&str).map(TypeStructure::Type)
+            }
+            simple_extensions::Type::Variant1(fields) => {
+                TypeStructure::try_from(serde_json::Value::Object(fields))
+            }
+        }
+    }
+}
+
+impl TryFrom<serde_json::Value> for TypeStructure {
+    type Error = String;
+
+    fn try_from(v: serde_json::Value) -> Result<Self, Self::Error> {
+        match v {
+            serde_json::Value::String(str) => TypeExpression::parse(&str).map(TypeStructure::Type),
+            serde_json::Value::Object(fields) => fields
+                .into_iter()
+                .map(|(name, value)| TypeStructure::try_from(value).map(|s| (name, s)))
+                .collect::<Result<_, _>>()
+                .map(TypeStructure::Struct),
+            _ => Err(format!("cannot produce type structure from: {}", v)),
+        }
+    }
+}
+
+impl From<simple_extensions::TypeParamDefsItem> for TypeParameterDefinition {
+    fn from(tp: simple_extensions::TypeParamDefsItem) -> Self {
+        TypeParameterDefinition {
+            name: tp.name,
+            description: tp.description,
+            kind: match tp.type_ {
+                simple_extensions::TypeParamDefsItemType::DataType => TypeParameterKind::DataType,
+                simple_extensions::TypeParamDefsItemType::Boolean => TypeParameterKind::Boolean,
+                simple_extensions::TypeParamDefsItemType::Integer => TypeParameterKind::Integer,
+                simple_extensions::TypeParamDefsItemType::Enumeration => {
+                    TypeParameterKind::Enumeration
+                }
+                simple_extensions::TypeParamDefsItemType::String => TypeParameterKind::String,
+            },
+            min: tp.min.map(|min| min as i64),
+            max: tp.max.map(|max| max as i64),
+            options: tp.options.map(|o| o.0).unwrap_or_default(),
+            optional: tp.optional.unwrap_or(false),
+        }
+    }
+}
+
+impl TypeDefinition {
+    // Checks the parameters of a decoded user-defined type against this definition
+    pub fn check(&self, parameters: &[TypeParameter]) -> Result<(), String> {
+        let required = self.parameters.iter().filter(|p| !p.optional).count();
+        if parameters.len() < required {
+            return Err(format!(
+                "type {} expects at least {} parameters, found {}",
+                self.name,
+                required,
+                parameters.len()
+            ));
+        }
+        if !self.variadic && parameters.len() > self.parameters.len() {
+            return Err(format!(
+                "type {} expects at most {} parameters, found {}",
+                self.name,
+                self.parameters.len(),
+                parameters.len()
+            ));
+        }
+
+        for (i, parameter) in parameters.iter().enumerate() {
+            let definition = match self.parameters.get(i).or(self.parameters.last()) {
+                Some(definition) => definition,
+                None => break,
+            };
+            definition
+                .check(parameter)
+                .map_err(|e| format!("parameter {} of type {}: {}", i, self.name, e))?;
+        }
+        Ok(())
+    }
+}
+
+impl TypeParameterDefinition {
+    fn check(&self, parameter: &TypeParameter) -> Result<(), String> {
+        match (self.kind, parameter) {
+            (_, TypeParameter::Null) if self.optional => Ok(()),
+            (TypeParameterKind::DataType, TypeParameter::DataType(_)) => Ok(()),
+            (TypeParameterKind::Boolean, TypeParameter::Boolean(_)) => Ok(()),
+            (TypeParameterKind::String, TypeParameter::String(_)) => Ok(()),
+            (TypeParameterKind::Integer, TypeParameter::Integer(i)) => {
+                if self.min.is_some_and(|min| *i < min) || self.max.is_some_and(|max| *i > max) {
+                    return Err(format!(
+                        "{} is outside of the range [{:?}, {:?}]",
+                        i, self.min, self.max
+                    ));
+                }
+                Ok(())
+            }
+            (TypeParameterKind::Enumeration, TypeParameter::Enum(e)) => {
+                if !self.options.is_empty() && !self.options.contains(e) {
+                    return Err(format!("{} is not one of {:?}", e, self.options));
+                }
+                Ok(())
+            }
+            (kind, parameter) => Err(format!("expected {:?}, found {:?}", kind, parameter)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Type;
+
+    fn definition(yaml: &str) -> TypeDefinition {
+        TypeDefinition::try_from(
+            serde_yaml::from_str::<simple_extensions::SimpleExtensionsTypesItem>(yaml).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn keeps_struct_fields_in_declaration_order() {
+        let point = definition(
+            r#"
+            name: point
+            structure:
+              y: fp64
+              x: fp64
+              label: { text: string, size: i32 }
+            "#,
+        );
+        let Some(TypeStructure::Struct(fields)) = &point.structure else {
+            panic!("expected a struct, found {:?}", point.structure);
+        };
+        let names: Vec<_> = fields.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["y", "x", "label"]);
+        let Some((_, TypeStructure::Struct(label))) = fields.last() else {
+            panic!("expected a nested struct, found {:?}", fields.last());
+        };
+        let names: Vec<_> = label.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["text", "size"]);
+    }
+
+    #[test]
+    fn checks_parameter_count() {
+        let vector = definition(
+            r#"
+            name: vector
+            parameters:
+              - { name: T, type: dataType }
+              - { name: N, type: integer, optional: true }
+            "#,
+        );
+        let element = TypeParameter::DataType(Type::FP64 { nullable: false });
+        assert!(vector.check(std::slice::from_ref(&element)).is_ok());
+        assert!(vector
+            .check(&[element.clone(), TypeParameter::Integer(3)])
+            .is_ok());
+        assert_eq!(
+            vector.check(&[]),
+            Err("type vector expects at least 1 parameters, found 0".to_string())
+        );
+        assert_eq!(
+            vector.check(&[element.clone(), TypeParameter::Integer(3), element.clone()]),
+            Err("type vector expects at most 2 parameters, found 3".to_string())
+        );
+    }
+
+    #[test]
+    fn repeats_the_last_parameter_of_variadic_types() {
+        let tuple = definition(
+            r#"
+            name: tuple
+            parameters:
+              - { name: T, type: dataType }
+            variadic: true
+            "#,
+        );
+        let element = TypeParameter::DataType(Type::I32 { nullable: false });
+        assert!(tuple
+            .check(&[element.clone(), element.clone(), element])
+            .is_ok());
+        assert_eq!(
+            tuple.check(&[
+                TypeParameter::DataType(Type::I32 { nullable: false }),
+                TypeParameter::Boolean(true)
+            ]),
+            Err("parameter 1 of type tuple: expected DataType, found Boolean(true)".to_string())
+        );
+    }
+
+    #[test]
+    fn checks_parameter_values() {
+        let grid = definition(
+            r#"
+            name: grid
+            parameters:
+              - { name: size, type: integer, min: 1, max: 10 }
+              - { name: order, type: enumeration, options: [ROW, COLUMN] }
+              - { name: label, type: string, optional: true }
+            "#,
+        );
+        let row = TypeParameter::Enum("ROW".to_string());
+        assert!(grid
+            .check(&[TypeParameter::Integer(10), row.clone()])
+            .is_ok());
+        assert!(grid
+            .check(&[TypeParameter::Integer(1), row.clone(), TypeParameter::Null])
+            .is_ok());
+        assert_eq!(
+            grid.check(&[TypeParameter::Integer(11), row.clone()]),
+            Err(
+                "parameter 0 of type grid: 11 is outside of the range [Some(1), Some(10)]"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            grid.check(&[
+                TypeParameter::Integer(1),
+                TypeParameter::Enum("DIAGONAL".to_string())
+            ]),
+            Err(
+                "parameter 1 of type grid: DIAGONAL is not one of [\"ROW\", \"COLUMN\"]"
+                    .to_string()
+            )
+        );
+        // only optional parameters may be null
+        assert_eq!(
+            grid.check(&[TypeParameter::Null, row]),
+            Err("parameter 0 of type grid: expected Integer, found Null".to_string())
+        );
+    }
+}
@@ -0,0 +1,295 @@
+use substrait::text::simple_extensions;
+
+// Type expressions as they appear in extension YAML files, e.g. `i64`, `decimal<P1,S1>?`,
+// `list<any1>` or `u!geometry`. Unlike crate::types::Type these may contain type variables
+// and integer parameter expressions that only get bound once the argument types are known.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeExpression {
+    // lower-cased base name
+    pub name: String,
+    pub nullable: bool,
+    pub parameters: Vec<TypeParameter>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypeParameter {
+    Type(TypeExpression),
+    // integer parameters are kept as source text (`38`, `P1`, `P1 + S2`)
+    Integer(String),
+}
+
+// Return types may be small programs that compute integer parameters before stating the
+// output type on the final line, e.g. the decimal arithmetic functions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReturnType {
+    pub program: Vec<(String, String)>,
+    pub output: TypeExpression,
+}
+
+// Types whose parameters are integers rather than types
+const INTEGER_PARAMETERIZED: [&str; 9] = [
+    "decimal",
+    "varchar",
+    "fixedchar",
+    "fixedbinary",
+    "precision_time",
+    "precision_timestamp",
+    "precision_timestamp_tz",
+    "interval_day",
+    "interval_compound",
+];
+
+impl TypeExpression {
+    pub fn parse(s: &str) -> Result<TypeExpression, String> {
+        let s = s.trim();
+        let (head, params) = match s.find('<') {
+            Some(start) => {
+                let end = s
+                    .rfind('>')
+                    .ok_or_else(|| format!("unbalanced '<' in type expression: {}", s))?;
+                let trailing = s[end + 1..].trim();
+                if !trailing.is_empty() && trailing != "?" {
+                    return Err(format!("unexpected trailing input in type: {}", s));
+                }
+                let head = format!("{}{}", s[..start].trim(), trailing);
+                (head, Some(&s[start + 1..end]))
+            }
+            None => (s.to_string(), None),
+        };
+
+        let (name, nullable) = match head.strip_suffix('?') {
+            Some(name) => (name.trim(), true),
+            None => (head.trim(), false),
+        };
+        // built-in names are case insensitive, user-defined names are kept as written
+        let name = if name.starts_with("u!") {
+            name.to_string()
+        } else {
+            name.to_lowercase()
+        };
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '!')
+        {
+            return Err(format!("invalid type name in type expression: {}", s));
+        }
+
+        let parameters = match params {
+            None => vec![],
+            Some(params) => split_parameters(params)
+                .into_iter()
+                .map(|p| {
+                    if INTEGER_PARAMETERIZED.contains(&name.as_str()) {
+                        Ok(TypeParameter::Integer(p.to_string()))
+                    } else {
+                        TypeExpression::parse(p).map(TypeParameter::Type)
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+
+        Ok(TypeExpression {
+            name,
+            nullable,
+            parameters,
+        })
+    }
+
+    // Type variables such as `any`, `any1` or `T` match any type
+    pub fn is_any(&self) -> bool {
+        self.name.starts_with("any") || (self.name.len() == 1 && self.parameters.is_empty())
+    }
+}
+
+// Splits a parameter list on commas that are not nested inside another parameter list
+fn split_parameters(s: &str) -> Vec<&str> {
+    let mut parameters = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => {
+                parameters.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parameters.push(s[start..].trim());
+    parameters.into_iter().filter(|p| !p.is_empty()).collect()
+}
+
+impl TryFrom<&str> for TypeExpression {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        TypeExpression::parse(s)
+    }
+}
+
+impl TryFrom<simple_extensions::Type> for TypeExpression {
+    type Error = String;
+
+    fn try_from(t: simple_extensions::Type) -> Result<Self, Self::Error> {
+        match t {
+            simple_extensions::Type::Variant0(str) => TypeExpression::parse(&str),
+            simple_extensions::Type::Variant1(_) => {
+                Err("cannot handle object type expressions".to_string())
+            }
+        }
+    }
+}
+
+impl ReturnType {
+    pub fn parse(s: &str) -> Result<ReturnType, String> {
+        let lines: Vec<&str> = s
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+            .collect();
+        let (output, statements) = lines
+            .split_last()
+            .ok_or_else(|| "return type must not be empty".to_string())?;
+        let program = statements
+            .iter()
+            .map(|statement| match statement.split_once('=') {
+                Some((name, expression)) => {
+                    Ok((name.trim().to_string(), expression.trim().to_string()))
+                }
+                None => Err(format!("expected assignment in return program: {}", s)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ReturnType {
+            program,
+            output: TypeExpression::parse(output)?,
+        })
+    }
+}
+
+impl TryFrom<&str> for ReturnType {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        ReturnType::parse(s)
+    }
+}
+
+impl TryFrom<simple_extensions::Type> for ReturnType {
+    type Error = String;
+
+    fn try_from(t: simple_extensions::Type) -> Result<Self, Self::Error> {
+        match t {
+            simple_extensions::Type::Variant0(str) => ReturnType::parse(&str),
+            simple_extensions::Type::Variant1(_) => {
+                Err("cannot handle object return types".to_string())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn integer(s: &str) -> TypeParameter {
+        TypeParameter::Integer(s.to_string())
+    }
+
+    #[test]
+    fn parses_parameterized_types() {
+        let decimal = TypeExpression::parse("DECIMAL<P1, S1>?").unwrap();
+        assert_eq!(decimal.name, "decimal");
+        assert!(decimal.nullable);
+        assert_eq!(decimal.parameters, vec![integer("P1"), integer("S1")]);
+
+        let map = TypeExpression::parse("map<string, list<decimal<38,0>>>").unwrap();
+        assert_eq!(map.name, "map");
+        assert!(!map.nullable);
+        let value = match &map.parameters[1] {
+            TypeParameter::Type(value) => value,
+            p => panic!("expected a type parameter, found {:?}", p),
+        };
+        assert_eq!(value.name, "list");
+        assert_eq!(
+            value.parameters,
+            vec![TypeParameter::Type(TypeExpression {
+                name: "decimal".to_string(),
+                nullable: false,
+                parameters: vec![integer("38"), integer("0")],
+            })]
+        );
+
+        assert!(TypeExpression::parse("list<i32").is_err());
+        assert!(TypeExpression::parse("list<i32>x").is_err());
+        assert!(TypeExpression::parse("").is_err());
+    }
+
+    #[test]
+    fn parses_type_variables() {
+        for variable in ["any", "any1", "T"] {
+            let t = TypeExpression::parse(variable).unwrap();
+            assert!(t.is_any(), "{} is a type variable", variable);
+        }
+        assert!(!TypeExpression::parse("i8").unwrap().is_any());
+
+        let list = TypeExpression::parse("list<any1>?").unwrap();
+        assert!(!list.is_any());
+        assert_eq!(
+            list.parameters,
+            vec![TypeParameter::Type(TypeExpression::parse("any1").unwrap())]
+        );
+    }
+
+    #[test]
+    fn parses_nullability() {
+        assert!(TypeExpression::parse("i64?").unwrap().nullable);
+        assert!(TypeExpression::parse("varchar<L1>?").unwrap().nullable);
+        assert!(!TypeExpression::parse("varchar<L1>").unwrap().nullable);
+        assert!(TypeExpression::parse("u!geometry?").unwrap().nullable);
+    }
+
+    #[test]
+    fn keeps_user_defined_names_as_written() {
+        assert_eq!(TypeExpression::parse("I64").unwrap().name, "i64");
+        assert_eq!(
+            TypeExpression::parse("u!Geo_Point?").unwrap().name,
+            "u!Geo_Point"
+        );
+    }
+
+    #[test]
+    fn parses_return_programs() {
+        let return_type = ReturnType::parse(
+            "
+            init_scale = max(S1,S2)
+            init_prec = init_scale + max(P1 - S1, P2 - S2) + 1
+            DECIMAL<init_prec, init_scale>
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            return_type.program,
+            vec![
+                ("init_scale".to_string(), "max(S1,S2)".to_string()),
+                (
+                    "init_prec".to_string(),
+                    "init_scale + max(P1 - S1, P2 - S2) + 1".to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            return_type.output.parameters,
+            vec![integer("init_prec"), integer("init_scale")]
+        );
+
+        let simple = ReturnType::parse("i32?").unwrap();
+        assert!(simple.program.is_empty());
+        assert!(simple.output.nullable);
+
+        assert!(ReturnType::parse("   ").is_err());
+        assert!(ReturnType::parse("scale\ndecimal<38, scale>").is_err());
+    }
+}
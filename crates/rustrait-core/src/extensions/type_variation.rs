@@ -0,0 +1,43 @@
+use substrait::text::simple_extensions;
+
+use crate::extensions::type_expression::TypeExpression;
+
+#[derive(Debug)]
+pub struct TypeVariation {
+    pub name: String,
+    pub description: Option<String>,
+    pub parent: TypeExpression,
+    pub functions: FunctionBehaviour,
+}
+
+// Whether functions defined for the parent type also apply to the variation
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FunctionBehaviour {
+    Inherits,
+    Separate,
+}
+
+impl TryFrom<simple_extensions::SimpleExtensionsTypeVariationsItem> for TypeVariation {
+    type Error = String;
+
+    fn try_from(
+        tv: simple_extensions::SimpleExtensionsTypeVariationsItem,
+    ) -> Result<Self, Self::Error> {
+        let parent = TypeExpression::try_from(tv.parent)
+            .map_err(|e| format!("type variation {}: {}", tv.name, e))?;
+        Ok(TypeVariation {
+            name: tv.name,
+            description: tv.description,
+            parent,
+            functions: match tv.functions {
+                None
+                | Some(simple_extensions::SimpleExtensionsTypeVariationsItemFunctions::Inherits) => {
+                    FunctionBehaviour::Inherits
+                }
+                Some(simple_extensions::SimpleExtensionsTypeVariationsItemFunctions::Separate) => {
+                    FunctionBehaviour::Separate
+                }
+            },
+        })
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+use substrait::text::simple_extensions;
+
+use crate::extensions::aggregate_function::Decomposable;
+use crate::extensions::arguments::Argument;
+use crate::extensions::properties::{
+    function_options, FunctionOption, NullabilityHandling, VariadicBehaviour,
+};
+use crate::extensions::type_expression::{ReturnType, TypeExpression};
+
+#[derive(Debug)]
+pub struct WindowFunction {
+    pub name: String,
+    pub description: Option<String>,
+    pub variants: Vec<WindowFunctionVariant>,
+}
+
+impl TryFrom<simple_extensions::WindowFunction> for WindowFunction {
+    type Error = String;
+
+    fn try_from(wf: simple_extensions::WindowFunction) -> Result<Self, Self::Error> {
+        let variants = wf
+            .impls
+            .into_iter()
+            .map(WindowFunctionVariant::try_from)
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("function {}: {}", wf.name, e))?;
+        Ok(WindowFunction {
+            name: wf.name,
+            description: wf.description,
+            variants,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WindowType {
+    // evaluated incrementally as rows stream through the window
+    Streaming,
+    // requires the whole partition before producing output
+    Partition,
+}
+
+impl From<Option<simple_extensions::WindowFunctionImplsItemWindowType>> for WindowType {
+    fn from(wt: Option<simple_extensions::WindowFunctionImplsItemWindowType>) -> Self {
+        match wt {
+            None | Some(simple_extensions::WindowFunctionImplsItemWindowType::Partition) => {
+                WindowType::Partition
+            }
+            Some(simple_extensions::WindowFunctionImplsItemWindowType::Streaming) => {
+                WindowType::Streaming
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct WindowFunctionVariant {
+    pub arguments: Vec<Argument>,
+    pub options: BTreeMap<String, FunctionOption>,
+    pub nullability: NullabilityHandling,
+    pub variadic: Option<VariadicBehaviour>,
+    pub deterministic: bool,
+    pub session_dependent: bool,
+    pub decomposable: Decomposable,
+    pub intermediate: Option<TypeExpression>,
+    pub ordered: bool,
+    pub maxset: Option<u64>,
+    pub window_type: WindowType,
+    pub return_type: ReturnType,
+}
+
+impl TryFrom<simple_extensions::WindowFunctionImplsItem> for WindowFunctionVariant {
+    type Error = String;
+
+    fn try_from(wfii: simple_extensions::WindowFunctionImplsItem) -> Result<Self, Self::Error> {
+        Ok(WindowFunctionVariant {
+            arguments: wfii
+                .args
+                .map(|args| args.0)
+                .unwrap_or_default()
+                .into_iter()
+                .map(Argument::try_from)
+                .collect::<Result<_, _>>()?,
+            options: function_options(wfii.options),
+            nullability: NullabilityHandling::from(wfii.nullability),
+            variadic: wfii.variadic.map(VariadicBehaviour::from),
+            deterministic: wfii.deterministic.map(|d| d.0).unwrap_or(true),
+            session_dependent: wfii.session_dependent.map(|sd| sd.0).unwrap_or(false),
+            decomposable: Decomposable::from(wfii.decomposable),
+            intermediate: wfii
+                .intermediate
+                .map(|i| TypeExpression::try_from(i.0))
+                .transpose()?,
+            ordered: wfii.ordered.map(|o| o.0).unwrap_or(false),
+            maxset: wfii.maxset.map(|m| m.0 as u64),
+            window_type: WindowType::from(wfii.window_type),
+            return_type: ReturnType::try_from(wfii.return_.0)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FUNCTIONS: &str = r#"
+    window_functions:
+      - name: "row_number"
+        impls:
+          - window_type: STREAMING
+            return: i64
+      - name: "sum"
+        impls:
+          - args:
+              - { name: x, value: i64 }
+            decomposable: MANY
+            intermediate: i64?
+            ordered: true
+            maxset: 2
+            return: i64?
+    "#;
+
+    fn window_functions() -> Vec<WindowFunction> {
+        let simple_extensions: simple_extensions::SimpleExtensions =
+            serde_yaml::from_str(FUNCTIONS).unwrap();
+        simple_extensions
+            .window_functions
+            .into_iter()
+            .map(WindowFunction::try_from)
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn converts_window_properties() {
+        let functions = window_functions();
+        let row_number = &functions[0].variants[0];
+        assert!(row_number.arguments.is_empty());
+        assert_eq!(row_number.window_type, WindowType::Streaming);
+        assert_eq!(row_number.decomposable, Decomposable::None);
+        assert_eq!(row_number.intermediate, None);
+        assert!(!row_number.ordered);
+        assert_eq!(row_number.maxset, None);
+
+        let sum = &functions[1].variants[0];
+        assert_eq!(sum.window_type, WindowType::Partition);
+        assert_eq!(sum.decomposable, Decomposable::Many);
+        assert_eq!(
+            sum.intermediate,
+            Some(TypeExpression::parse("i64?").unwrap())
+        );
+        assert!(sum.ordered);
+        assert_eq!(sum.maxset, Some(2));
+    }
+}
@@ -0,0 +1,281 @@
+use prost::Message;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use substrait::proto;
+
+pub mod text;
+
+// The serializations a Substrait plan can be exchanged in
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PlanFormat {
+    Binary,
+    Json,
+    Text,
+}
+
+#[derive(Debug)]
+pub enum FormatError {
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    Binary(prost::DecodeError),
+    Json(serde_json::Error),
+    Text(String),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::Io { path, error } => {
+                write!(f, "cannot access {}: {}", path.display(), error)
+            }
+            FormatError::Binary(error) => write!(f, "invalid binary plan: {}", error),
+            FormatError::Json(error) => write!(f, "invalid json plan: {}", error),
+            FormatError::Text(error) => write!(f, "invalid text plan: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl PlanFormat {
+    // JSON starts with an object and the text format with a field name or comment. Anything
+    // else, including input that is not printable text, is taken to be binary protobuf.
+    pub fn detect(bytes: &[u8]) -> PlanFormat {
+        let text = match std::str::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(_) => return PlanFormat::Binary,
+        };
+        if text
+            .chars()
+            .any(|c| c.is_control() && !c.is_ascii_whitespace())
+        {
+            return PlanFormat::Binary;
+        }
+        match text.trim_start().chars().next() {
+            Some('{') => PlanFormat::Json,
+            Some(c) if c.is_ascii_alphabetic() || c == '#' => PlanFormat::Text,
+            _ => PlanFormat::Binary,
+        }
+    }
+
+    // Guesses the format from the file extension, e.g. when choosing how to write a plan
+    pub fn from_path(path: &Path) -> Option<PlanFormat> {
+        match path.extension()?.to_str()? {
+            "bin" | "pb" | "proto" => Some(PlanFormat::Binary),
+            "json" | "substrait" => Some(PlanFormat::Json),
+            "txt" | "textproto" | "pbtxt" => Some(PlanFormat::Text),
+            _ => None,
+        }
+    }
+}
+
+pub fn parse_plan(bytes: &[u8], format: PlanFormat) -> Result<proto::Plan, FormatError> {
+    match format {
+        PlanFormat::Binary => proto::Plan::decode(bytes).map_err(FormatError::Binary),
+        PlanFormat::Json => serde_json::from_slice(bytes).map_err(FormatError::Json),
+        PlanFormat::Text => {
+            let text = std::str::from_utf8(bytes).map_err(|e| FormatError::Text(e.to_string()))?;
+            text::parse(text)
+        }
+    }
+}
+
+// Parses a plan in whichever format it was serialized in
+pub fn load_plan(bytes: &[u8]) -> Result<proto::Plan, FormatError> {
+    parse_plan(bytes, PlanFormat::detect(bytes))
+}
+
+pub fn load_plan_file(path: impl AsRef<Path>) -> Result<proto::Plan, FormatError> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|error| FormatError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    load_plan(&bytes)
+}
+
+pub fn write_plan(plan: &proto::Plan, format: PlanFormat) -> Result<Vec<u8>, FormatError> {
+    match format {
+        PlanFormat::Binary => Ok(plan.encode_to_vec()),
+        PlanFormat::Json => serde_json::to_vec_pretty(plan).map_err(FormatError::Json),
+        PlanFormat::Text => Ok(text::write(plan)?.into_bytes()),
+    }
+}
+
+pub fn write_plan_file(
+    plan: &proto::Plan,
+    path: impl AsRef<Path>,
+    format: PlanFormat,
+) -> Result<(), FormatError> {
+    let path = path.as_ref();
+    fs::write(path, write_plan(plan, format)?).map_err(|error| FormatError::Io {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_round_trips(plan: &proto::Plan) {
+        for format in [PlanFormat::Binary, PlanFormat::Json, PlanFormat::Text] {
+            let bytes = write_plan(plan, format).unwrap();
+            assert_eq!(PlanFormat::detect(&bytes), format);
+            assert_eq!(&load_plan(&bytes).unwrap(), plan, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn round_trips_every_format() {
+        let json = include_bytes!("../simple-select.substrait");
+        assert_eq!(PlanFormat::detect(json), PlanFormat::Json);
+        assert_round_trips(&load_plan(json).unwrap());
+
+        let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/plans");
+        let mut paths: Vec<_> = fs::read_dir(&corpus)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|e| e == "json"))
+            .collect();
+        paths.sort();
+        assert!(!paths.is_empty());
+        for path in paths {
+            assert_round_trips(&load_plan_file(&path).unwrap());
+        }
+    }
+
+    // Lists with a single element and unsigned 64-bit integers are written like singular
+    // fields and signed integers, so reading them back relies on the field tables
+    #[test]
+    fn round_trips_single_list_elements_and_unsigned_integers() {
+        use crate::encoder::encode_prost_plan;
+        use crate::plans::expressions::field_reference::FieldReference;
+        use crate::plans::{
+            Emit, Exchange, ExchangeKind, FileFormat, FileOrFiles, FilePath, KeyedJoin, Plan, Read,
+            ReadType, Rel,
+        };
+        use crate::types::{NamedStruct, Type};
+
+        let read = |start| {
+            Box::new(Rel::Read(Read {
+                emit: Emit::Direct,
+                base_schema: NamedStruct {
+                    names: vec!["a".to_string()],
+                    types: vec![Type::I64 { nullable: false }],
+                },
+                read_type: ReadType::LocalFiles {
+                    items: vec![FileOrFiles {
+                        path: FilePath::File("file:///data/a.parquet".to_string()),
+                        format: FileFormat::Parquet,
+                        partition_index: 3,
+                        start,
+                        length: 1 << 40,
+                    }],
+                },
+            }))
+        };
+        let plan = Plan {
+            root: Box::new(Rel::Exchange(Exchange {
+                emit: Emit::Direct,
+                input: Box::new(Rel::HashJoin(KeyedJoin {
+                    emit: Emit::Direct,
+                    left: read(0),
+                    right: read(u64::MAX - 1),
+                    left_keys: vec![FieldReference { field: 0 }],
+                    right_keys: vec![FieldReference { field: 0 }],
+                    post_join_filter: None,
+                    join_type: crate::plans::JoinType::Inner,
+                })),
+                partition_count: 4,
+                kind: ExchangeKind::Broadcast,
+            })),
+            names: vec![],
+            subtrees: vec![],
+        };
+        let mut proto_plan = encode_prost_plan(&plan);
+        match &mut proto_plan.relations[0].rel_type {
+            Some(proto::plan_rel::RelType::Rel(proto::Rel {
+                rel_type: Some(proto::rel::RelType::Exchange(exchange)),
+            })) => exchange.targets.push(proto::exchange_rel::ExchangeTarget {
+                partition_id: vec![2],
+                target_type: Some(proto::exchange_rel::exchange_target::TargetType::Uri(
+                    "file:///out".to_string(),
+                )),
+            }),
+            rel => panic!("expected an exchange, found {:?}", rel),
+        }
+
+        let text = String::from_utf8(write_plan(&proto_plan, PlanFormat::Text).unwrap()).unwrap();
+        assert!(text.contains("start: 18446744073709551614\n"), "{}", text);
+        assert!(text.contains("partition_id: 2\n"), "{}", text);
+        assert_round_trips(&proto_plan);
+    }
+
+    // Bytes are escaped in text format, so plans written by protoc load with the same bytes
+    #[test]
+    fn reads_and_writes_escaped_bytes() {
+        use base64::engine::general_purpose::STANDARD as BASE64;
+        use base64::Engine;
+
+        let text = r#"
+        relations {
+          root {
+            input {
+              read {
+                base_schema {
+                  names: "caf\303\251"
+                  struct {
+                    types {
+                      decimal { scale: 2 precision: 4 nullability: NULLABILITY_REQUIRED }
+                    }
+                    nullability: NULLABILITY_REQUIRED
+                  }
+                }
+                virtual_table {
+                  values {
+                    fields {
+                      decimal {
+                        value: "\322\004\000\000\000\000\000\000\000\000\000\000\000\000\000\000"
+                        precision: 4
+                        scale: 2
+                      }
+                    }
+                    fields { binary: "\x00\xffa\"" }
+                  }
+                }
+              }
+            }
+            names: "d"
+          }
+        }
+        "#;
+        let plan = parse_plan(text.as_bytes(), PlanFormat::Text).unwrap();
+        let json = serde_json::to_value(&plan).unwrap();
+        let read = &json["relations"][0]["root"]["input"]["read"];
+        assert_eq!(read["baseSchema"]["names"][0], "café");
+        let fields = &read["virtualTable"]["values"][0]["fields"];
+        assert_eq!(
+            fields[0]["decimal"]["value"],
+            BASE64.encode(1234i128.to_le_bytes())
+        );
+        assert_eq!(fields[1]["binary"], BASE64.encode([0, 255, b'a', b'"']));
+
+        let written = String::from_utf8(write_plan(&plan, PlanFormat::Text).unwrap()).unwrap();
+        assert!(
+            written.contains(
+                r#"value: "\322\004\000\000\000\000\000\000\000\000\000\000\000\000\000\000""#
+            ),
+            "{}",
+            written
+        );
+        assert!(written.contains(r#"binary: "\000\377a\"""#), "{}", written);
+        assert_round_trips(&plan);
+
+        assert!(parse_plan(br#"relations { root { names: "\q" } }"#, PlanFormat::Text).is_err());
+        assert!(parse_plan(br#"relations { root { names: "\400" } }"#, PlanFormat::Text).is_err());
+    }
+}
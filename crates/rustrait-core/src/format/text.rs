@@ -0,0 +1,467 @@
+use crate::format::FormatError;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{Map, Number, Value};
+use substrait::proto;
+
+// Protobuf text format support, implemented on top of the JSON mapping of the plan messages
+// since the generated types carry no reflection information. Where the text format needs to
+// know the field type, these tables stand in for the message descriptors.
+
+// Fields that are repeated in every Substrait message they appear in, so that a single
+// occurrence in text format is still read as a list
+const REPEATED_FIELDS: [&str; 32] = [
+    "args",
+    "arguments",
+    "expected_type_urls",
+    "expression_references",
+    "expressions",
+    "extension_uris",
+    "extensions",
+    "fields",
+    "grouping_expressions",
+    "groupings",
+    "ifs",
+    "inputs",
+    "items",
+    "key_values",
+    "keys",
+    "left_keys",
+    "measures",
+    "names",
+    "needles",
+    "options",
+    "output_mapping",
+    "partition_id",
+    "partitions",
+    "preference",
+    "relations",
+    "right_keys",
+    "sorts",
+    "struct_items",
+    "targets",
+    "type_parameters",
+    "types",
+    "values",
+];
+
+// Fields holding enum values, which are written unquoted
+const ENUM_FIELDS: [&str; 9] = [
+    "nullability",
+    "phase",
+    "invocation",
+    "direction",
+    "op",
+    "failure_behavior",
+    "bounds_type",
+    "operation",
+    "create_mode",
+];
+
+// Relations whose `type` field is the join type enum. Elsewhere `type` holds a message.
+const JOIN_RELATIONS: [&str; 4] = ["join", "hash_join", "merge_join", "nested_loop_join"];
+
+// Bytes fields, which the JSON mapping writes as base64 and text format as escaped strings.
+// Where the name alone is ambiguous the field is given along with the field holding its message,
+// e.g. the decimal literal's value, as the value of a varchar literal is a string.
+const BYTES_FIELDS: [(Option<&str>, &str); 6] = [
+    (None, "binary"),
+    (None, "fixed_binary"),
+    (None, "uuid"),
+    (Some("decimal"), "value"),
+    (Some("python_pickle_function"), "function"),
+    (Some("web_assembly_function"), "script"),
+];
+
+// 64-bit integer fields, signed and unsigned, which the JSON mapping writes as strings
+const INT64_FIELDS: [&str; 14] = [
+    "offset",
+    "count",
+    "i64",
+    "timestamp",
+    "timestamp_tz",
+    "time",
+    "seconds",
+    "subseconds",
+    "microseconds",
+    "integer",
+    "partition_index",
+    "start",
+    "length",
+    "max_set",
+];
+
+fn is_enum_field(parent: &str, name: &str) -> bool {
+    ENUM_FIELDS.contains(&name) || (name == "type" && JOIN_RELATIONS.contains(&parent))
+}
+
+fn is_bytes_field(parent: &str, name: &str) -> bool {
+    BYTES_FIELDS
+        .iter()
+        .any(|(p, n)| *n == name && p.is_none_or(|p| p == parent))
+}
+
+pub fn parse(text: &str) -> Result<proto::Plan, FormatError> {
+    let mut parser = Parser { text, position: 0 };
+    let message = parser.message("", None)?;
+    serde_json::from_value(Value::Object(message)).map_err(FormatError::Json)
+}
+
+pub fn write(plan: &proto::Plan) -> Result<String, FormatError> {
+    let value = serde_json::to_value(plan).map_err(FormatError::Json)?;
+    let mut out = String::new();
+    if let Value::Object(message) = value {
+        write_message(&message, "", 0, &mut out)?;
+    }
+    Ok(out)
+}
+
+fn write_message(
+    message: &Map<String, Value>,
+    parent: &str,
+    indent: usize,
+    out: &mut String,
+) -> Result<(), FormatError> {
+    for (key, value) in message {
+        let name = snake_case(key);
+        match value {
+            Value::Array(values) => {
+                for value in values {
+                    write_field(parent, &name, value, indent, out)?;
+                }
+            }
+            value => write_field(parent, &name, value, indent, out)?,
+        }
+    }
+    Ok(())
+}
+
+fn write_field(
+    parent: &str,
+    name: &str,
+    value: &Value,
+    indent: usize,
+    out: &mut String,
+) -> Result<(), FormatError> {
+    let padding = "  ".repeat(indent);
+    match value {
+        Value::Null => {}
+        Value::Object(message) => {
+            out.push_str(&format!("{}{} {{\n", padding, name));
+            write_message(message, name, indent + 1, out)?;
+            out.push_str(&format!("{}}}\n", padding));
+        }
+        Value::String(s) if is_enum_field(parent, name) => {
+            out.push_str(&format!("{}{}: {}\n", padding, name, s))
+        }
+        Value::String(s)
+            if INT64_FIELDS.contains(&name)
+                && (s.parse::<i64>().is_ok() || s.parse::<u64>().is_ok()) =>
+        {
+            out.push_str(&format!("{}{}: {}\n", padding, name, s))
+        }
+        Value::String(s) if is_bytes_field(parent, name) => {
+            let bytes = BASE64.decode(s).map_err(|e| {
+                FormatError::Text(format!("invalid base64 in bytes field {}: {}", name, e))
+            })?;
+            out.push_str(&format!("{}{}: {}\n", padding, name, quote_bytes(&bytes)))
+        }
+        Value::String(s) => out.push_str(&format!("{}{}: {}\n", padding, name, quote(s))),
+        Value::Bool(b) => out.push_str(&format!("{}{}: {}\n", padding, name, b)),
+        Value::Number(n) => out.push_str(&format!("{}{}: {}\n", padding, name, n)),
+        // nested lists do not occur in plans
+        Value::Array(_) => {}
+    }
+    Ok(())
+}
+
+fn snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            out.push('_');
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn quote(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// Printable ASCII is written as is and every other byte as an octal escape, as protoc does
+fn quote_bytes(bytes: &[u8]) -> String {
+    let mut out = String::from("\"");
+    for &b in bytes {
+        match b {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            b' '..=b'~' => out.push(b as char),
+            b => out.push_str(&format!("\\{:03o}", b)),
+        }
+    }
+    out.push('"');
+    out
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> FormatError {
+        let line = self.text[..self.position].matches('\n').count() + 1;
+        FormatError::Text(format!("{} on line {}", message, line))
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    // Skips whitespace and comments
+    fn skip(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.position += rest.len() - trimmed.len();
+            if !trimmed.starts_with('#') {
+                return;
+            }
+            self.position += trimmed.find('\n').unwrap_or(trimmed.len());
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip();
+        if self.rest().starts_with(c) {
+            self.position += c.len_utf8();
+            return true;
+        }
+        false
+    }
+
+    fn identifier(&mut self) -> Result<&'a str, FormatError> {
+        self.skip();
+        let rest = self.rest();
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+            .unwrap_or(rest.len());
+        if end == 0 {
+            return Err(self.error("expected a field name"));
+        }
+        let start = self.position;
+        self.position += end;
+        Ok(&self.text[start..self.position])
+    }
+
+    // Parses fields until the closing delimiter, or the end of input for the top-level message
+    fn message(
+        &mut self,
+        parent: &str,
+        close: Option<char>,
+    ) -> Result<Map<String, Value>, FormatError> {
+        let mut message = Map::new();
+        loop {
+            self.skip();
+            match close {
+                Some(c) if self.eat(c) => break,
+                None if self.rest().is_empty() => break,
+                _ if self.rest().is_empty() => return Err(self.error("unexpected end of input")),
+                _ => {}
+            }
+            let name = self.identifier()?.to_string();
+            let has_colon = self.eat(':');
+            let values = if self.eat('[') {
+                let mut values = vec![];
+                if !self.eat(']') {
+                    loop {
+                        values.push(self.value(parent, &name, has_colon)?);
+                        if self.eat(']') {
+                            break;
+                        }
+                        if !self.eat(',') {
+                            return Err(self.error("expected , or ]"));
+                        }
+                    }
+                }
+                values
+            } else {
+                vec![self.value(parent, &name, has_colon)?]
+            };
+            // separators between fields are optional
+            if !self.eat(',') {
+                self.eat(';');
+            }
+
+            let repeated = REPEATED_FIELDS.contains(&name.as_str());
+            match message.get_mut(&name) {
+                Some(Value::Array(existing)) => existing.extend(values),
+                Some(existing) => {
+                    let first = existing.take();
+                    *existing = Value::Array([first].into_iter().chain(values).collect());
+                }
+                None if repeated || values.len() != 1 => {
+                    message.insert(name, Value::Array(values));
+                }
+                None => {
+                    message.insert(name, values.into_iter().next().expect("one value"));
+                }
+            }
+        }
+        Ok(message)
+    }
+
+    fn value(&mut self, parent: &str, name: &str, has_colon: bool) -> Result<Value, FormatError> {
+        self.skip();
+        if self.eat('{') {
+            return Ok(Value::Object(self.message(name, Some('}'))?));
+        }
+        if self.eat('<') {
+            return Ok(Value::Object(self.message(name, Some('>'))?));
+        }
+        if !has_colon {
+            return Err(self.error("expected : before a scalar value"));
+        }
+        match self.rest().chars().next() {
+            Some('"') | Some('\'') => {
+                let bytes = self.string()?;
+                if is_bytes_field(parent, name) {
+                    return Ok(Value::String(BASE64.encode(bytes)));
+                }
+                String::from_utf8(bytes)
+                    .map(Value::String)
+                    .map_err(|_| self.error(&format!("field {} is not valid UTF-8", name)))
+            }
+            Some(c) if c.is_ascii_digit() || c == '-' || c == '.' => self.number(),
+            Some(_) => {
+                let identifier = self.identifier()?;
+                Ok(match identifier {
+                    "true" | "True" => Value::Bool(true),
+                    "false" | "False" => Value::Bool(false),
+                    // enum values are given by name
+                    name => Value::String(name.to_string()),
+                })
+            }
+            None => Err(self.error("expected a value")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, FormatError> {
+        let rest = self.rest();
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '+' || c == '.'))
+            .unwrap_or(rest.len());
+        let literal = &rest[..end];
+        self.position += end;
+        let number = if let Ok(i) = literal.parse::<i64>() {
+            Number::from(i)
+        } else if let Ok(u) = literal.parse::<u64>() {
+            Number::from(u)
+        } else {
+            literal
+                .trim_end_matches(['f', 'F'])
+                .parse::<f64>()
+                .ok()
+                .and_then(Number::from_f64)
+                .ok_or_else(|| self.error(&format!("invalid number {}", literal)))?
+        };
+        Ok(Value::Number(number))
+    }
+
+    // Reads a quoted string as bytes, which are only UTF-8 once escapes are decoded
+    fn string(&mut self) -> Result<Vec<u8>, FormatError> {
+        let text: &'a str = self.text;
+        let bytes = text.as_bytes();
+        let quote = bytes[self.position];
+        self.position += 1;
+        let mut out = vec![];
+        loop {
+            let Some(&b) = bytes.get(self.position) else {
+                return Err(self.error("unterminated string"));
+            };
+            self.position += 1;
+            match b {
+                b if b == quote => break,
+                b'\\' => self.escape(&mut out)?,
+                b => out.push(b),
+            }
+        }
+        // adjacent strings are concatenated
+        self.skip();
+        if matches!(self.rest().chars().next(), Some('"' | '\'')) {
+            out.extend(self.string()?);
+        }
+        Ok(out)
+    }
+
+    fn escape(&mut self, out: &mut Vec<u8>) -> Result<(), FormatError> {
+        let Some(&c) = self.text.as_bytes().get(self.position) else {
+            return Err(self.error("unterminated string"));
+        };
+        self.position += 1;
+        match c {
+            b'n' => out.push(b'\n'),
+            b'r' => out.push(b'\r'),
+            b't' => out.push(b'\t'),
+            b'a' => out.push(0x07),
+            b'b' => out.push(0x08),
+            b'f' => out.push(0x0c),
+            b'v' => out.push(0x0b),
+            b'\\' | b'\'' | b'"' | b'?' => out.push(c),
+            b'0'..=b'7' => {
+                self.position -= 1;
+                let value = self.escaped_number(8, 1, 3)?;
+                let byte = u8::try_from(value).map_err(|_| {
+                    self.error(&format!("octal escape {:o} is out of range", value))
+                })?;
+                out.push(byte);
+            }
+            b'x' | b'X' => {
+                let value = self.escaped_number(16, 1, 2)?;
+                out.push(value as u8);
+            }
+            b'u' | b'U' => {
+                let digits = if c == b'u' { 4 } else { 8 };
+                let value = self.escaped_number(16, digits, digits)?;
+                let c = char::from_u32(value)
+                    .ok_or_else(|| self.error(&format!("invalid code point {:x}", value)))?;
+                out.extend(c.to_string().as_bytes());
+            }
+            c => {
+                return Err(self.error(&format!("invalid escape \\{}", c as char)));
+            }
+        }
+        Ok(())
+    }
+
+    // Reads the digits of a numeric escape, taking as many as the escape allows
+    fn escaped_number(&mut self, radix: u32, min: usize, max: usize) -> Result<u32, FormatError> {
+        let rest = self.rest();
+        let len = rest
+            .bytes()
+            .take(max)
+            .take_while(|b| (*b as char).is_digit(radix))
+            .count();
+        if len < min {
+            return Err(self.error("incomplete escape sequence"));
+        }
+        self.position += len;
+        u32::from_str_radix(&rest[..len], radix).map_err(|_| self.error("invalid escape sequence"))
+    }
+}
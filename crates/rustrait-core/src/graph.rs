@@ -0,0 +1,181 @@
+use crate::checker::{plan_schemas, RelSchemas};
+use crate::explain::describe;
+use crate::plans::{Plan, Rel};
+use std::collections::HashMap;
+
+// Plan trees as graphs, with one node per relation and edges running from each input to the
+// relation that consumes it. Subtrees used through Rel::Reference are drawn once, so a shared
+// subtree shows up as a node with several outgoing edges.
+
+pub fn to_dot(plan: &Plan) -> String {
+    let graph = Graph::build(plan);
+    let mut out = String::from("digraph plan {\n  rankdir=BT;\n");
+    for (id, lines) in graph.nodes.iter().enumerate() {
+        let label = lines
+            .iter()
+            .map(|line| escape_dot(line))
+            .collect::<Vec<_>>()
+            .join("\\n");
+        out.push_str(&format!("  n{} [shape=box, label=\"{}\"];\n", id, label));
+    }
+    for (from, to) in &graph.edges {
+        out.push_str(&format!("  n{} -> n{};\n", from, to));
+    }
+    out.push_str("}\n");
+    out
+}
+
+pub fn to_mermaid(plan: &Plan) -> String {
+    let graph = Graph::build(plan);
+    let mut out = String::from("flowchart BT\n");
+    for (id, lines) in graph.nodes.iter().enumerate() {
+        let label = lines
+            .iter()
+            .map(|line| escape_mermaid(line))
+            .collect::<Vec<_>>()
+            .join("<br/>");
+        out.push_str(&format!("  n{}[\"{}\"]\n", id, label));
+    }
+    for (from, to) in &graph.edges {
+        out.push_str(&format!("  n{} --> n{}\n", from, to));
+    }
+    out
+}
+
+struct Graph<'a> {
+    schemas: RelSchemas<'a>,
+    // the label of each node, one entry per line
+    nodes: Vec<Vec<String>>,
+    edges: Vec<(usize, usize)>,
+    // node of each subtree that has been drawn
+    subtree_nodes: HashMap<usize, usize>,
+}
+
+impl<'a> Graph<'a> {
+    fn build(plan: &'a Plan) -> Graph<'a> {
+        let mut graph = Graph {
+            schemas: plan_schemas(plan),
+            nodes: vec![],
+            edges: vec![],
+            subtree_nodes: HashMap::new(),
+        };
+        let root = graph.add_rel(&plan.root, &plan.subtrees);
+        if !plan.names.is_empty() {
+            let id = graph.add_node(vec!["Root".to_string(), plan.names.join(", ")]);
+            graph.edges.push((root, id));
+        }
+        // subtrees nothing refers to are still part of the plan
+        for index in 0..plan.subtrees.len() {
+            graph.add_subtree(index, &plan.subtrees);
+        }
+        graph
+    }
+
+    fn add_node(&mut self, lines: Vec<String>) -> usize {
+        self.nodes.push(lines);
+        self.nodes.len() - 1
+    }
+
+    fn add_rel(&mut self, rel: &Rel, subtrees: &[Rel]) -> usize {
+        if let Rel::Reference(reference) = rel {
+            // references to subtrees that do not exist are drawn as they are
+            if reference.subtree < subtrees.len() {
+                return self.add_subtree(reference.subtree, subtrees);
+            }
+        }
+        let (name, attributes) = describe(rel, &self.schemas);
+        let id = self.add_node([name.to_string()].into_iter().chain(attributes).collect());
+        self.add_inputs(id, rel, subtrees);
+        id
+    }
+
+    fn add_subtree(&mut self, index: usize, subtrees: &[Rel]) -> usize {
+        if let Some(id) = self.subtree_nodes.get(&index) {
+            return *id;
+        }
+        let rel = &subtrees[index];
+        let (name, attributes) = describe(rel, &self.schemas);
+        let id = self.add_node(
+            [format!("{} (subtree {})", name, index)]
+                .into_iter()
+                .chain(attributes)
+                .collect(),
+        );
+        // registered before drawing the inputs so that a subtree referring to itself does not
+        // recurse forever
+        self.subtree_nodes.insert(index, id);
+        self.add_inputs(id, rel, subtrees);
+        id
+    }
+
+    fn add_inputs(&mut self, id: usize, rel: &Rel, subtrees: &[Rel]) {
+        for input in rel.inputs() {
+            let input = self.add_rel(input, subtrees);
+            self.edges.push((input, id));
+        }
+    }
+}
+
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Mermaid labels are HTML, with entity codes written as #name;
+fn escape_mermaid(s: &str) -> String {
+    s.replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plans::{Cross, Emit, Read, ReadType, Reference};
+    use crate::types::{NamedStruct, Type};
+
+    #[test]
+    fn draws_shared_subtrees_once() {
+        let read = Rel::Read(Read {
+            emit: Emit::Direct,
+            base_schema: NamedStruct {
+                names: vec!["A".to_string()],
+                types: vec![Type::I32 { nullable: false }],
+            },
+            read_type: ReadType::NamedTable {
+                names: vec!["T".to_string()],
+            },
+        });
+        let plan = Plan {
+            root: Box::new(Rel::Cross(Cross {
+                emit: Emit::Direct,
+                left: Box::new(Rel::Reference(Reference { subtree: 0 })),
+                right: Box::new(Rel::Reference(Reference { subtree: 0 })),
+            })),
+            names: vec!["A".to_string(), "\"B\"".to_string()],
+            subtrees: vec![read],
+        };
+
+        assert_eq!(
+            to_dot(&plan),
+            "digraph plan {\n  \
+             rankdir=BT;\n  \
+             n0 [shape=box, label=\"Cross\"];\n  \
+             n1 [shape=box, label=\"Read (subtree 0)\\ntable=T\\ncolumns=[A: i32]\"];\n  \
+             n2 [shape=box, label=\"Root\\nA, \\\"B\\\"\"];\n  \
+             n1 -> n0;\n  \
+             n1 -> n0;\n  \
+             n0 -> n2;\n\
+             }\n"
+        );
+        assert_eq!(
+            to_mermaid(&plan),
+            "flowchart BT\n  \
+             n0[\"Cross\"]\n  \
+             n1[\"Read (subtree 0)<br/>table=T<br/>columns=[A: i32]\"]\n  \
+             n2[\"Root<br/>A, #quot;B#quot;\"]\n  \
+             n1 --> n0\n  \
+             n1 --> n0\n  \
+             n0 --> n2\n"
+        );
+    }
+}
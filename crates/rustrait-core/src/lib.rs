@@ -1,6 +1,14 @@
-mod decoder;
-mod plans;
-mod types;
+pub mod checker;
+pub mod decoder;
+pub mod diagnostics;
+pub mod encoder;
+pub mod explain;
+pub mod extensions;
+pub mod format;
+pub mod graph;
+pub mod plans;
+pub mod types;
+pub mod validator;
 
 pub fn add(left: usize, right: usize) -> usize {
     left + right
@@ -1,27 +1,276 @@
+use crate::plans::expressions::aggregate_function::AggregateFunctionInvocation;
+use crate::plans::expressions::field_reference::FieldReference;
+use crate::plans::expressions::literal::Literal;
+use crate::plans::expressions::sort_field::SortField;
 use crate::plans::expressions::Expression;
 use crate::types::NamedStruct;
 
 pub mod expressions;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Plan {
     pub root: Box<Rel>,
+    // output column names of the root relation, empty if the plan did not name them
+    pub names: Vec<String>,
+    // relations shared by several parts of the plan, used through Rel::Reference
+    pub subtrees: Vec<Rel>,
 }
 
 // Should this be an enum or should it be done via traits ?!?!?
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum Rel {
     Read(Read),
+    Filter(Filter),
+    Fetch(Fetch),
+    Aggregate(Aggregate),
+    Sort(Sort),
+    Join(Join),
     Project(Project),
+    Set(Set),
+    Cross(Cross),
+    Reference(Reference),
+    HashJoin(KeyedJoin),
+    MergeJoin(KeyedJoin),
+    Exchange(Exchange),
 }
 
-#[derive(Debug)]
+// Which columns a relation outputs. Remap selects (and reorders) columns by their index in
+// the relation's direct output.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Emit {
+    #[default]
+    Direct,
+    Remap(Vec<i32>),
+}
+
+#[derive(Debug, PartialEq)]
 pub struct Read {
+    pub emit: Emit,
     pub base_schema: NamedStruct,
+    pub read_type: ReadType,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ReadType {
+    NamedTable { names: Vec<String> },
+    LocalFiles { items: Vec<FileOrFiles> },
+    // rows of literals, one per column of the base schema
+    VirtualTable { rows: Vec<Vec<Literal>> },
+}
+
+// A file, or the files under a path, read by a LocalFiles read
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileOrFiles {
+    pub path: FilePath,
+    pub format: FileFormat,
+    // files with the same partition index are read by the same partition
+    pub partition_index: u64,
+    // the byte range to read, a length of 0 reads the whole file
+    pub start: u64,
+    pub length: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FilePath {
+    // a file or a folder
+    Uri(String),
+    Glob(String),
+    File(String),
+    Folder(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FileFormat {
+    Parquet,
+    Arrow,
+    Orc,
+    Dwrf,
+    DelimitedText(DelimitedTextOptions),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DelimitedTextOptions {
+    pub field_delimiter: String,
+    pub max_line_size: u64,
+    pub quote: String,
+    pub header_lines_to_skip: u64,
+    pub escape: String,
+    pub value_treated_as_null: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Filter {
+    pub emit: Emit,
+    pub input: Box<Rel>,
+    pub condition: Expression,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Fetch {
+    pub emit: Emit,
+    pub input: Box<Rel>,
+    pub offset: i64,
+    // -1 means all remaining records
+    pub count: i64,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Aggregate {
+    pub emit: Emit,
+    pub input: Box<Rel>,
+    pub groupings: Vec<Vec<Expression>>,
+    pub measures: Vec<Measure>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Measure {
+    pub function: AggregateFunctionInvocation,
+    pub filter: Option<Expression>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Sort {
+    pub emit: Emit,
+    pub input: Box<Rel>,
+    pub sorts: Vec<SortField>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Join {
+    pub emit: Emit,
+    pub left: Box<Rel>,
+    pub right: Box<Rel>,
+    pub expression: Option<Expression>,
+    pub post_join_filter: Option<Expression>,
+    pub join_type: JoinType,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum JoinType {
+    Inner,
+    Outer,
+    Left,
+    Right,
+    LeftSemi,
+    LeftAnti,
+    LeftSingle,
+    RightSemi,
+    RightAnti,
+    RightSingle,
+    LeftMark,
+    RightMark,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Project {
+    pub emit: Emit,
     pub input: Box<Rel>,
     pub expressions: Vec<Expression>,
 }
+
+#[derive(Debug, PartialEq)]
+pub struct Set {
+    pub emit: Emit,
+    pub inputs: Vec<Rel>,
+    pub op: SetOp,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SetOp {
+    MinusPrimary,
+    MinusMultiset,
+    IntersectionPrimary,
+    IntersectionMultiset,
+    UnionDistinct,
+    UnionAll,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Cross {
+    pub emit: Emit,
+    pub left: Box<Rel>,
+    pub right: Box<Rel>,
+}
+
+// A join on equal keys, executed with the strategy the producer chose: HashJoin or MergeJoin.
+// The left keys refer to columns of the left input, the right keys to columns of the right.
+#[derive(Debug, PartialEq)]
+pub struct KeyedJoin {
+    pub emit: Emit,
+    pub left: Box<Rel>,
+    pub right: Box<Rel>,
+    pub left_keys: Vec<FieldReference>,
+    pub right_keys: Vec<FieldReference>,
+    pub post_join_filter: Option<Expression>,
+    pub join_type: JoinType,
+}
+
+// Redistributes the rows of the input over partitions. The targets of the exchange are not
+// kept.
+#[derive(Debug, PartialEq)]
+pub struct Exchange {
+    pub emit: Emit,
+    pub input: Box<Rel>,
+    pub partition_count: i32,
+    pub kind: ExchangeKind,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ExchangeKind {
+    // partitions by the hash of the fields
+    ScatterByFields(Vec<FieldReference>),
+    // the expression computes the partition of each row
+    SingleTarget(Expression),
+    // the expression computes a list of partitions for each row
+    MultiTarget {
+        expression: Expression,
+        constrained_to_count: bool,
+    },
+    RoundRobin {
+        exact: bool,
+    },
+    Broadcast,
+}
+
+// Outputs the rows of a subtree of the plan
+#[derive(Debug, PartialEq)]
+pub struct Reference {
+    pub subtree: usize,
+}
+
+impl Rel {
+    pub fn emit(&self) -> &Emit {
+        match self {
+            Rel::Read(r) => &r.emit,
+            Rel::Filter(r) => &r.emit,
+            Rel::Fetch(r) => &r.emit,
+            Rel::Aggregate(r) => &r.emit,
+            Rel::Sort(r) => &r.emit,
+            Rel::Join(r) => &r.emit,
+            Rel::Project(r) => &r.emit,
+            Rel::Set(r) => &r.emit,
+            Rel::Cross(r) => &r.emit,
+            Rel::Reference(_) => &Emit::Direct,
+            Rel::HashJoin(r) | Rel::MergeJoin(r) => &r.emit,
+            Rel::Exchange(r) => &r.emit,
+        }
+    }
+
+    pub fn inputs(&self) -> Vec<&Rel> {
+        match self {
+            Rel::Read(_) => vec![],
+            Rel::Filter(r) => vec![&r.input],
+            Rel::Fetch(r) => vec![&r.input],
+            Rel::Aggregate(r) => vec![&r.input],
+            Rel::Sort(r) => vec![&r.input],
+            Rel::Join(r) => vec![&r.left, &r.right],
+            Rel::Project(r) => vec![&r.input],
+            Rel::Set(r) => r.inputs.iter().collect(),
+            Rel::Cross(r) => vec![&r.left, &r.right],
+            // the subtree is shared, so it is not owned as an input
+            Rel::Reference(_) => vec![],
+            Rel::HashJoin(r) | Rel::MergeJoin(r) => vec![&r.left, &r.right],
+            Rel::Exchange(r) => vec![&r.input],
+        }
+    }
+}
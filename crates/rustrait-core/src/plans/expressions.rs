@@ -1,20 +1,27 @@
+use crate::extensions::signature::VariantReference;
+use crate::plans::expressions::cast::{Cast, FailureBehavior};
 use crate::plans::expressions::field_reference::FieldReference;
 use crate::plans::expressions::literal::Literal;
 use crate::plans::expressions::scalar_function::ScalarFunctionInvocation;
 use crate::types::Type;
+use std::fmt;
 
+pub mod aggregate_function;
+pub mod cast;
 pub mod field_reference;
 pub mod literal;
 pub mod scalar_function;
+pub mod sort_field;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum Expression {
     Literal(Literal),
     FieldReference(FieldReference),
     ScalarFunction(ScalarFunctionInvocation),
+    Cast(Cast),
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum FunctionArgument {
     Value(Expression),
     // TODO: Improve Enum Argument modelling
@@ -22,14 +29,110 @@ pub enum FunctionArgument {
     Type(Type),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Function {
     pub signature: FunctionSignature,
     pub extension: URI,
+    // set when the plan was decoded against a registry containing the extension
+    pub variant: Option<VariantReference>,
 }
 
-#[derive(Debug, Clone)]
+impl Function {
+    // The function name without the argument types of a compound signature
+    pub fn name(&self) -> &str {
+        match self.signature.0.split_once(':') {
+            Some((name, _)) => name,
+            None => &self.signature.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct FunctionSignature(pub String);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct URI(pub String);
+
+// Writes an expression in function-call form, e.g. `add($0, 1:i32) -> i32?`. Field references
+// use the name of the referenced column if it is known and unambiguous, and `$<index>`
+// otherwise.
+pub struct ExpressionDisplay<'a> {
+    expression: &'a Expression,
+    columns: &'a [Option<String>],
+}
+
+impl Expression {
+    pub fn display<'a>(&'a self, columns: &'a [Option<String>]) -> ExpressionDisplay<'a> {
+        ExpressionDisplay {
+            expression: self,
+            columns,
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.display(&[]))
+    }
+}
+
+impl fmt::Display for ExpressionDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.expression {
+            Expression::Literal(literal) => write!(f, "{}", literal),
+            Expression::FieldReference(field_reference) => {
+                match column_name(field_reference.field, self.columns) {
+                    Some(name) => write!(f, "{}", name),
+                    None => write!(f, "${}", field_reference.field),
+                }
+            }
+            Expression::ScalarFunction(invocation) => {
+                write!(
+                    f,
+                    "{}({}) -> {}",
+                    invocation.function.name(),
+                    display_arguments(&invocation.args, self.columns),
+                    invocation.output_type
+                )
+            }
+            // casts returning null on failure read as `try_cast(...)`
+            Expression::Cast(cast) => {
+                let name = match cast.failure_behavior {
+                    FailureBehavior::ReturnNull => "try_cast",
+                    FailureBehavior::Unspecified | FailureBehavior::ThrowException => "cast",
+                };
+                write!(
+                    f,
+                    "{}({}) -> {}",
+                    name,
+                    cast.input.display(self.columns),
+                    cast.r#type
+                )
+            }
+        }
+    }
+}
+
+// The name of the referenced column, unless it is unnamed or another column has the same name
+pub(crate) fn column_name(field: i32, columns: &[Option<String>]) -> Option<&str> {
+    let name = usize::try_from(field)
+        .ok()
+        .and_then(|i| columns.get(i))?
+        .as_deref()?;
+    let count = columns
+        .iter()
+        .filter(|c| c.as_deref() == Some(name))
+        .count();
+    (count == 1).then_some(name)
+}
+
+pub fn display_arguments(args: &[FunctionArgument], columns: &[Option<String>]) -> String {
+    args.iter()
+        .map(|arg| match arg {
+            FunctionArgument::Value(e) => e.display(columns).to_string(),
+            FunctionArgument::Enum(e) => e.clone(),
+            FunctionArgument::Type(t) => format!("type {}", t),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
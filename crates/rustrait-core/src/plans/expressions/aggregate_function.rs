@@ -0,0 +1,27 @@
+use crate::plans::expressions::sort_field::SortField;
+use crate::plans::expressions::{Function, FunctionArgument};
+use crate::types::Type;
+
+#[derive(Debug, PartialEq)]
+pub struct AggregateFunctionInvocation {
+    pub function: Function,
+    pub args: Vec<FunctionArgument>,
+    pub output_type: Type,
+    pub phase: AggregationPhase,
+    pub invocation: AggregationInvocation,
+    pub sorts: Vec<SortField>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AggregationPhase {
+    InitialToIntermediate,
+    IntermediateToIntermediate,
+    InitialToResult,
+    IntermediateToResult,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AggregationInvocation {
+    All,
+    Distinct,
+}
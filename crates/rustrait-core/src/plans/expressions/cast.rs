@@ -0,0 +1,18 @@
+use crate::plans::expressions::Expression;
+use crate::types::Type;
+
+#[derive(Debug, PartialEq)]
+pub struct Cast {
+    pub r#type: Type,
+    pub input: Box<Expression>,
+    pub failure_behavior: FailureBehavior,
+}
+
+// What happens to values that cannot be converted to the type
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FailureBehavior {
+    // left to the consumer
+    Unspecified,
+    ReturnNull,
+    ThrowException,
+}
@@ -1,5 +1,5 @@
 // TODO: This is a vastly simplified field reference
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct FieldReference {
     pub field: i32,
 }
@@ -1,13 +1,25 @@
-#[derive(Debug)]
+use crate::types::Type;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
 pub enum Literal {
+    // a null of the given type, which must be nullable
+    Null(Type),
     Bool(Bool),
     I32(I32),
     I64(I64),
+    FP32(FP32),
+    FP64(FP64),
+    String(Str),
+    Binary(Binary),
+    Date(Date),
+    Timestamp(Timestamp),
+    Decimal(Decimal),
 }
 
 macro_rules! literal_struct {
-    ($literal_name: ident, $literal_type: tt) => {
-        #[derive(Debug)]
+    ($literal_name: ident, $literal_type: ty) => {
+        #[derive(Debug, PartialEq)]
         pub struct $literal_name {
             pub value: $literal_type,
             pub nullable: bool,
@@ -18,3 +30,146 @@ macro_rules! literal_struct {
 literal_struct![Bool, bool];
 literal_struct![I32, i32];
 literal_struct![I64, i64];
+literal_struct![FP32, f32];
+literal_struct![FP64, f64];
+literal_struct![Str, String];
+literal_struct![Binary, Vec<u8>];
+// days since the Unix epoch
+literal_struct![Date, i32];
+// microseconds since the Unix epoch
+literal_struct![Timestamp, i64];
+
+// The unscaled value, so 12.34 is 1234 with scale 2
+#[derive(Debug, PartialEq)]
+pub struct Decimal {
+    pub value: i128,
+    pub precision: i32,
+    pub scale: i32,
+    pub nullable: bool,
+}
+
+impl Literal {
+    pub fn output_type(&self) -> Type {
+        match self {
+            Literal::Null(t) => t.clone(),
+            Literal::Bool(l) => Type::Bool {
+                nullable: l.nullable,
+            },
+            Literal::I32(l) => Type::I32 {
+                nullable: l.nullable,
+            },
+            Literal::I64(l) => Type::I64 {
+                nullable: l.nullable,
+            },
+            Literal::FP32(l) => Type::FP32 {
+                nullable: l.nullable,
+            },
+            Literal::FP64(l) => Type::FP64 {
+                nullable: l.nullable,
+            },
+            Literal::String(l) => Type::String {
+                nullable: l.nullable,
+            },
+            Literal::Binary(l) => Type::Binary {
+                nullable: l.nullable,
+            },
+            Literal::Date(l) => Type::Date {
+                nullable: l.nullable,
+            },
+            Literal::Timestamp(l) => Type::Timestamp {
+                nullable: l.nullable,
+            },
+            Literal::Decimal(l) => Type::Decimal {
+                nullable: l.nullable,
+                precision: l.precision,
+                scale: l.scale,
+            },
+        }
+    }
+}
+
+// The value followed by its type, e.g. `1:i32`, `"a":string` or `12.34:decimal<4,2>`
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Null(_) => write!(f, "null")?,
+            Literal::Bool(l) => write!(f, "{}", l.value)?,
+            Literal::I32(l) => write!(f, "{}", l.value)?,
+            Literal::I64(l) => write!(f, "{}", l.value)?,
+            Literal::FP32(l) => write!(f, "{:?}", l.value)?,
+            Literal::FP64(l) => write!(f, "{:?}", l.value)?,
+            Literal::String(l) => write!(f, "{:?}", l.value)?,
+            Literal::Binary(l) => {
+                write!(f, "x'")?;
+                for byte in &l.value {
+                    write!(f, "{:02x}", byte)?;
+                }
+                write!(f, "'")?;
+            }
+            Literal::Date(l) => write!(f, "{}", l.value)?,
+            Literal::Timestamp(l) => write!(f, "{}", l.value)?,
+            Literal::Decimal(l) => write!(f, "{}", display_decimal(l.value, l.scale))?,
+        }
+        write!(f, ":{}", self.output_type())
+    }
+}
+
+fn display_decimal(value: i128, scale: i32) -> String {
+    let sign = if value < 0 { "-" } else { "" };
+    let digits = value.unsigned_abs().to_string();
+    let scale = usize::try_from(scale).unwrap_or(0);
+    if scale == 0 {
+        return format!("{}{}", sign, digits);
+    }
+    let digits = format!("{:0>width$}", digits, width = scale + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - scale);
+    format!("{}{}.{}", sign, whole, fraction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn displays_values_with_their_types() {
+        let decimal = |value, scale| {
+            Literal::Decimal(Decimal {
+                value,
+                precision: 5,
+                scale,
+                nullable: false,
+            })
+        };
+        assert_eq!(decimal(1234, 2).to_string(), "12.34:decimal<5,2>");
+        assert_eq!(decimal(-5, 3).to_string(), "-0.005:decimal<5,3>");
+        assert_eq!(decimal(42, 0).to_string(), "42:decimal<5,0>");
+        assert_eq!(
+            Literal::Null(Type::I32 { nullable: true }).to_string(),
+            "null:i32?"
+        );
+        assert_eq!(
+            Literal::String(Str {
+                value: "a\"b".to_string(),
+                nullable: false,
+            })
+            .to_string(),
+            "\"a\\\"b\":string"
+        );
+        assert_eq!(
+            Literal::Binary(Binary {
+                value: vec![0, 171],
+                nullable: true,
+            })
+            .to_string(),
+            "x'00ab':binary?"
+        );
+        assert_eq!(
+            Literal::FP64(FP64 {
+                value: 1.0,
+                nullable: false,
+            })
+            .to_string(),
+            "1.0:fp64"
+        );
+    }
+}
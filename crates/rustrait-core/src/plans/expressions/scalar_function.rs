@@ -1,7 +1,7 @@
 use crate::plans::expressions::{Function, FunctionArgument};
 use crate::types::Type;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct ScalarFunctionInvocation {
     pub function: Function,
     pub args: Vec<FunctionArgument>,
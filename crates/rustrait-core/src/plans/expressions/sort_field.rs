@@ -0,0 +1,16 @@
+use crate::plans::expressions::Expression;
+
+#[derive(Debug, PartialEq)]
+pub struct SortField {
+    pub expression: Expression,
+    pub direction: SortDirection,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SortDirection {
+    AscNullsFirst,
+    AscNullsLast,
+    DescNullsFirst,
+    DescNullsLast,
+    Clustered,
+}
@@ -1,13 +1,105 @@
-#[derive(Debug)]
+use crate::plans::expressions::URI;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Type {
-    I32 { nullable: bool },
-    I64 { nullable: bool },
-    FP64 { nullable: bool },
-    String { nullable: bool },
-    Struct { nullable: bool, types: Vec<Type> },
+    Bool {
+        nullable: bool,
+    },
+    I8 {
+        nullable: bool,
+    },
+    I16 {
+        nullable: bool,
+    },
+    I32 {
+        nullable: bool,
+    },
+    I64 {
+        nullable: bool,
+    },
+    FP32 {
+        nullable: bool,
+    },
+    FP64 {
+        nullable: bool,
+    },
+    String {
+        nullable: bool,
+    },
+    Binary {
+        nullable: bool,
+    },
+    Timestamp {
+        nullable: bool,
+    },
+    TimestampTz {
+        nullable: bool,
+    },
+    Date {
+        nullable: bool,
+    },
+    Time {
+        nullable: bool,
+    },
+    IntervalYear {
+        nullable: bool,
+    },
+    IntervalDay {
+        nullable: bool,
+    },
+    UUID {
+        nullable: bool,
+    },
+    FixedChar {
+        nullable: bool,
+        length: i32,
+    },
+    VarChar {
+        nullable: bool,
+        length: i32,
+    },
+    FixedBinary {
+        nullable: bool,
+        length: i32,
+    },
+    Decimal {
+        nullable: bool,
+        precision: i32,
+        scale: i32,
+    },
+    Struct {
+        nullable: bool,
+        types: Vec<Type>,
+    },
+    List {
+        nullable: bool,
+        r#type: Box<Type>,
+    },
+    Map {
+        nullable: bool,
+        key: Box<Type>,
+        value: Box<Type>,
+    },
+    UserDefined {
+        nullable: bool,
+        extension: URI,
+        name: String,
+        parameters: Vec<TypeParameter>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypeParameter {
+    Null,
+    DataType(Type),
+    Boolean(bool),
+    Integer(i64),
+    Enum(String),
+    String(String),
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct NamedStruct {
     // TODO: should these be combained into a vec of tuples?
     //    Would that even be valid given that these could have different lengths?
@@ -15,36 +107,175 @@ pub struct NamedStruct {
     pub types: Vec<Type>,
 }
 
-const NULLABILITY_UNSPECIFIED: i32 = 0;
-const NULLABILITY_NULLABLE: i32 = 1;
-const NULLABILITY_REQUIRED: i32 = 2;
+impl NamedStruct {
+    // Names of the top-level columns. Names are stored depth-first, so the field names of
+    // nested structs are skipped.
+    pub fn column_names(&self) -> Vec<String> {
+        let mut names = self.names.iter();
+        self.types
+            .iter()
+            .map(|t| {
+                let name = names.next().cloned().unwrap_or_default();
+                for _ in 0..nested_name_count(t) {
+                    names.next();
+                }
+                name
+            })
+            .collect()
+    }
+}
 
-pub fn nullability(n: i32) -> bool {
-    match n {
-        NULLABILITY_UNSPECIFIED => panic!("nullability must be specified"),
-        NULLABILITY_NULLABLE => return false,
-        NULLABILITY_REQUIRED => return true,
-        _ => panic!("you are using a secret 4th nullability {}. please don't", n),
+fn nested_name_count(t: &Type) -> usize {
+    match t {
+        Type::Struct { types, .. } => {
+            types.len() + types.iter().map(nested_name_count).sum::<usize>()
+        }
+        Type::List { r#type, .. } => nested_name_count(r#type),
+        Type::Map { key, value, .. } => nested_name_count(key) + nested_name_count(value),
+        _ => 0,
     }
 }
 
-impl From<&str> for Type {
-    fn from(s: &str) -> Self {
-        match s {
-            "i64" => Type::I64 { nullable: false },
-            "fp64" => Type::FP64 { nullable: false },
-            "str" => Type::String { nullable: false },
-            _ => panic!("cannot produce type from type string: {}", s),
+impl Type {
+    pub fn nullable(&self) -> bool {
+        match self {
+            Type::Bool { nullable }
+            | Type::I8 { nullable }
+            | Type::I16 { nullable }
+            | Type::I32 { nullable }
+            | Type::I64 { nullable }
+            | Type::FP32 { nullable }
+            | Type::FP64 { nullable }
+            | Type::String { nullable }
+            | Type::Binary { nullable }
+            | Type::Timestamp { nullable }
+            | Type::TimestampTz { nullable }
+            | Type::Date { nullable }
+            | Type::Time { nullable }
+            | Type::IntervalYear { nullable }
+            | Type::IntervalDay { nullable }
+            | Type::UUID { nullable }
+            | Type::FixedChar { nullable, .. }
+            | Type::VarChar { nullable, .. }
+            | Type::FixedBinary { nullable, .. }
+            | Type::Decimal { nullable, .. }
+            | Type::Struct { nullable, .. }
+            | Type::List { nullable, .. }
+            | Type::Map { nullable, .. }
+            | Type::UserDefined { nullable, .. } => *nullable,
+        }
+    }
+
+    pub fn with_nullable(mut self, n: bool) -> Type {
+        match &mut self {
+            Type::Bool { nullable }
+            | Type::I8 { nullable }
+            | Type::I16 { nullable }
+            | Type::I32 { nullable }
+            | Type::I64 { nullable }
+            | Type::FP32 { nullable }
+            | Type::FP64 { nullable }
+            | Type::String { nullable }
+            | Type::Binary { nullable }
+            | Type::Timestamp { nullable }
+            | Type::TimestampTz { nullable }
+            | Type::Date { nullable }
+            | Type::Time { nullable }
+            | Type::IntervalYear { nullable }
+            | Type::IntervalDay { nullable }
+            | Type::UUID { nullable }
+            | Type::FixedChar { nullable, .. }
+            | Type::VarChar { nullable, .. }
+            | Type::FixedBinary { nullable, .. }
+            | Type::Decimal { nullable, .. }
+            | Type::Struct { nullable, .. }
+            | Type::List { nullable, .. }
+            | Type::Map { nullable, .. }
+            | Type::UserDefined { nullable, .. } => *nullable = n,
         }
+        self
+    }
+
+    // The name of the type as used in extension YAML files
+    pub fn name(&self) -> String {
+        match self {
+            Type::Bool { .. } => "boolean".to_string(),
+            Type::I8 { .. } => "i8".to_string(),
+            Type::I16 { .. } => "i16".to_string(),
+            Type::I32 { .. } => "i32".to_string(),
+            Type::I64 { .. } => "i64".to_string(),
+            Type::FP32 { .. } => "fp32".to_string(),
+            Type::FP64 { .. } => "fp64".to_string(),
+            Type::String { .. } => "string".to_string(),
+            Type::Binary { .. } => "binary".to_string(),
+            Type::Timestamp { .. } => "timestamp".to_string(),
+            Type::TimestampTz { .. } => "timestamp_tz".to_string(),
+            Type::Date { .. } => "date".to_string(),
+            Type::Time { .. } => "time".to_string(),
+            Type::IntervalYear { .. } => "interval_year".to_string(),
+            Type::IntervalDay { .. } => "interval_day".to_string(),
+            Type::UUID { .. } => "uuid".to_string(),
+            Type::FixedChar { .. } => "fixedchar".to_string(),
+            Type::VarChar { .. } => "varchar".to_string(),
+            Type::FixedBinary { .. } => "fixedbinary".to_string(),
+            Type::Decimal { .. } => "decimal".to_string(),
+            Type::Struct { .. } => "struct".to_string(),
+            Type::List { .. } => "list".to_string(),
+            Type::Map { .. } => "map".to_string(),
+            Type::UserDefined { name, .. } => format!("u!{}", name),
+        }
+    }
+}
+
+// Written in the syntax of the extension YAML files, e.g. `decimal?<10,2>` or `list<i32?>`
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())?;
+        if self.nullable() {
+            write!(f, "?")?;
+        }
+        let parameters = match self {
+            Type::FixedChar { length, .. }
+            | Type::VarChar { length, .. }
+            | Type::FixedBinary { length, .. } => vec![length.to_string()],
+            Type::Decimal {
+                precision, scale, ..
+            } => vec![precision.to_string(), scale.to_string()],
+            Type::Struct { types, .. } => types.iter().map(|t| t.to_string()).collect(),
+            Type::List { r#type, .. } => vec![r#type.to_string()],
+            Type::Map { key, value, .. } => vec![key.to_string(), value.to_string()],
+            Type::UserDefined { parameters, .. } => {
+                parameters.iter().map(|p| p.to_string()).collect()
+            }
+            _ => return Ok(()),
+        };
+        if parameters.is_empty() {
+            return Ok(());
+        }
+        write!(f, "<{}>", parameters.join(","))
     }
 }
 
-impl From<substrait::text::simple_extensions::Type> for Type {
-    fn from(t: substrait::text::simple_extensions::Type) -> Self {
-        match t {
-            substrait::text::simple_extensions::Type::Variant0(str) => Type::from(str.as_str()),
-            // TODO: What even is this?
-            substrait::text::simple_extensions::Type::Variant1(_) => panic!(),
+impl fmt::Display for TypeParameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeParameter::Null => write!(f, "null"),
+            TypeParameter::DataType(t) => write!(f, "{}", t),
+            TypeParameter::Boolean(b) => write!(f, "{}", b),
+            TypeParameter::Integer(i) => write!(f, "{}", i),
+            TypeParameter::Enum(e) => write!(f, "{}", e),
+            TypeParameter::String(s) => write!(f, "{:?}", s),
         }
     }
 }
+
+const NULLABILITY_NULLABLE: i32 = 1;
+const NULLABILITY_REQUIRED: i32 = 2;
+
+pub fn proto_nullability(nullable: bool) -> i32 {
+    if nullable {
+        NULLABILITY_NULLABLE
+    } else {
+        NULLABILITY_REQUIRED
+    }
+}
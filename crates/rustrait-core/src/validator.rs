@@ -0,0 +1,729 @@
+use crate::diagnostics::{Diagnostic, Severity};
+use std::collections::{BTreeSet, HashMap};
+use substrait::proto;
+use substrait::proto::extensions::simple_extension_declaration::MappingType;
+
+// Checks that the extension declarations of a plan are consistent with each other and with the
+// anchors referenced by its relations. Problems that prevent decoding are errors, while unused
+// or redundant declarations are warnings.
+pub fn validate_extensions(plan: &proto::Plan) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+    let mut references = ReferenceCollector::default();
+    for (i, plan_rel) in plan.relations.iter().enumerate() {
+        let path = format!("relations[{}]", i);
+        match &plan_rel.rel_type {
+            Some(proto::plan_rel::RelType::Rel(rel)) => references.rel(rel, &path),
+            Some(proto::plan_rel::RelType::Root(root)) => {
+                if let Some(input) = &root.input {
+                    references.rel(input, &format!("{}.root.input", path))
+                }
+            }
+            None => {}
+        }
+    }
+
+    let mut diagnostics = vec![];
+    let declarations = Declarations::gather(plan, &mut diagnostics);
+
+    for (kind, referenced, declared) in [
+        ("function", &references.functions, &declarations.functions),
+        ("type", &references.types, &declarations.types),
+        (
+            "type variation",
+            &references.type_variations,
+            &declarations.type_variations,
+        ),
+    ] {
+        for (anchor, path) in referenced {
+            if !declared.contains_key(anchor) {
+                diagnostics.push(Diagnostic::error(
+                    path,
+                    format!("{} anchor {} is not declared", kind, anchor),
+                ));
+            }
+        }
+        // references inside uninspected relations are unknown, so nothing can be called unused
+        if references.uninspected.is_empty() {
+            let used: BTreeSet<&u32> = referenced.iter().map(|(anchor, _)| anchor).collect();
+            for (anchor, index) in sorted(declared) {
+                if !used.contains(anchor) {
+                    diagnostics.push(Diagnostic::warning(
+                        &format!("extensions[{}]", index),
+                        format!("{} anchor {} is declared but never used", kind, anchor),
+                    ));
+                }
+            }
+        }
+    }
+
+    for path in &references.uninspected {
+        diagnostics.push(Diagnostic::warning(
+            path,
+            "relation is not supported; its extension references are not validated".to_string(),
+        ));
+    }
+
+    if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        return Err(diagnostics);
+    }
+    Ok(diagnostics)
+}
+
+fn sorted(declared: &HashMap<u32, usize>) -> Vec<(&u32, &usize)> {
+    let mut declared: Vec<(&u32, &usize)> = declared.iter().collect();
+    declared.sort_by_key(|(_, index)| **index);
+    declared
+}
+
+// Anchors declared by the plan, mapped to the index of their declaration
+#[derive(Default)]
+struct Declarations {
+    functions: HashMap<u32, usize>,
+    types: HashMap<u32, usize>,
+    type_variations: HashMap<u32, usize>,
+}
+
+impl Declarations {
+    fn gather(plan: &proto::Plan, diagnostics: &mut Vec<Diagnostic>) -> Declarations {
+        let mut uris: HashMap<u32, usize> = HashMap::new();
+        for (i, extension_uri) in plan.extension_uris.iter().enumerate() {
+            let anchor = extension_uri.extension_uri_anchor;
+            if let Some(first) = uris.get(&anchor) {
+                diagnostics.push(Diagnostic::error(
+                    &format!("extension_uris[{}]", i),
+                    format!(
+                        "extension uri anchor {} is already declared by extension_uris[{}]",
+                        anchor, first
+                    ),
+                ));
+                continue;
+            }
+            uris.insert(anchor, i);
+        }
+
+        let mut declarations = Declarations::default();
+        let mut used_uris = BTreeSet::new();
+        // (uri anchor, name) of each declared function, to find functions declared twice
+        let mut function_names: HashMap<(u32, &str), u32> = HashMap::new();
+        for (i, extension) in plan.extensions.iter().enumerate() {
+            let path = format!("extensions[{}]", i);
+            let (kind, uri_reference, anchor, name, anchors) = match &extension.mapping_type {
+                Some(MappingType::ExtensionFunction(ef)) => (
+                    "function",
+                    ef.extension_uri_reference,
+                    ef.function_anchor,
+                    &ef.name,
+                    &mut declarations.functions,
+                ),
+                Some(MappingType::ExtensionType(et)) => (
+                    "type",
+                    et.extension_uri_reference,
+                    et.type_anchor,
+                    &et.name,
+                    &mut declarations.types,
+                ),
+                Some(MappingType::ExtensionTypeVariation(etv)) => (
+                    "type variation",
+                    etv.extension_uri_reference,
+                    etv.type_variation_anchor,
+                    &etv.name,
+                    &mut declarations.type_variations,
+                ),
+                None => {
+                    diagnostics.push(Diagnostic::error(
+                        &path,
+                        "extension declaration has no mapping type".to_string(),
+                    ));
+                    continue;
+                }
+            };
+
+            if uris.contains_key(&uri_reference) {
+                used_uris.insert(uri_reference);
+            } else {
+                diagnostics.push(Diagnostic::error(
+                    &path,
+                    format!(
+                        "{} {} refers to undeclared extension uri anchor {}",
+                        kind, name, uri_reference
+                    ),
+                ));
+            }
+
+            if let Some(first) = anchors.get(&anchor) {
+                diagnostics.push(Diagnostic::error(
+                    &path,
+                    format!(
+                        "{} anchor {} is already declared by extensions[{}]",
+                        kind, anchor, first
+                    ),
+                ));
+                continue;
+            }
+            anchors.insert(anchor, i);
+
+            if kind == "function" {
+                if let Some(other) = function_names.insert((uri_reference, name.as_str()), anchor) {
+                    diagnostics.push(Diagnostic::warning(
+                        &path,
+                        format!(
+                            "function {} is declared under both anchor {} and anchor {}",
+                            name, other, anchor
+                        ),
+                    ));
+                }
+            }
+        }
+
+        for (anchor, index) in sorted(&uris) {
+            if !used_uris.contains(anchor) {
+                diagnostics.push(Diagnostic::warning(
+                    &format!("extension_uris[{}]", index),
+                    format!(
+                        "extension uri {} is declared but no extension refers to it",
+                        plan.extension_uris[*index].uri
+                    ),
+                ));
+            }
+        }
+        declarations
+    }
+}
+
+// Gathers every anchor referenced by the relations of a plan, along with the path of the node
+// referencing it
+#[derive(Default)]
+struct ReferenceCollector {
+    functions: Vec<(u32, String)>,
+    types: Vec<(u32, String)>,
+    type_variations: Vec<(u32, String)>,
+    uninspected: Vec<String>,
+}
+
+impl ReferenceCollector {
+    fn rel(&mut self, rel: &proto::Rel, path: &str) {
+        let rel_type = match &rel.rel_type {
+            Some(rel_type) => rel_type,
+            None => return,
+        };
+        match rel_type {
+            proto::rel::RelType::Read(rr) => {
+                let path = format!("{}.read", path);
+                if let Some(base_schema) = &rr.base_schema {
+                    if let Some(r#struct) = &base_schema.r#struct {
+                        for (i, t) in r#struct.types.iter().enumerate() {
+                            self.r#type(t, &format!("{}.base_schema.types[{}]", path, i));
+                        }
+                    }
+                }
+                if let Some(filter) = &rr.filter {
+                    self.expression(filter, &format!("{}.filter", path));
+                }
+                if let Some(filter) = &rr.best_effort_filter {
+                    self.expression(filter, &format!("{}.best_effort_filter", path));
+                }
+            }
+            proto::rel::RelType::Filter(fr) => {
+                let path = format!("{}.filter", path);
+                self.input(&fr.input, &path);
+                if let Some(condition) = &fr.condition {
+                    self.expression(condition, &format!("{}.condition", path));
+                }
+            }
+            proto::rel::RelType::Fetch(fr) => self.input(&fr.input, &format!("{}.fetch", path)),
+            proto::rel::RelType::Aggregate(ar) => {
+                let path = format!("{}.aggregate", path);
+                self.input(&ar.input, &path);
+                for (i, grouping) in ar.groupings.iter().enumerate() {
+                    for (j, e) in grouping.grouping_expressions.iter().enumerate() {
+                        self.expression(e, &format!("{}.groupings[{}][{}]", path, i, j));
+                    }
+                }
+                for (i, measure) in ar.measures.iter().enumerate() {
+                    let path = format!("{}.measures[{}]", path, i);
+                    if let Some(af) = &measure.measure {
+                        self.functions.push((af.function_reference, path.clone()));
+                        self.arguments(&af.arguments, &path);
+                        self.sorts(&af.sorts, &path);
+                        if let Some(t) = &af.output_type {
+                            self.r#type(t, &format!("{}.output_type", path));
+                        }
+                    }
+                    if let Some(filter) = &measure.filter {
+                        self.expression(filter, &format!("{}.filter", path));
+                    }
+                }
+            }
+            proto::rel::RelType::Sort(sr) => {
+                let path = format!("{}.sort", path);
+                self.input(&sr.input, &path);
+                self.sorts(&sr.sorts, &path);
+            }
+            proto::rel::RelType::Join(jr) => {
+                let path = format!("{}.join", path);
+                self.side(&jr.left, &format!("{}.left", path));
+                self.side(&jr.right, &format!("{}.right", path));
+                if let Some(e) = &jr.expression {
+                    self.expression(e, &format!("{}.expression", path));
+                }
+                if let Some(e) = &jr.post_join_filter {
+                    self.expression(e, &format!("{}.post_join_filter", path));
+                }
+            }
+            proto::rel::RelType::Project(pr) => {
+                let path = format!("{}.project", path);
+                self.input(&pr.input, &path);
+                for (i, e) in pr.expressions.iter().enumerate() {
+                    self.expression(e, &format!("{}.expressions[{}]", path, i));
+                }
+            }
+            proto::rel::RelType::Set(sr) => {
+                for (i, input) in sr.inputs.iter().enumerate() {
+                    self.rel(input, &format!("{}.set.inputs[{}]", path, i));
+                }
+            }
+            proto::rel::RelType::Cross(cr) => {
+                let path = format!("{}.cross", path);
+                self.side(&cr.left, &format!("{}.left", path));
+                self.side(&cr.right, &format!("{}.right", path));
+            }
+            proto::rel::RelType::HashJoin(jr) => {
+                let path = format!("{}.hash_join", path);
+                self.side(&jr.left, &format!("{}.left", path));
+                self.side(&jr.right, &format!("{}.right", path));
+                if let Some(e) = &jr.post_join_filter {
+                    self.expression(e, &format!("{}.post_join_filter", path));
+                }
+            }
+            proto::rel::RelType::MergeJoin(jr) => {
+                let path = format!("{}.merge_join", path);
+                self.side(&jr.left, &format!("{}.left", path));
+                self.side(&jr.right, &format!("{}.right", path));
+                if let Some(e) = &jr.post_join_filter {
+                    self.expression(e, &format!("{}.post_join_filter", path));
+                }
+            }
+            proto::rel::RelType::Exchange(er) => {
+                let path = format!("{}.exchange", path);
+                self.input(&er.input, &path);
+                let expression = match &er.exchange_kind {
+                    Some(proto::exchange_rel::ExchangeKind::SingleTarget(target)) => {
+                        &target.expression
+                    }
+                    Some(proto::exchange_rel::ExchangeKind::MultiTarget(target)) => {
+                        &target.expression
+                    }
+                    _ => &None,
+                };
+                if let Some(e) = expression {
+                    self.expression(e, &format!("{}.expression", path));
+                }
+            }
+            // the referenced subtree is inspected as one of the plan relations
+            proto::rel::RelType::Reference(_) => {}
+            _ => self.uninspected.push(path.to_string()),
+        }
+    }
+
+    fn input(&mut self, input: &Option<Box<proto::Rel>>, path: &str) {
+        self.side(input, &format!("{}.input", path));
+    }
+
+    fn side(&mut self, rel: &Option<Box<proto::Rel>>, path: &str) {
+        if let Some(rel) = rel {
+            self.rel(rel, path);
+        }
+    }
+
+    fn sorts(&mut self, sorts: &[proto::SortField], path: &str) {
+        for (i, sort) in sorts.iter().enumerate() {
+            let path = format!("{}.sorts[{}]", path, i);
+            if let Some(e) = &sort.expr {
+                self.expression(e, &path);
+            }
+            if let Some(proto::sort_field::SortKind::ComparisonFunctionReference(reference)) =
+                &sort.sort_kind
+            {
+                self.functions.push((*reference, path));
+            }
+        }
+    }
+
+    fn arguments(&mut self, arguments: &[proto::FunctionArgument], path: &str) {
+        for (i, argument) in arguments.iter().enumerate() {
+            let path = format!("{}.args[{}]", path, i);
+            match &argument.arg_type {
+                Some(proto::function_argument::ArgType::Value(e)) => self.expression(e, &path),
+                Some(proto::function_argument::ArgType::Type(t)) => self.r#type(t, &path),
+                Some(proto::function_argument::ArgType::Enum(_)) | None => {}
+            }
+        }
+    }
+
+    fn expression(&mut self, expression: &proto::Expression, path: &str) {
+        use proto::expression::RexType;
+        let rex_type = match &expression.rex_type {
+            Some(rex_type) => rex_type,
+            None => return,
+        };
+        match rex_type {
+            RexType::Literal(literal) => self.literal(literal, path),
+            RexType::Selection(field_reference) => {
+                if let Some(proto::expression::field_reference::RootType::Expression(e)) =
+                    &field_reference.root_type
+                {
+                    self.expression(e, &format!("{}.root", path));
+                }
+            }
+            RexType::ScalarFunction(sf) => {
+                self.functions
+                    .push((sf.function_reference, path.to_string()));
+                self.arguments(&sf.arguments, path);
+                if let Some(t) = &sf.output_type {
+                    self.r#type(t, &format!("{}.output_type", path));
+                }
+            }
+            RexType::WindowFunction(wf) => {
+                self.functions
+                    .push((wf.function_reference, path.to_string()));
+                self.arguments(&wf.arguments, path);
+                self.sorts(&wf.sorts, path);
+                for (i, e) in wf.partitions.iter().enumerate() {
+                    self.expression(e, &format!("{}.partitions[{}]", path, i));
+                }
+                if let Some(t) = &wf.output_type {
+                    self.r#type(t, &format!("{}.output_type", path));
+                }
+            }
+            RexType::IfThen(if_then) => {
+                for (i, clause) in if_then.ifs.iter().enumerate() {
+                    if let Some(e) = &clause.r#if {
+                        self.expression(e, &format!("{}.ifs[{}].if", path, i));
+                    }
+                    if let Some(e) = &clause.then {
+                        self.expression(e, &format!("{}.ifs[{}].then", path, i));
+                    }
+                }
+                if let Some(e) = &if_then.r#else {
+                    self.expression(e, &format!("{}.else", path));
+                }
+            }
+            RexType::SwitchExpression(switch) => {
+                if let Some(e) = &switch.r#match {
+                    self.expression(e, &format!("{}.match", path));
+                }
+                for (i, clause) in switch.ifs.iter().enumerate() {
+                    if let Some(literal) = &clause.r#if {
+                        self.literal(literal, &format!("{}.ifs[{}].if", path, i));
+                    }
+                    if let Some(e) = &clause.then {
+                        self.expression(e, &format!("{}.ifs[{}].then", path, i));
+                    }
+                }
+                if let Some(e) = &switch.r#else {
+                    self.expression(e, &format!("{}.else", path));
+                }
+            }
+            RexType::SingularOrList(or_list) => {
+                if let Some(e) = &or_list.value {
+                    self.expression(e, &format!("{}.value", path));
+                }
+                for (i, e) in or_list.options.iter().enumerate() {
+                    self.expression(e, &format!("{}.options[{}]", path, i));
+                }
+            }
+            RexType::MultiOrList(or_list) => {
+                for (i, e) in or_list.value.iter().enumerate() {
+                    self.expression(e, &format!("{}.value[{}]", path, i));
+                }
+                for (i, record) in or_list.options.iter().enumerate() {
+                    for (j, e) in record.fields.iter().enumerate() {
+                        self.expression(e, &format!("{}.options[{}][{}]", path, i, j));
+                    }
+                }
+            }
+            RexType::Cast(cast) => {
+                if let Some(t) = &cast.r#type {
+                    self.r#type(t, &format!("{}.type", path));
+                }
+                if let Some(e) = &cast.input {
+                    self.expression(e, &format!("{}.input", path));
+                }
+            }
+            RexType::Subquery(subquery) => self.subquery(subquery, path),
+            RexType::Nested(nested) => {
+                use proto::expression::nested::NestedType;
+                self.type_variation(nested.type_variation_reference, path);
+                match &nested.nested_type {
+                    Some(NestedType::Struct(s)) => {
+                        for (i, e) in s.fields.iter().enumerate() {
+                            self.expression(e, &format!("{}.fields[{}]", path, i));
+                        }
+                    }
+                    Some(NestedType::List(l)) => {
+                        for (i, e) in l.values.iter().enumerate() {
+                            self.expression(e, &format!("{}.values[{}]", path, i));
+                        }
+                    }
+                    Some(NestedType::Map(m)) => {
+                        for (i, kv) in m.key_values.iter().enumerate() {
+                            if let Some(e) = &kv.key {
+                                self.expression(e, &format!("{}.key_values[{}].key", path, i));
+                            }
+                            if let Some(e) = &kv.value {
+                                self.expression(e, &format!("{}.key_values[{}].value", path, i));
+                            }
+                        }
+                    }
+                    None => {}
+                }
+            }
+            #[allow(deprecated)]
+            RexType::Enum(_) => {}
+        }
+    }
+
+    fn subquery(&mut self, subquery: &proto::expression::Subquery, path: &str) {
+        use proto::expression::subquery::SubqueryType;
+        let path = format!("{}.subquery", path);
+        match &subquery.subquery_type {
+            Some(SubqueryType::Scalar(scalar)) => {
+                self.side(&scalar.input, &format!("{}.input", path))
+            }
+            Some(SubqueryType::InPredicate(in_predicate)) => {
+                for (i, e) in in_predicate.needles.iter().enumerate() {
+                    self.expression(e, &format!("{}.needles[{}]", path, i));
+                }
+                self.side(&in_predicate.haystack, &format!("{}.haystack", path));
+            }
+            Some(SubqueryType::SetPredicate(set_predicate)) => {
+                self.side(&set_predicate.tuples, &format!("{}.tuples", path))
+            }
+            Some(SubqueryType::SetComparison(set_comparison)) => {
+                if let Some(e) = &set_comparison.left {
+                    self.expression(e, &format!("{}.left", path));
+                }
+                self.side(&set_comparison.right, &format!("{}.right", path));
+            }
+            None => {}
+        }
+    }
+
+    fn literal(&mut self, literal: &proto::expression::Literal, path: &str) {
+        use proto::expression::literal::LiteralType;
+        self.type_variation(literal.type_variation_reference, path);
+        match &literal.literal_type {
+            Some(LiteralType::Null(t)) => self.r#type(t, path),
+            Some(LiteralType::UserDefined(ud)) => {
+                self.types.push((ud.type_reference, path.to_string()));
+                self.parameters(&ud.type_parameters, path);
+            }
+            Some(LiteralType::List(list)) => {
+                for (i, l) in list.values.iter().enumerate() {
+                    self.literal(l, &format!("{}.values[{}]", path, i));
+                }
+            }
+            Some(LiteralType::Struct(s)) => {
+                for (i, l) in s.fields.iter().enumerate() {
+                    self.literal(l, &format!("{}.fields[{}]", path, i));
+                }
+            }
+            Some(LiteralType::Map(map)) => {
+                for (i, kv) in map.key_values.iter().enumerate() {
+                    if let Some(l) = &kv.key {
+                        self.literal(l, &format!("{}.key_values[{}].key", path, i));
+                    }
+                    if let Some(l) = &kv.value {
+                        self.literal(l, &format!("{}.key_values[{}].value", path, i));
+                    }
+                }
+            }
+            Some(LiteralType::EmptyList(list)) => {
+                self.type_variation(list.type_variation_reference, path);
+                if let Some(t) = &list.r#type {
+                    self.r#type(t, path);
+                }
+            }
+            Some(LiteralType::EmptyMap(map)) => {
+                self.type_variation(map.type_variation_reference, path);
+                if let Some(t) = &map.key {
+                    self.r#type(t, &format!("{}.key", path));
+                }
+                if let Some(t) = &map.value {
+                    self.r#type(t, &format!("{}.value", path));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn r#type(&mut self, t: &proto::Type, path: &str) {
+        use proto::r#type::Kind;
+        let kind = match &t.kind {
+            Some(kind) => kind,
+            None => return,
+        };
+        let variation = match kind {
+            Kind::Bool(v) => v.type_variation_reference,
+            Kind::I8(v) => v.type_variation_reference,
+            Kind::I16(v) => v.type_variation_reference,
+            Kind::I32(v) => v.type_variation_reference,
+            Kind::I64(v) => v.type_variation_reference,
+            Kind::Fp32(v) => v.type_variation_reference,
+            Kind::Fp64(v) => v.type_variation_reference,
+            Kind::String(v) => v.type_variation_reference,
+            Kind::Binary(v) => v.type_variation_reference,
+            #[allow(deprecated)]
+            Kind::Timestamp(v) => v.type_variation_reference,
+            Kind::Date(v) => v.type_variation_reference,
+            #[allow(deprecated)]
+            Kind::Time(v) => v.type_variation_reference,
+            Kind::IntervalYear(v) => v.type_variation_reference,
+            Kind::IntervalDay(v) => v.type_variation_reference,
+            #[allow(deprecated)]
+            Kind::TimestampTz(v) => v.type_variation_reference,
+            Kind::Uuid(v) => v.type_variation_reference,
+            Kind::FixedChar(v) => v.type_variation_reference,
+            Kind::Varchar(v) => v.type_variation_reference,
+            Kind::FixedBinary(v) => v.type_variation_reference,
+            Kind::Decimal(v) => v.type_variation_reference,
+            Kind::PrecisionTimestamp(v) => v.type_variation_reference,
+            Kind::PrecisionTimestampTz(v) => v.type_variation_reference,
+            Kind::Struct(s) => {
+                for (i, t) in s.types.iter().enumerate() {
+                    self.r#type(t, &format!("{}.types[{}]", path, i));
+                }
+                s.type_variation_reference
+            }
+            Kind::List(list) => {
+                if let Some(t) = &list.r#type {
+                    self.r#type(t, &format!("{}.type", path));
+                }
+                list.type_variation_reference
+            }
+            Kind::Map(map) => {
+                if let Some(t) = &map.key {
+                    self.r#type(t, &format!("{}.key", path));
+                }
+                if let Some(t) = &map.value {
+                    self.r#type(t, &format!("{}.value", path));
+                }
+                map.type_variation_reference
+            }
+            Kind::UserDefined(ud) => {
+                self.types.push((ud.type_reference, path.to_string()));
+                self.parameters(&ud.type_parameters, path);
+                ud.type_variation_reference
+            }
+            #[allow(deprecated)]
+            Kind::UserDefinedTypeReference(reference) => {
+                self.types.push((*reference, path.to_string()));
+                0
+            }
+        };
+        self.type_variation(variation, path);
+    }
+
+    fn parameters(&mut self, parameters: &[proto::r#type::Parameter], path: &str) {
+        for (i, p) in parameters.iter().enumerate() {
+            if let Some(proto::r#type::parameter::Parameter::DataType(t)) = &p.parameter {
+                self.r#type(t, &format!("{}.parameters[{}]", path, i));
+            }
+        }
+    }
+
+    // 0 is the system-preferred variation and does not refer to a declaration
+    fn type_variation(&mut self, reference: u32, path: &str) {
+        if reference != 0 {
+            self.type_variations.push((reference, path.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proto::extensions::simple_extension_declaration::ExtensionFunction;
+    use proto::extensions::{SimpleExtensionDeclaration, SimpleExtensionUri};
+
+    fn uri(anchor: u32, uri: &str) -> SimpleExtensionUri {
+        SimpleExtensionUri {
+            extension_uri_anchor: anchor,
+            uri: uri.to_string(),
+        }
+    }
+
+    fn function(uri_reference: u32, anchor: u32, name: &str) -> SimpleExtensionDeclaration {
+        SimpleExtensionDeclaration {
+            mapping_type: Some(MappingType::ExtensionFunction(ExtensionFunction {
+                extension_uri_reference: uri_reference,
+                function_anchor: anchor,
+                name: name.to_string(),
+            })),
+        }
+    }
+
+    // A project of a single scalar function invocation
+    fn plan(function_reference: u32) -> proto::Plan {
+        let invocation = proto::Expression {
+            rex_type: Some(proto::expression::RexType::ScalarFunction(
+                proto::expression::ScalarFunction {
+                    function_reference,
+                    ..Default::default()
+                },
+            )),
+        };
+        let project = proto::Rel {
+            rel_type: Some(proto::rel::RelType::Project(Box::new(proto::ProjectRel {
+                input: Some(Box::new(proto::Rel {
+                    rel_type: Some(proto::rel::RelType::Read(Box::default())),
+                })),
+                expressions: vec![invocation],
+                ..Default::default()
+            }))),
+        };
+        proto::Plan {
+            relations: vec![proto::PlanRel {
+                rel_type: Some(proto::plan_rel::RelType::Rel(project)),
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn messages(diagnostics: &[Diagnostic]) -> Vec<String> {
+        diagnostics.iter().map(|d| d.to_string()).collect()
+    }
+
+    #[test]
+    fn accepts_consistent_declarations() {
+        let mut plan = plan(1);
+        plan.extension_uris = vec![uri(1, "/functions_arithmetic.yaml")];
+        plan.extensions = vec![function(1, 1, "add:i64_i64")];
+        assert_eq!(validate_extensions(&plan).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn reports_inconsistent_declarations() {
+        let mut plan = plan(3);
+        plan.extension_uris = vec![
+            uri(1, "/functions_arithmetic.yaml"),
+            uri(2, "/functions_string.yaml"),
+        ];
+        plan.extensions = vec![
+            function(1, 1, "add:i64_i64"),
+            function(1, 2, "add:i64_i64"),
+            function(5, 2, "concat:vchar"),
+        ];
+        assert_eq!(
+            messages(&validate_extensions(&plan).unwrap_err()),
+            vec![
+                "warning at extensions[1]: function add:i64_i64 is declared under both anchor 1 and anchor 2",
+                "error at extensions[2]: function concat:vchar refers to undeclared extension uri anchor 5",
+                "error at extensions[2]: function anchor 2 is already declared by extensions[1]",
+                "warning at extension_uris[1]: extension uri /functions_string.yaml is declared but no extension refers to it",
+                "error at relations[0].project.expressions[0]: function anchor 3 is not declared",
+                "warning at extensions[0]: function anchor 1 is declared but never used",
+                "warning at extensions[1]: function anchor 2 is declared but never used",
+            ]
+        );
+    }
+}
@@ -0,0 +1,586 @@
+use proptest::prelude::*;
+use rustrait_core::checker::check_plan;
+use rustrait_core::decoder::decode_prost_plan;
+use rustrait_core::encoder::encode_prost_plan;
+use rustrait_core::plans::expressions::aggregate_function::{
+    AggregateFunctionInvocation, AggregationInvocation, AggregationPhase,
+};
+use rustrait_core::plans::expressions::cast::{Cast, FailureBehavior};
+use rustrait_core::plans::expressions::field_reference::FieldReference;
+use rustrait_core::plans::expressions::literal::{Bool, Literal, I32, I64};
+use rustrait_core::plans::expressions::scalar_function::ScalarFunctionInvocation;
+use rustrait_core::plans::expressions::sort_field::{SortDirection, SortField};
+use rustrait_core::plans::expressions::{
+    Expression, Function, FunctionArgument, FunctionSignature, URI,
+};
+use rustrait_core::plans::{
+    Aggregate, Cross, DelimitedTextOptions, Emit, Exchange, ExchangeKind, Fetch, FileFormat,
+    FileOrFiles, FilePath, Filter, Join, JoinType, KeyedJoin, Measure, Plan, Project, Read,
+    ReadType, Reference, Rel, Set, SetOp, Sort,
+};
+use rustrait_core::types::{NamedStruct, Type};
+use rustrait_core::validator::validate_extensions;
+use std::fs;
+use std::path::Path;
+use substrait::proto;
+
+// Checks that decode -> encode -> decode preserves the plan, and that encoding is stable
+fn assert_round_trips(plan: &Plan) {
+    let encoded = encode_prost_plan(plan);
+    if let Err(diagnostics) = validate_extensions(&encoded) {
+        panic!("encoded plan is inconsistent: {:?}", diagnostics);
+    }
+    let decoded = decode_prost_plan(&encoded).unwrap();
+    assert_eq!(&decoded, plan);
+    assert_eq!(encode_prost_plan(&decoded), encoded);
+}
+
+fn load(path: &Path) -> Plan {
+    let json = fs::read_to_string(path).expect("plan is readable");
+    let proto_plan = serde_json::from_str::<proto::Plan>(&json)
+        .unwrap_or_else(|e| panic!("cannot parse {}: {}", path.display(), e));
+    decode_prost_plan(&proto_plan)
+        .unwrap_or_else(|e| panic!("cannot decode {}: {:?}", path.display(), e))
+}
+
+#[test]
+fn simple_select_round_trips() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("simple-select.substrait");
+    assert_round_trips(&load(&path));
+}
+
+#[test]
+fn corpus_round_trips() {
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/plans");
+    let mut paths: Vec<_> = fs::read_dir(&corpus)
+        .expect("corpus directory exists")
+        .map(|entry| entry.expect("corpus entry is readable").path())
+        .filter(|path| path.extension().is_some_and(|e| e == "json"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+    for path in paths {
+        assert_round_trips(&load(&path));
+    }
+}
+
+// Builds a random well-typed plan from a sequence of choices, so proptest can shrink failing
+// plans by shrinking the choices.
+struct PlanGenerator {
+    choices: Vec<u32>,
+    position: usize,
+    // subtrees generated so far, with the types of their columns
+    subtrees: Vec<(Rel, Vec<Type>)>,
+}
+
+impl PlanGenerator {
+    fn choose(&mut self, n: u32) -> u32 {
+        let choice = self.choices.get(self.position).copied().unwrap_or(0);
+        self.position += 1;
+        choice % n
+    }
+
+    fn plan(&mut self) -> Plan {
+        // later subtrees and the root may refer to the subtrees generated before them
+        for _ in 0..self.choose(3) {
+            let subtree = self.rel(2);
+            self.subtrees.push(subtree);
+        }
+        let (root, types) = self.rel(3);
+        let names = (0..types.len()).map(|i| format!("out{}", i)).collect();
+        Plan {
+            root: Box::new(root),
+            names,
+            subtrees: self.subtrees.drain(..).map(|(rel, _)| rel).collect(),
+        }
+    }
+
+    fn r#type(&mut self) -> Type {
+        let nullable = self.choose(2) == 0;
+        match self.choose(3) {
+            0 => Type::Bool { nullable },
+            1 => Type::I32 { nullable },
+            _ => Type::I64 { nullable },
+        }
+    }
+
+    // Returns the relation and the types of the columns it outputs
+    fn rel(&mut self, depth: u32) -> (Rel, Vec<Type>) {
+        if depth == 0 {
+            return self.read();
+        }
+        match self.choose(12) {
+            0 => self.read(),
+            1 => {
+                let (input, types) = self.rel(depth - 1);
+                let condition = self.expression(&types, &Type::Bool { nullable: true }, 2);
+                let rel = Rel::Filter(Filter {
+                    emit: Emit::Direct,
+                    input: Box::new(input),
+                    condition,
+                });
+                (rel, types)
+            }
+            2 => {
+                let (input, types) = self.rel(depth - 1);
+                let rel = Rel::Fetch(Fetch {
+                    emit: Emit::Direct,
+                    input: Box::new(input),
+                    offset: self.choose(10) as i64,
+                    count: self.choose(10) as i64 - 1,
+                });
+                (rel, types)
+            }
+            3 => {
+                let (input, input_types) = self.rel(depth - 1);
+                let mut types = input_types.clone();
+                let mut expressions = vec![];
+                for _ in 0..self.choose(3) + 1 {
+                    let t = self.r#type();
+                    expressions.push(self.expression(&input_types, &t, 2));
+                    types.push(t);
+                }
+                let (emit, types) = self.emit(types);
+                let rel = Rel::Project(Project {
+                    emit,
+                    input: Box::new(input),
+                    expressions,
+                });
+                (rel, types)
+            }
+            4 => {
+                let (input, types) = self.rel(depth - 1);
+                let sorts = (0..self.choose(3))
+                    .map(|_| {
+                        let t = types[self.choose(types.len() as u32) as usize].clone();
+                        SortField {
+                            expression: self.expression(&types, &t, 1),
+                            direction: match self.choose(5) {
+                                0 => SortDirection::AscNullsFirst,
+                                1 => SortDirection::AscNullsLast,
+                                2 => SortDirection::DescNullsFirst,
+                                3 => SortDirection::DescNullsLast,
+                                _ => SortDirection::Clustered,
+                            },
+                        }
+                    })
+                    .collect();
+                let rel = Rel::Sort(Sort {
+                    emit: Emit::Direct,
+                    input: Box::new(input),
+                    sorts,
+                });
+                (rel, types)
+            }
+            5 => {
+                let (left, mut types) = self.rel(depth - 1);
+                let (right, right_types) = self.rel(depth - 1);
+                types.extend(right_types);
+                let (emit, types) = self.emit(types);
+                let rel = Rel::Cross(Cross {
+                    emit,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                });
+                (rel, types)
+            }
+            6 => {
+                let (left, left_types) = self.rel(depth - 1);
+                let (mut right, mut right_types) = self.rel(depth - 1);
+                let (mut left_keys, mut right_keys) = (vec![], vec![]);
+                for _ in 0..self.choose(3) + 1 {
+                    let left_key = self.choose(left_types.len() as u32) as usize;
+                    // keys are compared, so they must have the same type up to nullability
+                    let key_type = left_types[left_key].clone().with_nullable(false);
+                    let matching: Vec<usize> = (0..right_types.len())
+                        .filter(|i| right_types[*i].clone().with_nullable(false) == key_type)
+                        .collect();
+                    if matching.is_empty() {
+                        continue;
+                    }
+                    left_keys.push(FieldReference {
+                        field: left_key as i32,
+                    });
+                    right_keys.push(FieldReference {
+                        field: matching[self.choose(matching.len() as u32) as usize] as i32,
+                    });
+                }
+                // a join without keys is a cross join, so join with a read of the left columns
+                if left_keys.is_empty() {
+                    right = self.read_of(&left_types);
+                    right_types = left_types.clone();
+                    let key = self.choose(left_types.len() as u32) as i32;
+                    left_keys.push(FieldReference { field: key });
+                    right_keys.push(FieldReference { field: key });
+                }
+                let combined: Vec<Type> = left_types.iter().chain(&right_types).cloned().collect();
+                let post_join_filter = match self.choose(2) {
+                    0 => None,
+                    _ => Some(self.expression(&combined, &Type::Bool { nullable: true }, 1)),
+                };
+                let join_type = self.join_type();
+                let (emit, types) = self.emit(join_output(join_type, left_types, right_types));
+                let join = KeyedJoin {
+                    emit,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                    left_keys,
+                    right_keys,
+                    post_join_filter,
+                    join_type,
+                };
+                let rel = match self.choose(2) {
+                    0 => Rel::HashJoin(join),
+                    _ => Rel::MergeJoin(join),
+                };
+                (rel, types)
+            }
+            7 => {
+                let (input, types) = self.rel(depth - 1);
+                let kind = match self.choose(3) {
+                    0 => ExchangeKind::ScatterByFields(vec![FieldReference {
+                        field: self.choose(types.len() as u32) as i32,
+                    }]),
+                    1 => ExchangeKind::RoundRobin {
+                        exact: self.choose(2) == 0,
+                    },
+                    _ => ExchangeKind::Broadcast,
+                };
+                let rel = Rel::Exchange(Exchange {
+                    emit: Emit::Direct,
+                    input: Box::new(input),
+                    partition_count: self.choose(8) as i32 + 1,
+                    kind,
+                });
+                (rel, types)
+            }
+            8 => {
+                let (input, types) = self.rel(depth - 1);
+                let op = match self.choose(6) {
+                    0 => SetOp::MinusPrimary,
+                    1 => SetOp::MinusMultiset,
+                    2 => SetOp::IntersectionPrimary,
+                    3 => SetOp::IntersectionMultiset,
+                    4 => SetOp::UnionDistinct,
+                    _ => SetOp::UnionAll,
+                };
+                let rel = Rel::Set(Set {
+                    emit: Emit::Direct,
+                    inputs: vec![input, self.read_of(&types)],
+                    op,
+                });
+                (rel, types)
+            }
+            9 => self.aggregate(depth),
+            10 => {
+                let (left, left_types) = self.rel(depth - 1);
+                let (right, right_types) = self.rel(depth - 1);
+                let combined: Vec<Type> = left_types.iter().chain(&right_types).cloned().collect();
+                let mut condition = || match self.choose(2) {
+                    0 => None,
+                    _ => Some(self.expression(&combined, &Type::Bool { nullable: true }, 2)),
+                };
+                let (expression, post_join_filter) = (condition(), condition());
+                let join_type = self.join_type();
+                let (emit, types) = self.emit(join_output(join_type, left_types, right_types));
+                let rel = Rel::Join(Join {
+                    emit,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                    expression,
+                    post_join_filter,
+                    join_type,
+                });
+                (rel, types)
+            }
+            _ => {
+                if self.subtrees.is_empty() {
+                    return self.read();
+                }
+                let subtree = self.choose(self.subtrees.len() as u32) as usize;
+                let types = self.subtrees[subtree].1.clone();
+                (Rel::Reference(Reference { subtree }), types)
+            }
+        }
+    }
+
+    // Groups by random columns in up to three grouping sets, computing one or two counts
+    fn aggregate(&mut self, depth: u32) -> (Rel, Vec<Type>) {
+        let (input, input_types) = self.rel(depth - 1);
+        let groupings: Vec<Vec<Expression>> = (0..self.choose(4))
+            .map(|_| {
+                (0..self.choose(3))
+                    .map(|_| {
+                        Expression::FieldReference(FieldReference {
+                            field: self.choose(input_types.len() as u32) as i32,
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // distinct keys in order of appearance, nullable unless every grouping set has them
+        let mut keys: Vec<&Expression> = vec![];
+        let mut types = vec![];
+        for expression in groupings.iter().flatten() {
+            if keys.contains(&expression) {
+                continue;
+            }
+            keys.push(expression);
+            let field = match expression {
+                Expression::FieldReference(FieldReference { field }) => *field as usize,
+                _ => unreachable!("grouping keys are field references"),
+            };
+            let nullable =
+                input_types[field].nullable() || !groupings.iter().all(|g| g.contains(expression));
+            types.push(input_types[field].clone().with_nullable(nullable));
+        }
+
+        let measures: Vec<Measure> = (0..self.choose(2) + 1)
+            .map(|_| {
+                let t = input_types[self.choose(input_types.len() as u32) as usize].clone();
+                let argument = self.expression(&input_types, &t, 1);
+                let sorts = (0..self.choose(2))
+                    .map(|_| SortField {
+                        expression: self.expression(&input_types, &t, 0),
+                        direction: SortDirection::AscNullsLast,
+                    })
+                    .collect();
+                let filter = match self.choose(2) {
+                    0 => None,
+                    _ => Some(self.expression(&input_types, &Type::Bool { nullable: true }, 1)),
+                };
+                Measure {
+                    function: AggregateFunctionInvocation {
+                        function: Function {
+                            signature: FunctionSignature("count:any".to_string()),
+                            extension: URI("/functions_aggregate_generic.yaml".to_string()),
+                            variant: None,
+                        },
+                        args: vec![FunctionArgument::Value(argument)],
+                        output_type: Type::I64 { nullable: false },
+                        phase: match self.choose(4) {
+                            0 => AggregationPhase::InitialToIntermediate,
+                            1 => AggregationPhase::IntermediateToIntermediate,
+                            2 => AggregationPhase::InitialToResult,
+                            _ => AggregationPhase::IntermediateToResult,
+                        },
+                        invocation: match self.choose(2) {
+                            0 => AggregationInvocation::All,
+                            _ => AggregationInvocation::Distinct,
+                        },
+                        sorts,
+                    },
+                    filter,
+                }
+            })
+            .collect();
+        types.extend(measures.iter().map(|m| m.function.output_type.clone()));
+        if groupings.len() > 1 {
+            types.push(Type::I32 { nullable: false });
+        }
+
+        let (emit, types) = self.emit(types);
+        let rel = Rel::Aggregate(Aggregate {
+            emit,
+            input: Box::new(input),
+            groupings,
+            measures,
+        });
+        (rel, types)
+    }
+
+    fn join_type(&mut self) -> JoinType {
+        match self.choose(12) {
+            0 => JoinType::Inner,
+            1 => JoinType::Outer,
+            2 => JoinType::Left,
+            3 => JoinType::Right,
+            4 => JoinType::LeftSemi,
+            5 => JoinType::LeftAnti,
+            6 => JoinType::LeftSingle,
+            7 => JoinType::RightSemi,
+            8 => JoinType::RightAnti,
+            9 => JoinType::RightSingle,
+            10 => JoinType::LeftMark,
+            _ => JoinType::RightMark,
+        }
+    }
+
+    fn read(&mut self) -> (Rel, Vec<Type>) {
+        let types: Vec<Type> = (0..self.choose(4) + 1).map(|_| self.r#type()).collect();
+        (self.read_of(&types), types)
+    }
+
+    fn read_of(&mut self, types: &[Type]) -> Rel {
+        Rel::Read(Read {
+            emit: Emit::Direct,
+            base_schema: NamedStruct {
+                names: (0..types.len()).map(|i| format!("c{}", i)).collect(),
+                types: types.to_vec(),
+            },
+            read_type: self.read_type(types),
+        })
+    }
+
+    fn read_type(&mut self, types: &[Type]) -> ReadType {
+        match self.choose(3) {
+            0 => {
+                return ReadType::NamedTable {
+                    names: vec![format!("t{}", self.choose(100))],
+                }
+            }
+            1 => {
+                let rows = (0..self.choose(3))
+                    .map(|_| {
+                        types
+                            .iter()
+                            .map(|t| match self.literal(t) {
+                                Expression::Literal(literal) => literal,
+                                _ => unreachable!("literal() returns literals"),
+                            })
+                            .collect()
+                    })
+                    .collect();
+                return ReadType::VirtualTable { rows };
+            }
+            _ => {}
+        }
+        let items = (0..self.choose(3) + 1)
+            .map(|i| FileOrFiles {
+                path: match self.choose(4) {
+                    0 => FilePath::Uri(format!("file:///data/f{}.parquet", i)),
+                    1 => FilePath::Glob("file:///data/*.parquet".to_string()),
+                    2 => FilePath::File(format!("file:///data/f{}.parquet", i)),
+                    _ => FilePath::Folder("file:///data/".to_string()),
+                },
+                format: match self.choose(3) {
+                    0 => FileFormat::Parquet,
+                    1 => FileFormat::Arrow,
+                    _ => FileFormat::DelimitedText(DelimitedTextOptions {
+                        field_delimiter: "|".to_string(),
+                        max_line_size: 1024,
+                        quote: "\"".to_string(),
+                        header_lines_to_skip: self.choose(2) as u64,
+                        escape: "\\".to_string(),
+                        value_treated_as_null: Some(String::new()),
+                    }),
+                },
+                partition_index: self.choose(4) as u64,
+                start: self.choose(100) as u64,
+                length: self.choose(100) as u64,
+            })
+            .collect();
+        ReadType::LocalFiles { items }
+    }
+
+    // Either outputs all columns or a random selection of them
+    fn emit(&mut self, types: Vec<Type>) -> (Emit, Vec<Type>) {
+        if self.choose(2) == 0 {
+            return (Emit::Direct, types);
+        }
+        let mapping: Vec<i32> = (0..self.choose(types.len() as u32) + 1)
+            .map(|_| self.choose(types.len() as u32) as i32)
+            .collect();
+        let types = mapping.iter().map(|i| types[*i as usize].clone()).collect();
+        (Emit::Remap(mapping), types)
+    }
+
+    // Generates an expression over the input columns that evaluates to the given type
+    fn expression(&mut self, input: &[Type], t: &Type, depth: u32) -> Expression {
+        // nullable columns may only be referenced where a nullable value is expected
+        let matching: Vec<usize> = (0..input.len())
+            .filter(|i| input[*i] == *t || input[*i].clone().with_nullable(true) == *t)
+            .collect();
+        match self.choose(4) {
+            0 if !matching.is_empty() => Expression::FieldReference(FieldReference {
+                field: matching[self.choose(matching.len() as u32) as usize] as i32,
+            }),
+            1 if depth > 0 => {
+                let (name, extension) = match t {
+                    Type::Bool { .. } => ("and:bool", "/functions_boolean.yaml"),
+                    Type::I32 { .. } => ("add:i32_i32", "/functions_arithmetic.yaml"),
+                    _ => ("add:i64_i64", "/functions_arithmetic.yaml"),
+                };
+                let args = (0..2)
+                    .map(|_| FunctionArgument::Value(self.expression(input, t, depth - 1)))
+                    .collect();
+                Expression::ScalarFunction(ScalarFunctionInvocation {
+                    function: Function {
+                        signature: FunctionSignature(name.to_string()),
+                        extension: URI(extension.to_string()),
+                        variant: None,
+                    },
+                    args,
+                    output_type: t.clone(),
+                })
+            }
+            2 if depth > 0 => {
+                // a cast from any column type keeps the nullability of its input
+                let from = self.r#type().with_nullable(t.nullable());
+                Expression::Cast(Cast {
+                    r#type: t.clone(),
+                    input: Box::new(self.expression(input, &from, depth - 1)),
+                    failure_behavior: match self.choose(3) {
+                        0 => FailureBehavior::Unspecified,
+                        1 => FailureBehavior::ReturnNull,
+                        _ => FailureBehavior::ThrowException,
+                    },
+                })
+            }
+            _ => self.literal(t),
+        }
+    }
+
+    fn literal(&mut self, t: &Type) -> Expression {
+        let nullable = t.nullable();
+        let value = self.choose(1000);
+        if nullable && value.is_multiple_of(4) {
+            return Expression::Literal(Literal::Null(t.clone()));
+        }
+        Expression::Literal(match t {
+            Type::Bool { .. } => Literal::Bool(Bool {
+                value: value.is_multiple_of(2),
+                nullable,
+            }),
+            Type::I32 { .. } => Literal::I32(I32 {
+                value: value as i32 - 500,
+                nullable,
+            }),
+            _ => Literal::I64(I64 {
+                value: value as i64 * 1_000_000_007,
+                nullable,
+            }),
+        })
+    }
+}
+
+// The types of the columns output by a join of the given type
+fn join_output(join_type: JoinType, left: Vec<Type>, right: Vec<Type>) -> Vec<Type> {
+    let nullable = |types: Vec<Type>| -> Vec<Type> {
+        types.into_iter().map(|t| t.with_nullable(true)).collect()
+    };
+    let concat = |left: Vec<Type>, right: Vec<Type>| -> Vec<Type> {
+        left.into_iter().chain(right).collect()
+    };
+    let mark = Type::Bool { nullable: true };
+    match join_type {
+        JoinType::Inner => concat(left, right),
+        JoinType::Outer => concat(nullable(left), nullable(right)),
+        JoinType::Left | JoinType::LeftSingle => concat(left, nullable(right)),
+        JoinType::Right | JoinType::RightSingle => concat(nullable(left), right),
+        JoinType::LeftSemi | JoinType::LeftAnti => left,
+        JoinType::RightSemi | JoinType::RightAnti => right,
+        JoinType::LeftMark => concat(left, vec![mark]),
+        JoinType::RightMark => concat(right, vec![mark]),
+    }
+}
+
+proptest! {
+    #[test]
+    fn generated_plans_round_trip(choices in prop::collection::vec(any::<u32>(), 0..128)) {
+        let plan = PlanGenerator { choices, position: 0, subtrees: vec![] }.plan();
+        prop_assert!(check_plan(&plan).is_ok());
+        assert_round_trips(&plan);
+    }
+}
@@ -0,0 +1,995 @@
+use crate::functions::{FunctionMapping, FunctionMappings, Target};
+use crate::local_files::LocalFilesTable;
+use crate::types::{to_arrow_schema, to_arrow_type, to_scalar_value};
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::common::{not_impl_err, plan_err, Column, DFSchema, TableReference};
+use datafusion::dataframe::DataFrame;
+use datafusion::datasource::{provider_as_source, MemTable, TableProvider};
+use datafusion::error::Result;
+use datafusion::execution::context::{SessionContext, SessionState};
+use datafusion::execution::FunctionRegistry;
+use datafusion::logical_expr::aggregate_function::AggregateFunction as BuiltInAggregate;
+use datafusion::logical_expr::expr::{AggregateFunction, Cast, ScalarFunction, TryCast};
+use datafusion::logical_expr::utils::COUNT_STAR_EXPANSION;
+use datafusion::logical_expr::{
+    binary_expr, Expr, GroupingSet, JoinType, LogicalPlan, LogicalPlanBuilder, Partitioning,
+};
+use datafusion::scalar::ScalarValue;
+use rustrait_core::plans;
+use rustrait_core::plans::expressions::aggregate_function::{
+    AggregationInvocation, AggregationPhase,
+};
+use rustrait_core::plans::expressions::cast::FailureBehavior;
+use rustrait_core::plans::expressions::field_reference::FieldReference;
+use rustrait_core::plans::expressions::literal::Literal;
+use rustrait_core::plans::expressions::scalar_function::ScalarFunctionInvocation;
+use rustrait_core::plans::expressions::sort_field::{SortDirection, SortField};
+use rustrait_core::plans::expressions::{Expression, FunctionArgument};
+use rustrait_core::plans::{
+    Aggregate, Emit, Exchange, ExchangeKind, Join, KeyedJoin, Measure, Plan, Project, Read,
+    ReadType, Rel, Set, SetOp,
+};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Arc;
+
+// Converts the plan into a DataFusion logical plan. Tables are looked up in the catalogs of the
+// context, and functions through the standard function mappings.
+pub async fn to_logical_plan(ctx: &SessionContext, plan: &Plan) -> Result<LogicalPlan> {
+    to_logical_plan_with_mappings(ctx, plan, &FunctionMappings::standard()).await
+}
+
+pub async fn to_logical_plan_with_mappings(
+    ctx: &SessionContext,
+    plan: &Plan,
+    mappings: &FunctionMappings,
+) -> Result<LogicalPlan> {
+    let tables = resolve_tables(ctx, plan).await?;
+    let state = ctx.state();
+    LogicalPlanConsumer::new(&state, mappings, tables, plan).convert_plan(plan)
+}
+
+pub async fn to_dataframe(ctx: &SessionContext, plan: &Plan) -> Result<DataFrame> {
+    let logical_plan = to_logical_plan(ctx, plan).await?;
+    ctx.execute_logical_plan(logical_plan).await
+}
+
+// Table lookups are async, so every table of the plan is resolved before it is converted
+pub(crate) async fn resolve_tables(
+    ctx: &SessionContext,
+    plan: &Plan,
+) -> Result<HashMap<Vec<String>, Arc<dyn TableProvider>>> {
+    let mut table_names = vec![];
+    collect_tables(&plan.root, &mut table_names);
+    for subtree in &plan.subtrees {
+        collect_tables(subtree, &mut table_names);
+    }
+    let mut tables = HashMap::new();
+    for names in table_names {
+        if let Entry::Vacant(entry) = tables.entry(names) {
+            let provider = resolve_table(ctx, entry.key()).await?;
+            entry.insert(provider);
+        }
+    }
+    Ok(tables)
+}
+
+fn collect_tables(rel: &Rel, tables: &mut Vec<Vec<String>>) {
+    if let Rel::Read(read) = rel {
+        match &read.read_type {
+            ReadType::NamedTable { names } => tables.push(names.clone()),
+            ReadType::LocalFiles { .. } | ReadType::VirtualTable { .. } => {}
+        }
+    }
+    for input in rel.inputs() {
+        collect_tables(input, tables);
+    }
+}
+
+async fn resolve_table(ctx: &SessionContext, names: &[String]) -> Result<Arc<dyn TableProvider>> {
+    let lowercase: Vec<String> = names.iter().map(|name| name.to_lowercase()).collect();
+    match ctx.table_provider(table_reference(names)?).await {
+        // tables registered through SQL have lower case names unless they were quoted
+        Err(_) if lowercase != names => ctx.table_provider(table_reference(&lowercase)?).await,
+        result => result,
+    }
+}
+
+fn table_reference(names: &[String]) -> Result<TableReference> {
+    match names {
+        [table] => Ok(TableReference::bare(table.as_str())),
+        [schema, table] => Ok(TableReference::partial(schema.as_str(), table.as_str())),
+        [catalog, schema, table] => Ok(TableReference::full(
+            catalog.as_str(),
+            schema.as_str(),
+            table.as_str(),
+        )),
+        _ => plan_err!("invalid table name {}", names.join(".")),
+    }
+}
+
+pub(crate) struct LogicalPlanConsumer<'a> {
+    state: &'a SessionState,
+    mappings: &'a FunctionMappings,
+    tables: HashMap<Vec<String>, Arc<dyn TableProvider>>,
+    subtrees: &'a [Rel],
+    // subtrees are converted once, however often they are referenced
+    subtree_plans: HashMap<usize, LogicalPlan>,
+    // subtrees being converted, to detect references to themselves
+    converting: Vec<usize>,
+    // plans to use in place of converting relations, keyed by the address of the relation
+    substitutes: HashMap<usize, LogicalPlan>,
+    // qualifiers of the scans converted so far. DataFusion cannot combine inputs with columns
+    // of the same qualifier and name, as when a table is joined with itself.
+    qualifiers: HashSet<String>,
+}
+
+impl<'a> LogicalPlanConsumer<'a> {
+    pub(crate) fn new(
+        state: &'a SessionState,
+        mappings: &'a FunctionMappings,
+        tables: HashMap<Vec<String>, Arc<dyn TableProvider>>,
+        plan: &'a Plan,
+    ) -> LogicalPlanConsumer<'a> {
+        LogicalPlanConsumer {
+            state,
+            mappings,
+            tables,
+            subtrees: &plan.subtrees,
+            subtree_plans: HashMap::new(),
+            converting: vec![],
+            substitutes: HashMap::new(),
+            qualifiers: HashSet::new(),
+        }
+    }
+
+    // The relation is converted to the given plan wherever it occurs
+    pub(crate) fn substitute(&mut self, rel: &Rel, plan: LogicalPlan) {
+        self.substitutes.insert(rel as *const Rel as usize, plan);
+    }
+
+    fn convert_plan(&mut self, plan: &Plan) -> Result<LogicalPlan> {
+        let root = self.convert_rel(&plan.root)?;
+        let columns = root.schema().columns();
+        let Some(names) = root_names(plan, columns.len()) else {
+            return Ok(root);
+        };
+        let named = columns
+            .into_iter()
+            .zip(names)
+            .map(|(column, name)| Expr::Column(column).alias(name));
+        LogicalPlanBuilder::from(root).project(named)?.build()
+    }
+
+    pub(crate) fn convert_rel(&mut self, rel: &Rel) -> Result<LogicalPlan> {
+        if let Some(plan) = self.substitutes.get(&(rel as *const Rel as usize)) {
+            return Ok(plan.clone());
+        }
+        let plan = match rel {
+            Rel::Read(read) => self.convert_read(read)?,
+            Rel::Filter(filter) => {
+                let input = self.convert_rel(&filter.input)?;
+                let condition = self.convert_expression(&filter.condition, input.schema())?;
+                LogicalPlanBuilder::from(input).filter(condition)?.build()?
+            }
+            Rel::Fetch(fetch) => {
+                let input = self.convert_rel(&fetch.input)?;
+                let offset = match usize::try_from(fetch.offset) {
+                    Ok(offset) => offset,
+                    Err(_) => return plan_err!("negative fetch offset {}", fetch.offset),
+                };
+                // a negative count means all remaining rows
+                let count = usize::try_from(fetch.count).ok();
+                LogicalPlanBuilder::from(input)
+                    .limit(offset, count)?
+                    .build()?
+            }
+            Rel::Aggregate(aggregate) => self.convert_aggregate(aggregate)?,
+            Rel::Sort(sort) => {
+                let input = self.convert_rel(&sort.input)?;
+                let sorts = self.convert_sorts(&sort.sorts, input.schema())?;
+                LogicalPlanBuilder::from(input).sort(sorts)?.build()?
+            }
+            Rel::Join(join) => self.convert_join(join)?,
+            Rel::Project(project) => self.convert_project(project)?,
+            Rel::Set(set) => self.convert_set(set)?,
+            Rel::Cross(cross) => {
+                let left = self.convert_rel(&cross.left)?;
+                let right = self.convert_rel(&cross.right)?;
+                LogicalPlanBuilder::from(left).cross_join(right)?.build()?
+            }
+            Rel::Reference(reference) => self.convert_subtree(reference.subtree)?,
+            // physical relations are planned as their logical equivalents
+            Rel::HashJoin(join) | Rel::MergeJoin(join) => self.convert_keyed_join(join)?,
+            Rel::Exchange(exchange) => {
+                let input = self.convert_rel(&exchange.input)?;
+                let partitioning = match &exchange.kind {
+                    ExchangeKind::ScatterByFields(fields) => Partitioning::Hash(
+                        fields
+                            .iter()
+                            .map(|f| field(input.schema(), f.field))
+                            .collect::<Result<_>>()?,
+                        partition_count(exchange)?,
+                    ),
+                    ExchangeKind::RoundRobin { exact: false } => {
+                        Partitioning::RoundRobinBatch(partition_count(exchange)?)
+                    }
+                    kind => return not_impl_err!("exchange of kind {:?}", kind),
+                };
+                LogicalPlanBuilder::from(input)
+                    .repartition(partitioning)?
+                    .build()?
+            }
+        };
+        apply_emit(plan, rel.emit())
+    }
+
+    fn convert_subtree(&mut self, index: usize) -> Result<LogicalPlan> {
+        if let Some(plan) = self.subtree_plans.get(&index) {
+            return Ok(plan.clone());
+        }
+        let subtrees = self.subtrees;
+        let Some(subtree) = subtrees.get(index) else {
+            return plan_err!("reference to subtree {} which does not exist", index);
+        };
+        if self.converting.contains(&index) {
+            return plan_err!("subtree {} refers to itself", index);
+        }
+        self.converting.push(index);
+        let plan = self.convert_rel(subtree);
+        self.converting.pop();
+        let plan = plan?;
+        self.subtree_plans.insert(index, plan.clone());
+        Ok(plan)
+    }
+
+    fn convert_read(&mut self, read: &Read) -> Result<LogicalPlan> {
+        let (name, scan) = self.convert_scan(read)?;
+        // later scans of a name are qualified with an alias instead
+        let qualifier = unique_name(&name, &mut self.qualifiers);
+        if qualifier == name {
+            return Ok(scan);
+        }
+        LogicalPlanBuilder::from(scan)
+            .alias(TableReference::bare(qualifier))?
+            .build()
+    }
+
+    // The scan of the read with the name qualifying its columns
+    fn convert_scan(&self, read: &Read) -> Result<(String, LogicalPlan)> {
+        let scan = match &read.read_type {
+            ReadType::NamedTable { names } => {
+                let provider = &self.tables[names];
+                let table_schema = provider.schema();
+                // the base schema selects the columns of the table that the plan reads
+                let projection = read
+                    .base_schema
+                    .column_names()
+                    .iter()
+                    .map(|name| {
+                        let fields = table_schema.fields();
+                        match fields.iter().position(|f| f.name() == name).or_else(|| {
+                            fields
+                                .iter()
+                                .position(|f| f.name().eq_ignore_ascii_case(name))
+                        }) {
+                            Some(i) => Ok(i),
+                            None => plan_err!("table {} has no column {}", names.join("."), name),
+                        }
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let scan = LogicalPlanBuilder::scan(
+                    table_reference(names)?,
+                    provider_as_source(provider.clone()),
+                    Some(projection),
+                )?
+                .build()?;
+                (names.join("."), scan)
+            }
+            ReadType::VirtualTable { rows } => {
+                let schema = Arc::new(to_arrow_schema(&read.base_schema)?);
+                let batch = virtual_table_batch(rows, schema.clone())?;
+                let table = MemTable::try_new(schema, vec![vec![batch]])?;
+                let scan = LogicalPlanBuilder::scan(
+                    "virtual_table",
+                    provider_as_source(Arc::new(table)),
+                    None,
+                )?
+                .build()?;
+                ("virtual_table".to_string(), scan)
+            }
+            ReadType::LocalFiles { items } => {
+                let schema = Arc::new(to_arrow_schema(&read.base_schema)?);
+                let provider = LocalFilesTable::try_new(items, schema)?;
+                let scan = LogicalPlanBuilder::scan(
+                    "local_files",
+                    provider_as_source(Arc::new(provider)),
+                    None,
+                )?
+                .build()?;
+                ("local_files".to_string(), scan)
+            }
+        };
+        Ok(scan)
+    }
+
+    fn convert_aggregate(&mut self, aggregate: &Aggregate) -> Result<LogicalPlan> {
+        let input = self.convert_rel(&aggregate.input)?;
+        let schema = input.schema().clone();
+        let mut groupings = vec![];
+        for grouping in &aggregate.groupings {
+            let mut keys = vec![];
+            for expression in grouping {
+                let key = self.convert_expression(expression, &schema)?;
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+            groupings.push(keys);
+        }
+        let group_exprs = if groupings.len() > 1 {
+            vec![Expr::GroupingSet(GroupingSet::GroupingSets(groupings))]
+        } else {
+            groupings.into_iter().flatten().collect()
+        };
+
+        let measures = aggregate
+            .measures
+            .iter()
+            .map(|measure| self.convert_measure(measure, &schema))
+            .collect::<Result<Vec<_>>>()?;
+        LogicalPlanBuilder::from(input)
+            .aggregate(group_exprs, unique_names(measures)?)?
+            .build()
+    }
+
+    fn convert_measure(&self, measure: &Measure, schema: &DFSchema) -> Result<Expr> {
+        let function = &measure.function;
+        if function.phase != AggregationPhase::InitialToResult {
+            return not_impl_err!("aggregation phase {:?}", function.phase);
+        }
+        let mapping = self
+            .mappings
+            .get(&function.function.extension.0, function.function.name())?;
+        let Target::Aggregate(name) = &mapping.target else {
+            return plan_err!(
+                "{} is not mapped to an aggregate function",
+                function.function.name()
+            );
+        };
+        let mut args = self.convert_arguments(&function.args, mapping, schema)?;
+        // count without arguments counts rows
+        if name == "count" && args.is_empty() {
+            args.push(Expr::Literal(COUNT_STAR_EXPANSION));
+        }
+        let distinct = function.invocation == AggregationInvocation::Distinct;
+        let filter = match &measure.filter {
+            Some(filter) => Some(Box::new(self.convert_expression(filter, schema)?)),
+            None => None,
+        };
+        let order_by = match function.sorts.is_empty() {
+            true => None,
+            false => Some(self.convert_sorts(&function.sorts, schema)?),
+        };
+
+        if let Ok(udaf) = self.state.udaf(name) {
+            return Ok(Expr::AggregateFunction(AggregateFunction::new_udf(
+                udaf, args, distinct, filter, order_by, None,
+            )));
+        }
+        match BuiltInAggregate::from_str(name) {
+            Ok(fun) => Ok(Expr::AggregateFunction(AggregateFunction::new(
+                fun, args, distinct, filter, order_by, None,
+            ))),
+            Err(_) => plan_err!(
+                "{} is mapped to aggregate function {} which is not registered",
+                function.function.name(),
+                name
+            ),
+        }
+    }
+
+    fn convert_sorts(&self, sorts: &[SortField], schema: &DFSchema) -> Result<Vec<Expr>> {
+        sorts
+            .iter()
+            .map(|sort_field| {
+                let (asc, nulls_first) = match sort_field.direction {
+                    SortDirection::AscNullsFirst => (true, true),
+                    SortDirection::AscNullsLast => (true, false),
+                    SortDirection::DescNullsFirst => (false, true),
+                    SortDirection::DescNullsLast => (false, false),
+                    SortDirection::Clustered => return not_impl_err!("clustered sorts"),
+                };
+                let expression = self.convert_expression(&sort_field.expression, schema)?;
+                Ok(expression.sort(asc, nulls_first))
+            })
+            .collect()
+    }
+
+    fn convert_join(&mut self, join: &Join) -> Result<LogicalPlan> {
+        let left = self.convert_rel(&join.left)?;
+        let right = self.convert_rel(&join.right)?;
+        let join_type = join_type(&join.join_type)?;
+        // the join expression refers to the columns of both inputs
+        let schema = left.schema().join(right.schema())?;
+        let on = match &join.expression {
+            Some(expression) => Some(self.convert_expression(expression, &schema)?),
+            None => None,
+        };
+        let plan = LogicalPlanBuilder::from(left)
+            .join_on(right, join_type, on)?
+            .build()?;
+        match &join.post_join_filter {
+            Some(filter) => {
+                let filter = self.convert_expression(filter, plan.schema())?;
+                LogicalPlanBuilder::from(plan).filter(filter)?.build()
+            }
+            None => Ok(plan),
+        }
+    }
+
+    fn convert_keyed_join(&mut self, join: &KeyedJoin) -> Result<LogicalPlan> {
+        let left = self.convert_rel(&join.left)?;
+        let right = self.convert_rel(&join.right)?;
+        let keys = |schema: &DFSchema, keys: &[FieldReference]| {
+            keys.iter()
+                .map(|key| column(schema, key.field))
+                .collect::<Result<Vec<_>>>()
+        };
+        let left_keys = keys(left.schema(), &join.left_keys)?;
+        let right_keys = keys(right.schema(), &join.right_keys)?;
+        let plan = LogicalPlanBuilder::from(left)
+            .join(
+                right,
+                join_type(&join.join_type)?,
+                (left_keys, right_keys),
+                None,
+            )?
+            .build()?;
+        match &join.post_join_filter {
+            Some(filter) => {
+                let filter = self.convert_expression(filter, plan.schema())?;
+                LogicalPlanBuilder::from(plan).filter(filter)?.build()
+            }
+            None => Ok(plan),
+        }
+    }
+
+    fn convert_project(&mut self, project: &Project) -> Result<LogicalPlan> {
+        let input = self.convert_rel(&project.input)?;
+        let schema = input.schema().clone();
+        // a project outputs its input columns followed by the computed ones
+        let mut exprs: Vec<Expr> = schema.columns().into_iter().map(Expr::Column).collect();
+        for expression in &project.expressions {
+            exprs.push(self.convert_expression(expression, &schema)?);
+        }
+        LogicalPlanBuilder::from(input)
+            .project(unique_names(exprs)?)?
+            .build()
+    }
+
+    fn convert_set(&mut self, set: &Set) -> Result<LogicalPlan> {
+        let inputs = set
+            .inputs
+            .iter()
+            .map(|input| self.convert_rel(input))
+            .collect::<Result<Vec<_>>>()?;
+        if inputs.len() < 2 {
+            return plan_err!(
+                "set relation has {} inputs, expected at least 2",
+                inputs.len()
+            );
+        }
+        let mut inputs = inputs.into_iter();
+        let primary = inputs.next().expect("at least two inputs");
+        match set.op {
+            SetOp::UnionAll => inputs.try_fold(primary, |left, right| {
+                LogicalPlanBuilder::from(left).union(right)?.build()
+            }),
+            SetOp::UnionDistinct => inputs.try_fold(primary, |left, right| {
+                LogicalPlanBuilder::from(left)
+                    .union_distinct(right)?
+                    .build()
+            }),
+            SetOp::MinusPrimary => inputs.try_fold(primary, |left, right| {
+                LogicalPlanBuilder::except(left, right, false)
+            }),
+            SetOp::MinusMultiset => inputs.try_fold(primary, |left, right| {
+                LogicalPlanBuilder::except(left, right, true)
+            }),
+            // rows of the primary input that occur in any of the other inputs
+            SetOp::IntersectionPrimary => {
+                let first = inputs.next().expect("at least two inputs");
+                let secondary = inputs.try_fold(first, |left, right| {
+                    LogicalPlanBuilder::from(left).union(right)?.build()
+                })?;
+                LogicalPlanBuilder::intersect(primary, secondary, false)
+            }
+            SetOp::IntersectionMultiset => inputs.try_fold(primary, |left, right| {
+                LogicalPlanBuilder::intersect(left, right, true)
+            }),
+        }
+    }
+
+    fn convert_arguments(
+        &self,
+        args: &[FunctionArgument],
+        mapping: &FunctionMapping,
+        schema: &DFSchema,
+    ) -> Result<Vec<Expr>> {
+        let mut values = vec![];
+        let mut enums = vec![];
+        for arg in args {
+            match arg {
+                FunctionArgument::Value(expression) => {
+                    values.push(self.convert_expression(expression, schema)?)
+                }
+                FunctionArgument::Enum(e) => enums.push(e.as_str()),
+                FunctionArgument::Type(t) => return not_impl_err!("type argument {}", t),
+            }
+        }
+        mapping.map_arguments(values, &enums)
+    }
+
+    pub(crate) fn convert_expression(
+        &self,
+        expression: &Expression,
+        schema: &DFSchema,
+    ) -> Result<Expr> {
+        match expression {
+            Expression::Literal(literal) => Ok(Expr::Literal(to_scalar_value(literal)?)),
+            Expression::FieldReference(field_reference) => field(schema, field_reference.field),
+            Expression::ScalarFunction(invocation) => {
+                self.convert_scalar_function(invocation, schema)
+            }
+            Expression::Cast(cast) => {
+                let expr = Box::new(self.convert_expression(&cast.input, schema)?);
+                let data_type = to_arrow_type(&cast.r#type)?;
+                match cast.failure_behavior {
+                    FailureBehavior::ReturnNull => Ok(Expr::TryCast(TryCast::new(expr, data_type))),
+                    // DataFusion casts fail on values they cannot convert
+                    FailureBehavior::Unspecified | FailureBehavior::ThrowException => {
+                        Ok(Expr::Cast(Cast::new(expr, data_type)))
+                    }
+                }
+            }
+        }
+    }
+
+    fn convert_scalar_function(
+        &self,
+        invocation: &ScalarFunctionInvocation,
+        schema: &DFSchema,
+    ) -> Result<Expr> {
+        let name = invocation.function.name();
+        let mapping = self.mappings.get(&invocation.function.extension.0, name)?;
+        let args = self.convert_arguments(&invocation.args, mapping, schema)?;
+        match &mapping.target {
+            Target::Operator(operator) => {
+                if args.len() < 2 {
+                    return plan_err!("{} expects at least 2 arguments, got {}", name, args.len());
+                }
+                // operators such as and/or are variadic in Substrait
+                let expr = args
+                    .into_iter()
+                    .reduce(|left, right| binary_expr(left, *operator, right));
+                Ok(expr.expect("at least two arguments"))
+            }
+            Target::Builder(builder) => builder(args),
+            Target::ScalarUdf(udf_name) => match self.state.udf(udf_name) {
+                Ok(udf) => Ok(Expr::ScalarFunction(ScalarFunction::new_udf(udf, args))),
+                Err(_) => plan_err!(
+                    "{} is mapped to scalar function {} which is not registered",
+                    name,
+                    udf_name
+                ),
+            },
+            Target::Aggregate(_) => plan_err!("aggregate function {} used as a scalar", name),
+        }
+    }
+}
+
+// Builds a batch from the rows of literals, checking their types against the schema
+fn virtual_table_batch(rows: &[Vec<Literal>], schema: SchemaRef) -> Result<RecordBatch> {
+    let fields = schema.fields();
+    let mut columns = vec![vec![]; fields.len()];
+    for (i, row) in rows.iter().enumerate() {
+        if row.len() != fields.len() {
+            return plan_err!(
+                "row {} of virtual table has {} values, but the base schema has {} columns",
+                i,
+                row.len(),
+                fields.len()
+            );
+        }
+        for (j, literal) in row.iter().enumerate() {
+            let value = to_scalar_value(literal)?;
+            if value.data_type() != *fields[j].data_type() {
+                return plan_err!(
+                    "value {} in column {} of virtual table row {} does not have the column \
+                     type {}",
+                    literal,
+                    fields[j].name(),
+                    i,
+                    fields[j].data_type()
+                );
+            }
+            columns[j].push(value);
+        }
+    }
+    if rows.is_empty() {
+        return Ok(RecordBatch::new_empty(schema));
+    }
+    let arrays = columns
+        .into_iter()
+        .map(ScalarValue::iter_to_array)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(RecordBatch::try_new(schema, arrays)?)
+}
+
+pub(crate) fn join_type(join_type: &plans::JoinType) -> Result<JoinType> {
+    let join_type = match join_type {
+        plans::JoinType::Inner => JoinType::Inner,
+        plans::JoinType::Outer => JoinType::Full,
+        plans::JoinType::Left => JoinType::Left,
+        plans::JoinType::Right => JoinType::Right,
+        plans::JoinType::LeftSemi => JoinType::LeftSemi,
+        plans::JoinType::LeftAnti => JoinType::LeftAnti,
+        plans::JoinType::RightSemi => JoinType::RightSemi,
+        plans::JoinType::RightAnti => JoinType::RightAnti,
+        join_type => return not_impl_err!("join type {:?}", join_type),
+    };
+    Ok(join_type)
+}
+
+pub(crate) fn partition_count(exchange: &Exchange) -> Result<usize> {
+    match usize::try_from(exchange.partition_count) {
+        Ok(count) if count > 0 => Ok(count),
+        _ => plan_err!(
+            "exchange into {} partitions, expected a positive count",
+            exchange.partition_count
+        ),
+    }
+}
+
+fn field(schema: &DFSchema, index: i32) -> Result<Expr> {
+    Ok(Expr::Column(column(schema, index)?))
+}
+
+fn column(schema: &DFSchema, index: i32) -> Result<Column> {
+    let i = field_index(index, schema.fields().len())?;
+    Ok(Column::from(schema.qualified_field(i)))
+}
+
+// The position of the referenced field among the columns of the input
+pub(crate) fn field_index(index: i32, count: usize) -> Result<usize> {
+    match usize::try_from(index).ok().filter(|i| *i < count) {
+        Some(i) => Ok(i),
+        None => plan_err!(
+            "field reference ${} is out of range for {} columns",
+            index,
+            count
+        ),
+    }
+}
+
+// The names given to the columns of the root, unless the names also cover the fields of nested
+// structs, in which case the columns keep their names
+pub(crate) fn root_names(plan: &Plan, column_count: usize) -> Option<&[String]> {
+    (plan.names.len() == column_count).then_some(plan.names.as_slice())
+}
+
+fn apply_emit(plan: LogicalPlan, emit: &Emit) -> Result<LogicalPlan> {
+    match emit {
+        Emit::Direct => Ok(plan),
+        Emit::Remap(mapping) => {
+            let exprs = mapping
+                .iter()
+                .map(|i| field(plan.schema(), *i))
+                .collect::<Result<Vec<_>>>()?;
+            LogicalPlanBuilder::from(plan)
+                .project(unique_names(exprs)?)?
+                .build()
+        }
+    }
+}
+
+// DataFusion requires the columns of a relation to have distinct names while Substrait does
+// not, so repeated names are made unique with a suffix
+fn unique_names(exprs: Vec<Expr>) -> Result<Vec<Expr>> {
+    let mut taken = HashSet::new();
+    exprs
+        .into_iter()
+        .map(|expr| {
+            let name = expr.display_name()?;
+            match unique_name(&name, &mut taken) {
+                unique if unique == name => Ok(expr),
+                unique => Ok(expr.alias(unique)),
+            }
+        })
+        .collect()
+}
+
+// The name, or the name with the first free suffix if it is taken. The result is taken too.
+pub(crate) fn unique_name(name: &str, taken: &mut HashSet<String>) -> String {
+    let unique = match taken.contains(name) {
+        false => name.to_string(),
+        true => (1..)
+            .map(|i| format!("{}#{}", name, i))
+            .find(|candidate| !taken.contains(candidate))
+            .expect("some suffix is free"),
+    };
+    taken.insert(unique.clone());
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::Int32Array;
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::arrow::record_batch::RecordBatch;
+    use datafusion::assert_batches_eq;
+    use datafusion::assert_batches_sorted_eq;
+    use rustrait_core::decoder::decode_prost_plan;
+    use rustrait_core::format::load_plan;
+    use rustrait_core::plans::expressions::aggregate_function::AggregateFunctionInvocation;
+    use rustrait_core::plans::expressions::literal::I32;
+    use rustrait_core::plans::expressions::{Function, FunctionSignature, URI};
+    use rustrait_core::plans::{Fetch, Filter, JoinType, Sort};
+    use rustrait_core::types::{NamedStruct, Type};
+
+    // t(a, b) = (1, 10), (2, 20), (2, 30), (3, 40)
+    fn context() -> Result<SessionContext> {
+        let ctx = SessionContext::new();
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Int32, false),
+            Field::new("b", DataType::Int32, false),
+        ]);
+        let a = Int32Array::from(vec![1, 2, 2, 3]);
+        let b = Int32Array::from(vec![10, 20, 30, 40]);
+        ctx.register_batch(
+            "t",
+            RecordBatch::try_new(Arc::new(schema), vec![Arc::new(a), Arc::new(b)])?,
+        )?;
+        Ok(ctx)
+    }
+
+    fn columns(names: &[&str]) -> NamedStruct {
+        NamedStruct {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            types: vec![Type::I32 { nullable: false }; names.len()],
+        }
+    }
+
+    fn read() -> Rel {
+        Rel::Read(Read {
+            emit: Emit::Direct,
+            base_schema: columns(&["a", "b"]),
+            read_type: ReadType::NamedTable {
+                names: vec!["t".to_string()],
+            },
+        })
+    }
+
+    fn values(values: &[i32]) -> Rel {
+        Rel::Read(Read {
+            emit: Emit::Direct,
+            base_schema: columns(&["x"]),
+            read_type: ReadType::VirtualTable {
+                rows: values.iter().map(|value| vec![literal(*value)]).collect(),
+            },
+        })
+    }
+
+    fn literal(value: i32) -> Literal {
+        Literal::I32(I32 {
+            value,
+            nullable: false,
+        })
+    }
+
+    fn field(field: i32) -> Expression {
+        Expression::FieldReference(FieldReference { field })
+    }
+
+    fn function(signature: &str, extension: &str) -> Function {
+        Function {
+            signature: FunctionSignature(signature.to_string()),
+            extension: URI(extension.to_string()),
+            variant: None,
+        }
+    }
+
+    fn comparison(name: &str, left: Expression, right: Expression) -> Expression {
+        Expression::ScalarFunction(ScalarFunctionInvocation {
+            function: function(&format!("{}:any_any", name), "/functions_comparison.yaml"),
+            args: vec![
+                FunctionArgument::Value(left),
+                FunctionArgument::Value(right),
+            ],
+            output_type: Type::Bool { nullable: false },
+        })
+    }
+
+    async fn collect(root: Rel, names: &[&str]) -> Result<Vec<RecordBatch>> {
+        let plan = Plan {
+            root: Box::new(root),
+            names: names.iter().map(|name| name.to_string()).collect(),
+            subtrees: vec![],
+        };
+        to_dataframe(&context()?, &plan).await?.collect().await
+    }
+
+    #[tokio::test]
+    async fn executes_simple_select() -> Result<()> {
+        let ctx = SessionContext::new();
+        let schema = Schema::new(vec![Field::new("BAR", DataType::Int32, true)]);
+        let bar = Int32Array::from(vec![Some(1), None, Some(41)]);
+        ctx.register_batch(
+            "FOO",
+            RecordBatch::try_new(Arc::new(schema), vec![Arc::new(bar)])?,
+        )?;
+
+        let proto_plan = load_plan(include_bytes!(
+            "../../rustrait-core/simple-select.substrait"
+        ))
+        .unwrap();
+        let plan = decode_prost_plan(&proto_plan).unwrap();
+        let batches = to_dataframe(&ctx, &plan).await?.collect().await?;
+        assert_batches_sorted_eq!(
+            [
+                "+--------+",
+                "| EXPR$0 |",
+                "+--------+",
+                "|        |",
+                "| 2      |",
+                "| 42     |",
+                "+--------+",
+            ],
+            &batches
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn filters_rows() -> Result<()> {
+        let rel = Rel::Filter(Filter {
+            emit: Emit::Direct,
+            input: Box::new(read()),
+            condition: comparison("gt", field(0), Expression::Literal(literal(1))),
+        });
+        assert_batches_sorted_eq!(
+            [
+                "+---+----+",
+                "| a | b  |",
+                "+---+----+",
+                "| 2 | 20 |",
+                "| 2 | 30 |",
+                "| 3 | 40 |",
+                "+---+----+",
+            ],
+            &collect(rel, &["a", "b"]).await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn aggregates_grouping_sets() -> Result<()> {
+        let rel = Rel::Aggregate(Aggregate {
+            emit: Emit::Direct,
+            input: Box::new(read()),
+            groupings: vec![vec![field(0)], vec![]],
+            measures: vec![Measure {
+                function: AggregateFunctionInvocation {
+                    function: function("sum:i32", "/functions_arithmetic.yaml"),
+                    args: vec![FunctionArgument::Value(field(1))],
+                    output_type: Type::I64 { nullable: true },
+                    phase: AggregationPhase::InitialToResult,
+                    invocation: AggregationInvocation::All,
+                    sorts: vec![],
+                },
+                filter: None,
+            }],
+        });
+        // the grouping without keys totals all rows
+        assert_batches_sorted_eq!(
+            [
+                "+---+-------+",
+                "| a | total |",
+                "+---+-------+",
+                "|   | 100   |",
+                "| 1 | 10    |",
+                "| 2 | 50    |",
+                "| 3 | 40    |",
+                "+---+-------+",
+            ],
+            &collect(rel, &["a", "total"]).await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sorts_and_fetches_rows() -> Result<()> {
+        let rel = Rel::Fetch(Fetch {
+            emit: Emit::Direct,
+            input: Box::new(Rel::Sort(Sort {
+                emit: Emit::Direct,
+                input: Box::new(read()),
+                sorts: vec![SortField {
+                    expression: field(1),
+                    direction: SortDirection::DescNullsLast,
+                }],
+            })),
+            offset: 1,
+            count: 2,
+        });
+        assert_batches_eq!(
+            [
+                "+---+----+",
+                "| a | b  |",
+                "+---+----+",
+                "| 2 | 30 |",
+                "| 2 | 20 |",
+                "+---+----+",
+            ],
+            &collect(rel, &["a", "b"]).await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn joins_a_table_with_itself() -> Result<()> {
+        let rel = Rel::Join(Join {
+            emit: Emit::Remap(vec![1, 3]),
+            left: Box::new(read()),
+            right: Box::new(read()),
+            expression: Some(comparison("equal", field(0), field(2))),
+            post_join_filter: Some(comparison("lt", field(1), field(3))),
+            join_type: JoinType::Inner,
+        });
+        assert_batches_sorted_eq!(
+            [
+                "+----+----+",
+                "| b  | c  |",
+                "+----+----+",
+                "| 20 | 30 |",
+                "+----+----+",
+            ],
+            &collect(rel, &["b", "c"]).await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn converts_set_ops() -> Result<()> {
+        let expected = [
+            (SetOp::UnionAll, vec![1, 1, 2, 2, 2, 3, 3, 4]),
+            (SetOp::UnionDistinct, vec![1, 2, 3, 4]),
+            (SetOp::MinusPrimary, vec![1]),
+            (SetOp::MinusMultiset, vec![1, 1]),
+            (SetOp::IntersectionPrimary, vec![2, 3]),
+            (SetOp::IntersectionMultiset, vec![2, 3]),
+        ];
+        for (op, rows) in expected {
+            let rel = Rel::Set(Set {
+                emit: Emit::Direct,
+                inputs: vec![values(&[1, 1, 2, 3]), values(&[2, 2, 3, 4])],
+                op,
+            });
+            let batches = collect(rel, &["x"]).await?;
+            let mut actual: Vec<i32> = batches
+                .iter()
+                .flat_map(|batch| {
+                    let column = batch.column(0).as_any().downcast_ref::<Int32Array>();
+                    column.expect("i32 column").values().to_vec()
+                })
+                .collect();
+            actual.sort();
+            assert_eq!(actual, rows, "{:?}", op);
+        }
+        Ok(())
+    }
+}
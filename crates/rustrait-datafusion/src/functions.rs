@@ -0,0 +1,368 @@
+use datafusion::common::{not_impl_err, plan_err};
+use datafusion::error::Result;
+use datafusion::logical_expr::{lit, Expr, Operator};
+use datafusion::scalar::ScalarValue;
+use rustrait_core::extensions::registry::standard_uris;
+use std::collections::HashMap;
+
+// What a Substrait function becomes in DataFusion
+#[derive(Clone, Debug)]
+pub enum Target {
+    // a binary operator, applied left to right when there are more than two arguments
+    Operator(Operator),
+    // builds the expression from the mapped arguments, e.g. for unary operators
+    Builder(fn(Vec<Expr>) -> Result<Expr>),
+    // a scalar function registered with the session
+    ScalarUdf(String),
+    // an aggregate function registered with the session or built into DataFusion
+    Aggregate(String),
+}
+
+// Where an argument of the DataFusion call comes from. Value and enum arguments of the
+// Substrait call are numbered separately, so that optional enum arguments do not shift the
+// positions of the values.
+#[derive(Clone, Debug)]
+pub enum ArgumentMapping {
+    Value(usize),
+    // an enum argument passed as a string literal, translated through the table or else
+    // lower-cased
+    Enum {
+        index: usize,
+        values: Vec<(String, String)>,
+    },
+    Literal(ScalarValue),
+}
+
+#[derive(Clone, Debug)]
+pub struct FunctionMapping {
+    pub target: Target,
+    // None passes the value arguments through in order
+    pub arguments: Option<Vec<ArgumentMapping>>,
+}
+
+impl FunctionMapping {
+    pub fn operator(operator: Operator) -> FunctionMapping {
+        FunctionMapping {
+            target: Target::Operator(operator),
+            arguments: None,
+        }
+    }
+
+    pub fn builder(builder: fn(Vec<Expr>) -> Result<Expr>) -> FunctionMapping {
+        FunctionMapping {
+            target: Target::Builder(builder),
+            arguments: None,
+        }
+    }
+
+    pub fn scalar_udf(name: &str) -> FunctionMapping {
+        FunctionMapping {
+            target: Target::ScalarUdf(name.to_string()),
+            arguments: None,
+        }
+    }
+
+    pub fn aggregate(name: &str) -> FunctionMapping {
+        FunctionMapping {
+            target: Target::Aggregate(name.to_string()),
+            arguments: None,
+        }
+    }
+
+    pub fn with_arguments(self, arguments: Vec<ArgumentMapping>) -> FunctionMapping {
+        FunctionMapping {
+            arguments: Some(arguments),
+            ..self
+        }
+    }
+
+    // Forms the arguments of the DataFusion call from those of the Substrait call
+    pub fn map_arguments(&self, values: Vec<Expr>, enums: &[&str]) -> Result<Vec<Expr>> {
+        let Some(arguments) = &self.arguments else {
+            if let Some(e) = enums.first() {
+                return not_impl_err!("enum argument {} has no mapping", e);
+            }
+            return Ok(values);
+        };
+        arguments
+            .iter()
+            .map(|argument| match argument {
+                ArgumentMapping::Value(index) => match values.get(*index) {
+                    Some(value) => Ok(value.clone()),
+                    None => plan_err!("missing value argument {}", index),
+                },
+                ArgumentMapping::Enum { index, values } => match enums.get(*index) {
+                    Some(e) => {
+                        let translated = values
+                            .iter()
+                            .find(|(from, _)| from == e)
+                            .map(|(_, to)| to.clone())
+                            .unwrap_or_else(|| e.to_lowercase());
+                        Ok(lit(translated))
+                    }
+                    None => plan_err!("missing enum argument {}", index),
+                },
+                ArgumentMapping::Literal(value) => Ok(Expr::Literal(value.clone())),
+            })
+            .collect()
+    }
+}
+
+// Maps Substrait functions, identified by extension URI and function name, to DataFusion
+#[derive(Clone, Debug, Default)]
+pub struct FunctionMappings {
+    mappings: HashMap<(String, String), FunctionMapping>,
+}
+
+impl FunctionMappings {
+    pub fn new() -> FunctionMappings {
+        FunctionMappings::default()
+    }
+
+    // Mappings for the functions of the standard extension files, under each of the URIs the
+    // files are commonly referred to by
+    pub fn standard() -> FunctionMappings {
+        let mut mappings = FunctionMappings::new();
+        let mut add = |file: &str, name: &str, mapping: FunctionMapping| {
+            for uri in standard_uris(file) {
+                mappings.insert(&uri, name, mapping.clone());
+            }
+        };
+
+        for file in [
+            "functions_arithmetic.yaml",
+            "functions_arithmetic_decimal.yaml",
+        ] {
+            add(file, "add", FunctionMapping::operator(Operator::Plus));
+            add(file, "subtract", FunctionMapping::operator(Operator::Minus));
+            add(
+                file,
+                "multiply",
+                FunctionMapping::operator(Operator::Multiply),
+            );
+            add(file, "divide", FunctionMapping::operator(Operator::Divide));
+            add(file, "modulus", FunctionMapping::operator(Operator::Modulo));
+            for name in ["sum", "avg", "min", "max"] {
+                add(file, name, FunctionMapping::aggregate(name));
+            }
+        }
+        let arithmetic = "functions_arithmetic.yaml";
+        add(arithmetic, "negate", FunctionMapping::builder(negate));
+        add(arithmetic, "power", FunctionMapping::scalar_udf("power"));
+        add(arithmetic, "sign", FunctionMapping::scalar_udf("signum"));
+        for name in ["abs", "sqrt", "exp"] {
+            add(arithmetic, name, FunctionMapping::scalar_udf(name));
+        }
+
+        let comparison = "functions_comparison.yaml";
+        for (name, operator) in [
+            ("equal", Operator::Eq),
+            ("not_equal", Operator::NotEq),
+            ("lt", Operator::Lt),
+            ("lte", Operator::LtEq),
+            ("gt", Operator::Gt),
+            ("gte", Operator::GtEq),
+            ("is_distinct_from", Operator::IsDistinctFrom),
+            ("is_not_distinct_from", Operator::IsNotDistinctFrom),
+        ] {
+            add(comparison, name, FunctionMapping::operator(operator));
+        }
+        add(comparison, "is_null", FunctionMapping::builder(is_null));
+        add(
+            comparison,
+            "is_not_null",
+            FunctionMapping::builder(is_not_null),
+        );
+        add(comparison, "is_nan", FunctionMapping::scalar_udf("isnan"));
+        add(
+            comparison,
+            "coalesce",
+            FunctionMapping::scalar_udf("coalesce"),
+        );
+
+        let boolean = "functions_boolean.yaml";
+        add(boolean, "and", FunctionMapping::operator(Operator::And));
+        add(boolean, "or", FunctionMapping::operator(Operator::Or));
+        add(boolean, "not", FunctionMapping::builder(not));
+        add(boolean, "bool_and", FunctionMapping::aggregate("bool_and"));
+        add(boolean, "bool_or", FunctionMapping::aggregate("bool_or"));
+
+        let string = "functions_string.yaml";
+        add(string, "like", FunctionMapping::builder(like));
+        add(string, "substring", FunctionMapping::scalar_udf("substr"));
+        add(
+            string,
+            "char_length",
+            FunctionMapping::scalar_udf("character_length"),
+        );
+        add(string, "trim", FunctionMapping::scalar_udf("btrim"));
+        for name in [
+            "concat",
+            "lower",
+            "upper",
+            "ltrim",
+            "rtrim",
+            "strpos",
+            "replace",
+            "starts_with",
+            "ends_with",
+        ] {
+            add(string, name, FunctionMapping::scalar_udf(name));
+        }
+
+        // the component is an enum in Substrait and a string in DataFusion
+        add(
+            "functions_datetime.yaml",
+            "extract",
+            FunctionMapping::scalar_udf("date_part").with_arguments(vec![
+                ArgumentMapping::Enum {
+                    index: 0,
+                    values: vec![
+                        ("DAY_OF_YEAR".to_string(), "doy".to_string()),
+                        ("DAY_OF_WEEK".to_string(), "dow".to_string()),
+                    ],
+                },
+                ArgumentMapping::Value(0),
+            ]),
+        );
+
+        let logarithmic = "functions_logarithmic.yaml";
+        for name in ["ln", "log10", "log2"] {
+            add(logarithmic, name, FunctionMapping::scalar_udf(name));
+        }
+        // logb takes the base second, log takes it first
+        add(
+            logarithmic,
+            "logb",
+            FunctionMapping::scalar_udf("log")
+                .with_arguments(vec![ArgumentMapping::Value(1), ArgumentMapping::Value(0)]),
+        );
+
+        let rounding = "functions_rounding.yaml";
+        for name in ["ceil", "floor", "round"] {
+            add(rounding, name, FunctionMapping::scalar_udf(name));
+        }
+
+        add(
+            "functions_aggregate_generic.yaml",
+            "count",
+            FunctionMapping::aggregate("count"),
+        );
+        add(
+            "functions_aggregate_approx.yaml",
+            "approx_count_distinct",
+            FunctionMapping::aggregate("approx_distinct"),
+        );
+        mappings
+    }
+
+    // Adds or replaces the mapping of a function
+    pub fn insert(&mut self, uri: &str, name: &str, mapping: FunctionMapping) {
+        self.mappings
+            .insert((uri.to_string(), name.to_string()), mapping);
+    }
+
+    pub fn get(&self, uri: &str, name: &str) -> Result<&FunctionMapping> {
+        match self.mappings.get(&(uri.to_string(), name.to_string())) {
+            Some(mapping) => Ok(mapping),
+            None => not_impl_err!(
+                "function {} of extension {} has no DataFusion mapping, add one to the \
+                 FunctionMappings",
+                name,
+                uri
+            ),
+        }
+    }
+
+    // The name of the Substrait function that maps onto the target with its arguments in
+    // order, used when producing plans
+    pub fn substrait_name(&self, target: &Target) -> Option<&str> {
+        let mut names: Vec<&str> = self
+            .mappings
+            .iter()
+            .filter(|(_, mapping)| mapping.arguments.is_none())
+            .filter(|(_, mapping)| match (&mapping.target, target) {
+                (Target::Operator(a), Target::Operator(b)) => a == b,
+                (Target::ScalarUdf(a), Target::ScalarUdf(b)) => a == b,
+                (Target::Aggregate(a), Target::Aggregate(b)) => a == b,
+                _ => false,
+            })
+            .map(|((_, name), _)| name.as_str())
+            .collect();
+        // map iteration order is not stable
+        names.sort();
+        names.first().copied()
+    }
+}
+
+fn single(name: &str, mut args: Vec<Expr>) -> Result<Expr> {
+    if args.len() != 1 {
+        return plan_err!("{} expects 1 argument, got {}", name, args.len());
+    }
+    Ok(args.remove(0))
+}
+
+fn negate(args: Vec<Expr>) -> Result<Expr> {
+    Ok(Expr::Negative(Box::new(single("negate", args)?)))
+}
+
+fn not(args: Vec<Expr>) -> Result<Expr> {
+    Ok(Expr::Not(Box::new(single("not", args)?)))
+}
+
+fn is_null(args: Vec<Expr>) -> Result<Expr> {
+    Ok(single("is_null", args)?.is_null())
+}
+
+fn is_not_null(args: Vec<Expr>) -> Result<Expr> {
+    Ok(single("is_not_null", args)?.is_not_null())
+}
+
+fn like(args: Vec<Expr>) -> Result<Expr> {
+    match <[Expr; 2]>::try_from(args) {
+        Ok([input, pattern]) => Ok(input.like(pattern)),
+        Err(args) => plan_err!("like expects 2 arguments, got {}", args.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::logical_expr::col;
+
+    #[test]
+    fn maps_arguments() {
+        let mappings = FunctionMappings::standard();
+        let logb = mappings.get("/functions_logarithmic.yaml", "logb").unwrap();
+        assert_eq!(
+            logb.map_arguments(vec![col("x"), lit(2.0)], &[]).unwrap(),
+            vec![lit(2.0), col("x")]
+        );
+
+        let extract = mappings
+            .get(
+                "https://github.com/substrait-io/substrait/blob/main/extensions/functions_datetime.yaml",
+                "extract",
+            )
+            .unwrap();
+        assert_eq!(
+            extract
+                .map_arguments(vec![col("t")], &["DAY_OF_WEEK", "ONE"])
+                .unwrap(),
+            vec![lit("dow"), col("t")]
+        );
+        assert_eq!(
+            extract.map_arguments(vec![col("t")], &["YEAR"]).unwrap(),
+            vec![lit("year"), col("t")]
+        );
+
+        assert_eq!(
+            mappings.substrait_name(&Target::Operator(Operator::Plus)),
+            Some("add")
+        );
+        let error = mappings
+            .get("/functions_custom.yaml", "frobnicate")
+            .unwrap_err();
+        assert!(error.to_string().contains("frobnicate"));
+    }
+}
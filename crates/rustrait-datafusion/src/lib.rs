@@ -0,0 +1,7 @@
+pub mod consumer;
+pub mod functions;
+pub mod local_files;
+pub mod physical;
+pub mod producer;
+pub mod types;
+pub mod udf;
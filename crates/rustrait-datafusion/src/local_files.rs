@@ -0,0 +1,356 @@
+use async_trait::async_trait;
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::common::{not_impl_err, plan_err};
+use datafusion::datasource::file_format::arrow::ArrowFormat;
+use datafusion::datasource::file_format::csv::CsvFormat;
+use datafusion::datasource::file_format::parquet::ParquetFormat;
+use datafusion::datasource::file_format::FileFormat as DataFusionFileFormat;
+use datafusion::datasource::listing::{FileRange, ListingTableUrl, PartitionedFile};
+use datafusion::datasource::physical_plan::FileScanConfig;
+use datafusion::datasource::TableProvider;
+use datafusion::error::Result;
+use datafusion::execution::context::SessionState;
+use datafusion::logical_expr::{Expr, TableType};
+use datafusion::physical_plan::ExecutionPlan;
+use futures::TryStreamExt;
+use rustrait_core::plans::{DelimitedTextOptions, FileFormat, FileOrFiles, FilePath};
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+// The files of a LocalFiles read. Unlike a listing table the files are read with the byte
+// ranges of the read, and files with the same partition index are read by the same partition.
+pub struct LocalFilesTable {
+    items: Vec<FileOrFiles>,
+    schema: SchemaRef,
+    format: Arc<dyn DataFusionFileFormat>,
+}
+
+impl LocalFilesTable {
+    pub fn try_new(items: &[FileOrFiles], schema: SchemaRef) -> Result<LocalFilesTable> {
+        let Some(first) = items.first() else {
+            return plan_err!("local files read lists no files");
+        };
+        // a scan reads files of a single format
+        if let Some(other) = items.iter().find(|item| item.format != first.format) {
+            return not_impl_err!(
+                "local files read mixes file formats {:?} and {:?}",
+                first.format,
+                other.format
+            );
+        }
+        Ok(LocalFilesTable {
+            items: items.to_vec(),
+            schema,
+            format: file_format(&first.format)?,
+        })
+    }
+}
+
+fn file_format(format: &FileFormat) -> Result<Arc<dyn DataFusionFileFormat>> {
+    match format {
+        FileFormat::Parquet => Ok(Arc::new(ParquetFormat::default())),
+        FileFormat::Arrow => Ok(Arc::new(ArrowFormat)),
+        FileFormat::DelimitedText(options) => Ok(Arc::new(csv_format(options)?)),
+        FileFormat::Orc | FileFormat::Dwrf => {
+            not_impl_err!("DataFusion cannot read {:?} files", format)
+        }
+    }
+}
+
+// DataFusion reads lines of any length, so `max_line_size` is not enforced
+fn csv_format(options: &DelimitedTextOptions) -> Result<CsvFormat> {
+    if options.header_lines_to_skip > 1 {
+        return not_impl_err!(
+            "cannot skip {} header lines, at most one is supported",
+            options.header_lines_to_skip
+        );
+    }
+    // DataFusion always reads empty values as null
+    match &options.value_treated_as_null {
+        Some(null) if null.is_empty() => {}
+        Some(null) => {
+            return not_impl_err!("only empty values can be treated as null, not {:?}", null)
+        }
+        None => {
+            return not_impl_err!(
+                "empty values are read as null, so value_treated_as_null must be the empty string"
+            )
+        }
+    }
+    let format = CsvFormat::default()
+        .with_has_header(options.header_lines_to_skip == 1)
+        .with_delimiter(single_byte("field delimiter", &options.field_delimiter)?)
+        .with_quote(single_byte("quote", &options.quote)?);
+    match options.escape.as_str() {
+        "" => Ok(format),
+        escape => Ok(format.with_escape(Some(single_byte("escape", escape)?))),
+    }
+}
+
+fn single_byte(option: &str, value: &str) -> Result<u8> {
+    match value.as_bytes() {
+        [byte] => Ok(*byte),
+        _ => not_impl_err!("{} must be a single byte, got {:?}", option, value),
+    }
+}
+
+// DataFusion takes byte ranges as signed offsets, while plans give them as unsigned ones
+fn byte_range(item: &FileOrFiles) -> Result<FileRange> {
+    let end = item.start.checked_add(item.length);
+    match (i64::try_from(item.start), end.map(i64::try_from)) {
+        (Ok(start), Some(Ok(end))) => Ok(FileRange { start, end }),
+        _ => plan_err!(
+            "byte range of {} bytes from {} is out of range",
+            item.length,
+            item.start
+        ),
+    }
+}
+
+fn listing_url(path: &FilePath) -> Result<ListingTableUrl> {
+    match path {
+        FilePath::Uri(uri) | FilePath::File(uri) | FilePath::Glob(uri) => {
+            ListingTableUrl::parse(uri)
+        }
+        // listing table URLs refer to folders when they end in a slash
+        FilePath::Folder(uri) if uri.ends_with('/') => ListingTableUrl::parse(uri),
+        FilePath::Folder(uri) => ListingTableUrl::parse(format!("{}/", uri)),
+    }
+}
+
+#[async_trait]
+impl TableProvider for LocalFilesTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    async fn scan(
+        &self,
+        state: &SessionState,
+        projection: Option<&Vec<usize>>,
+        _filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let mut partitions: BTreeMap<u64, Vec<PartitionedFile>> = BTreeMap::new();
+        let mut object_store_url = None;
+        for item in &self.items {
+            let url = listing_url(&item.path)?;
+            match &object_store_url {
+                None => object_store_url = Some(url.object_store()),
+                Some(store) if *store != url.object_store() => {
+                    return not_impl_err!("local files read spans several object stores")
+                }
+                Some(_) => {}
+            }
+            let store = state.runtime_env().object_store(&url)?;
+            let objects: Vec<_> = url
+                .list_all_files(state, store.as_ref(), "")
+                .await?
+                .try_collect()
+                .await?;
+            if objects.is_empty() {
+                return plan_err!("no files found at {}", url);
+            }
+            let range = match item.length {
+                0 => None,
+                _ => Some(byte_range(item)?),
+            };
+            if let (Some(range), true) = (&range, objects.len() > 1) {
+                return plan_err!(
+                    "byte range {}..{} given for {} files at {}",
+                    range.start,
+                    range.end,
+                    objects.len(),
+                    url
+                );
+            }
+            for object in objects {
+                let mut file = PartitionedFile::from(object);
+                file.range = range.clone();
+                partitions
+                    .entry(item.partition_index)
+                    .or_default()
+                    .push(file);
+            }
+        }
+
+        let config = FileScanConfig::new(
+            object_store_url.expect("local files read lists files"),
+            self.schema.clone(),
+        )
+        .with_file_groups(partitions.into_values().collect())
+        .with_projection(projection.cloned())
+        .with_limit(limit);
+        self.format.create_physical_plan(state, config, None).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{byte_range, csv_format};
+    use crate::consumer::to_dataframe;
+    use datafusion::assert_batches_sorted_eq;
+    use datafusion::error::Result;
+    use datafusion::execution::context::SessionContext;
+    use datafusion::physical_plan::ExecutionPlanProperties;
+    use rustrait_core::plans::{
+        DelimitedTextOptions, Emit, FileFormat, FileOrFiles, FilePath, Plan, Read, ReadType, Rel,
+    };
+    use rustrait_core::types::{NamedStruct, Type};
+    use std::fs;
+
+    #[tokio::test]
+    async fn reads_partitioned_csv_files() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rustrait-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("a.csv"), "id|name\n1|'x|y'\n2|\n")?;
+        fs::write(dir.join("b.csv"), "id|name\n3|z\n")?;
+
+        let format = FileFormat::DelimitedText(DelimitedTextOptions {
+            field_delimiter: "|".to_string(),
+            max_line_size: 1024,
+            quote: "'".to_string(),
+            header_lines_to_skip: 1,
+            escape: String::new(),
+            value_treated_as_null: Some(String::new()),
+        });
+        let item = |file: &str, partition_index| FileOrFiles {
+            path: FilePath::File(format!("file://{}", dir.join(file).display())),
+            format: format.clone(),
+            partition_index,
+            start: 0,
+            length: 0,
+        };
+        let plan = Plan {
+            root: Box::new(Rel::Read(Read {
+                emit: Emit::Direct,
+                base_schema: NamedStruct {
+                    names: vec!["id".to_string(), "name".to_string()],
+                    types: vec![
+                        Type::I64 { nullable: false },
+                        Type::String { nullable: true },
+                    ],
+                },
+                read_type: ReadType::LocalFiles {
+                    items: vec![item("a.csv", 0), item("b.csv", 1)],
+                },
+            })),
+            names: vec![],
+            subtrees: vec![],
+        };
+
+        let ctx = SessionContext::new();
+        let df = to_dataframe(&ctx, &plan).await?;
+        assert_eq!(
+            df.clone()
+                .create_physical_plan()
+                .await?
+                .output_partitioning()
+                .partition_count(),
+            2
+        );
+        let batches = df.collect().await?;
+        assert_batches_sorted_eq!(
+            [
+                "+----+------+",
+                "| id | name |",
+                "+----+------+",
+                "| 1  | x|y  |",
+                "| 2  |      |",
+                "| 3  | z    |",
+                "+----+------+",
+            ],
+            &batches
+        );
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    fn csv_options(header_lines_to_skip: u64) -> DelimitedTextOptions {
+        DelimitedTextOptions {
+            field_delimiter: ",".to_string(),
+            max_line_size: 1024,
+            quote: "\"".to_string(),
+            header_lines_to_skip,
+            escape: String::new(),
+            value_treated_as_null: Some(String::new()),
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_byte_ranges() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rustrait-range-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("ids.csv"), "1\n2\n3\n4\n")?;
+
+        // the range holds the lines starting within it
+        let plan = Plan {
+            root: Box::new(Rel::Read(Read {
+                emit: Emit::Direct,
+                base_schema: NamedStruct {
+                    names: vec!["id".to_string()],
+                    types: vec![Type::I64 { nullable: false }],
+                },
+                read_type: ReadType::LocalFiles {
+                    items: vec![FileOrFiles {
+                        path: FilePath::File(format!("file://{}", dir.join("ids.csv").display())),
+                        format: FileFormat::DelimitedText(csv_options(0)),
+                        partition_index: 0,
+                        start: 2,
+                        length: 4,
+                    }],
+                },
+            })),
+            names: vec![],
+            subtrees: vec![],
+        };
+        let batches = to_dataframe(&SessionContext::new(), &plan)
+            .await?
+            .collect()
+            .await?;
+        assert_batches_sorted_eq!(
+            ["+----+", "| id |", "+----+", "| 2  |", "| 3  |", "+----+"],
+            &batches
+        );
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unsupported_csv_options() {
+        let options = DelimitedTextOptions {
+            value_treated_as_null: None,
+            ..csv_options(1)
+        };
+        let error = csv_format(&options).unwrap_err();
+        assert!(error.to_string().contains("value_treated_as_null"));
+
+        let error = csv_format(&csv_options(2)).unwrap_err();
+        assert!(error.to_string().contains("2 header lines"));
+    }
+
+    #[test]
+    fn rejects_out_of_range_byte_ranges() {
+        let item = |start, length| FileOrFiles {
+            path: FilePath::File("file:///data/ids.csv".to_string()),
+            format: FileFormat::DelimitedText(csv_options(0)),
+            partition_index: 0,
+            start,
+            length,
+        };
+        let range = byte_range(&item(2, 4)).unwrap();
+        assert_eq!((range.start, range.end), (2, 6));
+        assert!(byte_range(&item(u64::MAX - 1, 4)).is_err());
+        assert!(byte_range(&item(i64::MAX as u64, 1)).is_err());
+        assert!(byte_range(&item(1 << 63, 0)).is_err());
+    }
+}
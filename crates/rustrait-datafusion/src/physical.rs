@@ -0,0 +1,568 @@
+use crate::consumer::{
+    field_index, join_type, partition_count, resolve_tables, root_names, unique_name,
+    LogicalPlanConsumer,
+};
+use crate::functions::FunctionMappings;
+use async_trait::async_trait;
+use datafusion::arrow::compute::SortOptions;
+use datafusion::arrow::datatypes::{Field, Schema, SchemaRef};
+use datafusion::common::{not_impl_err, plan_err, DFSchema};
+use datafusion::datasource::{provider_as_source, TableProvider};
+use datafusion::error::Result;
+use datafusion::execution::context::{SessionContext, SessionState};
+use datafusion::logical_expr::{Expr, LogicalPlanBuilder, TableType};
+use datafusion::physical_expr::expressions::Column;
+use datafusion::physical_expr::{create_physical_expr, PhysicalExpr, PhysicalSortExpr};
+use datafusion::physical_plan::coalesce_partitions::CoalescePartitionsExec;
+use datafusion::physical_plan::filter::FilterExec;
+use datafusion::physical_plan::joins::utils::JoinOn;
+use datafusion::physical_plan::joins::{HashJoinExec, PartitionMode, SortMergeJoinExec};
+use datafusion::physical_plan::projection::ProjectionExec;
+use datafusion::physical_plan::repartition::RepartitionExec;
+use datafusion::physical_plan::sorts::sort::SortExec;
+use datafusion::physical_plan::{ExecutionPlan, ExecutionPlanProperties, Partitioning};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use rustrait_core::plans::expressions::Expression;
+use rustrait_core::plans::{Emit, Exchange, ExchangeKind, KeyedJoin, Plan, Rel};
+use std::any::Any;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+// Converts the plan into a DataFusion execution plan. Hash joins, merge joins and exchanges
+// become the corresponding operators, while the other relations are converted into a logical
+// plan that DataFusion plans as usual.
+pub async fn to_execution_plan(
+    ctx: &SessionContext,
+    plan: &Plan,
+) -> Result<Arc<dyn ExecutionPlan>> {
+    to_execution_plan_with_mappings(ctx, plan, &FunctionMappings::standard()).await
+}
+
+pub async fn to_execution_plan_with_mappings(
+    ctx: &SessionContext,
+    plan: &Plan,
+    mappings: &FunctionMappings,
+) -> Result<Arc<dyn ExecutionPlan>> {
+    let tables = resolve_tables(ctx, plan).await?;
+    let state = ctx.state();
+    let mut planner = PhysicalPlanner {
+        state: &state,
+        consumer: LogicalPlanConsumer::new(&state, mappings, tables, plan),
+        subtrees: &plan.subtrees,
+        substituted: 0,
+    };
+    let root = planner.plan_rel(&plan.root).await?;
+    let schema = root.schema();
+    let Some(names) = root_names(plan, schema.fields().len()) else {
+        return Ok(root);
+    };
+    let exprs = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (column(&schema, i), name.clone()))
+        .collect();
+    Ok(Arc::new(ProjectionExec::try_new(exprs, root)?))
+}
+
+fn is_physical(rel: &Rel) -> bool {
+    matches!(rel, Rel::HashJoin(_) | Rel::MergeJoin(_) | Rel::Exchange(_))
+}
+
+struct PhysicalPlanner<'a> {
+    state: &'a SessionState,
+    consumer: LogicalPlanConsumer<'a>,
+    subtrees: &'a [Rel],
+    // physical relations read by logical ones so far, to name the tables standing in for them
+    substituted: usize,
+}
+
+impl<'a> PhysicalPlanner<'a> {
+    fn plan_rel<'s>(&'s mut self, rel: &'a Rel) -> BoxFuture<'s, Result<Arc<dyn ExecutionPlan>>> {
+        async move {
+            let plan = match rel {
+                Rel::HashJoin(join) => self.plan_hash_join(join).await?,
+                Rel::MergeJoin(join) => self.plan_merge_join(join).await?,
+                Rel::Exchange(exchange) => self.plan_exchange(exchange).await?,
+                _ => return self.plan_logical(rel).await,
+            };
+            apply_emit(plan, rel.emit())
+        }
+        .boxed()
+    }
+
+    // The physical relations below the relation are planned first, and the logical plan reads
+    // their output as tables
+    async fn plan_logical(&mut self, rel: &'a Rel) -> Result<Arc<dyn ExecutionPlan>> {
+        let mut physical = vec![];
+        self.collect_physical(rel, &mut vec![], &mut physical);
+        for descendant in physical {
+            let plan = self.plan_rel(descendant).await?;
+            self.substituted += 1;
+            let table = ExecutionPlanTable::try_new(plan)?;
+            let scan = LogicalPlanBuilder::scan(
+                format!("physical_{}", self.substituted),
+                provider_as_source(Arc::new(table)),
+                None,
+            )?
+            .build()?;
+            self.consumer.substitute(descendant, scan);
+        }
+        let logical_plan = self.consumer.convert_rel(rel)?;
+        self.state.create_physical_plan(&logical_plan).await
+    }
+
+    // Gathers the nearest physical relations below the relation, following references into
+    // the subtrees of the plan once
+    fn collect_physical(
+        &self,
+        rel: &'a Rel,
+        references: &mut Vec<usize>,
+        physical: &mut Vec<&'a Rel>,
+    ) {
+        let inputs = match rel {
+            Rel::Reference(reference) if !references.contains(&reference.subtree) => {
+                references.push(reference.subtree);
+                self.subtrees.get(reference.subtree).into_iter().collect()
+            }
+            _ => rel.inputs(),
+        };
+        for input in inputs {
+            if !is_physical(input) {
+                self.collect_physical(input, references, physical);
+            } else if !physical.iter().any(|p| std::ptr::eq(*p, input)) {
+                physical.push(input);
+            }
+        }
+    }
+
+    async fn plan_hash_join(&mut self, join: &'a KeyedJoin) -> Result<Arc<dyn ExecutionPlan>> {
+        let left = self.plan_rel(&join.left).await?;
+        let right = self.plan_rel(&join.right).await?;
+        let on = join_on(join, &left, &right)?;
+        // DataFusion builds the hash table from the left input, collected into one partition
+        let plan = Arc::new(HashJoinExec::try_new(
+            left,
+            right,
+            on,
+            None,
+            &join_type(&join.join_type)?,
+            None,
+            PartitionMode::CollectLeft,
+            false,
+        )?);
+        self.post_join_filter(join, plan)
+    }
+
+    async fn plan_merge_join(&mut self, join: &'a KeyedJoin) -> Result<Arc<dyn ExecutionPlan>> {
+        let left = self.plan_rel(&join.left).await?;
+        let right = self.plan_rel(&join.right).await?;
+        let on = join_on(join, &left, &right)?;
+        let left = sorted_on(left, on.iter().map(|(l, _)| l.clone()).collect());
+        let right = sorted_on(right, on.iter().map(|(_, r)| r.clone()).collect());
+        let sort_options = vec![SortOptions::default(); on.len()];
+        let plan = Arc::new(SortMergeJoinExec::try_new(
+            left,
+            right,
+            on,
+            None,
+            join_type(&join.join_type)?,
+            sort_options,
+            false,
+        )?);
+        self.post_join_filter(join, plan)
+    }
+
+    fn post_join_filter(
+        &self,
+        join: &KeyedJoin,
+        plan: Arc<dyn ExecutionPlan>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        match &join.post_join_filter {
+            Some(filter) => {
+                let predicate = self.physical_expr(filter, &plan)?;
+                Ok(Arc::new(FilterExec::try_new(predicate, plan)?))
+            }
+            None => Ok(plan),
+        }
+    }
+
+    async fn plan_exchange(&mut self, exchange: &'a Exchange) -> Result<Arc<dyn ExecutionPlan>> {
+        let input = self.plan_rel(&exchange.input).await?;
+        let count = partition_count(exchange)?;
+        let partitioning = match &exchange.kind {
+            ExchangeKind::ScatterByFields(fields) => {
+                let schema = input.schema();
+                let exprs = fields
+                    .iter()
+                    .map(|f| field(&schema, f.field))
+                    .collect::<Result<_>>()?;
+                Partitioning::Hash(exprs, count)
+            }
+            // DataFusion distributes whole batches rather than single rows
+            ExchangeKind::RoundRobin { exact: false } => Partitioning::RoundRobinBatch(count),
+            kind => return not_impl_err!("exchange of kind {:?}", kind),
+        };
+        Ok(Arc::new(RepartitionExec::try_new(input, partitioning)?))
+    }
+
+    // Expressions are converted through their logical equivalent. Columns are referred to by
+    // position, so the input columns are renamed to be unique, as logical plans require.
+    fn physical_expr(
+        &self,
+        expression: &Expression,
+        input: &Arc<dyn ExecutionPlan>,
+    ) -> Result<Arc<dyn PhysicalExpr>> {
+        let schema = input.schema();
+        let fields: Vec<Field> = unique_names(&schema)
+            .into_iter()
+            .zip(schema.fields())
+            .map(|(name, f)| f.as_ref().clone().with_name(name))
+            .collect();
+        let schema = DFSchema::try_from(Schema::new(fields))?;
+        let expr = self.consumer.convert_expression(expression, &schema)?;
+        create_physical_expr(&expr, &schema, self.state.execution_props())
+    }
+}
+
+fn join_on(
+    join: &KeyedJoin,
+    left: &Arc<dyn ExecutionPlan>,
+    right: &Arc<dyn ExecutionPlan>,
+) -> Result<JoinOn> {
+    if join.left_keys.len() != join.right_keys.len() {
+        return plan_err!(
+            "join has {} left keys but {} right keys",
+            join.left_keys.len(),
+            join.right_keys.len()
+        );
+    }
+    let (left, right) = (left.schema(), right.schema());
+    join.left_keys
+        .iter()
+        .zip(&join.right_keys)
+        .map(|(l, r)| Ok((field(&left, l.field)?, field(&right, r.field)?)))
+        .collect()
+}
+
+// Merges the partitions of the input and sorts it on the keys, unless it already is
+fn sorted_on(
+    input: Arc<dyn ExecutionPlan>,
+    keys: Vec<Arc<dyn PhysicalExpr>>,
+) -> Arc<dyn ExecutionPlan> {
+    let input: Arc<dyn ExecutionPlan> = if input.output_partitioning().partition_count() > 1 {
+        Arc::new(CoalescePartitionsExec::new(input))
+    } else {
+        input
+    };
+    let ordering: Vec<PhysicalSortExpr> = keys
+        .into_iter()
+        .map(|expr| PhysicalSortExpr {
+            expr,
+            options: SortOptions::default(),
+        })
+        .collect();
+    if input.equivalence_properties().ordering_satisfy(&ordering) {
+        return input;
+    }
+    Arc::new(SortExec::new(ordering, input))
+}
+
+fn apply_emit(plan: Arc<dyn ExecutionPlan>, emit: &Emit) -> Result<Arc<dyn ExecutionPlan>> {
+    match emit {
+        Emit::Direct => Ok(plan),
+        Emit::Remap(mapping) => {
+            let schema = plan.schema();
+            let exprs = mapping
+                .iter()
+                .map(|i| {
+                    let expr = field(&schema, *i)?;
+                    Ok((expr, schema.field(*i as usize).name().clone()))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Arc::new(ProjectionExec::try_new(exprs, plan)?))
+        }
+    }
+}
+
+fn field(schema: &Schema, index: i32) -> Result<Arc<dyn PhysicalExpr>> {
+    Ok(column(schema, field_index(index, schema.fields().len())?))
+}
+
+fn column(schema: &Schema, index: usize) -> Arc<dyn PhysicalExpr> {
+    Arc::new(Column::new(schema.field(index).name(), index))
+}
+
+// Repeated names are made unique with a suffix, as in the logical consumer
+fn unique_names(schema: &Schema) -> Vec<String> {
+    let mut taken = HashSet::new();
+    schema
+        .fields()
+        .iter()
+        .map(|f| unique_name(f.name(), &mut taken))
+        .collect()
+}
+
+// The output of a physical relation, read by a logical one
+struct ExecutionPlanTable {
+    plan: Arc<dyn ExecutionPlan>,
+}
+
+impl ExecutionPlanTable {
+    fn try_new(plan: Arc<dyn ExecutionPlan>) -> Result<ExecutionPlanTable> {
+        let schema = plan.schema();
+        let names = unique_names(&schema);
+        if names
+            .iter()
+            .zip(schema.fields())
+            .all(|(n, f)| n == f.name())
+        {
+            return Ok(ExecutionPlanTable { plan });
+        }
+        let exprs = names
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| (column(&schema, i), name))
+            .collect();
+        Ok(ExecutionPlanTable {
+            plan: Arc::new(ProjectionExec::try_new(exprs, plan)?),
+        })
+    }
+}
+
+#[async_trait]
+impl TableProvider for ExecutionPlanTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.plan.schema()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Temporary
+    }
+
+    async fn scan(
+        &self,
+        _state: &SessionState,
+        projection: Option<&Vec<usize>>,
+        _filters: &[Expr],
+        _limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let Some(projection) = projection else {
+            return Ok(self.plan.clone());
+        };
+        let schema = self.plan.schema();
+        let exprs = projection
+            .iter()
+            .map(|i| (column(&schema, *i), schema.field(*i).name().clone()))
+            .collect();
+        Ok(Arc::new(ProjectionExec::try_new(exprs, self.plan.clone())?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::{BooleanArray, Int64Array, StringArray};
+    use datafusion::arrow::datatypes::DataType;
+    use datafusion::arrow::record_batch::RecordBatch;
+    use datafusion::assert_batches_sorted_eq;
+    use datafusion::physical_plan::{collect, displayable};
+    use rustrait_core::plans::expressions::field_reference::FieldReference;
+    use rustrait_core::plans::{Filter, JoinType, Read, ReadType};
+    use rustrait_core::types::{NamedStruct, Type};
+
+    fn read(table: &str, names: &[&str], types: Vec<Type>) -> Box<Rel> {
+        Box::new(Rel::Read(Read {
+            emit: Emit::Direct,
+            base_schema: NamedStruct {
+                names: names.iter().map(|n| n.to_string()).collect(),
+                types,
+            },
+            read_type: ReadType::NamedTable {
+                names: vec![table.to_string()],
+            },
+        }))
+    }
+
+    // foo(id, name) = (1, a), (2, b), (3, c) and bar(id, flag) = (1, true), (1, false), (3, true)
+    fn context() -> Result<SessionContext> {
+        let ctx = SessionContext::new();
+        let foo = Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]);
+        ctx.register_batch(
+            "foo",
+            RecordBatch::try_new(
+                Arc::new(foo),
+                vec![
+                    Arc::new(Int64Array::from(vec![1, 2, 3])),
+                    Arc::new(StringArray::from(vec!["a", "b", "c"])),
+                ],
+            )?,
+        )?;
+        let bar = Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("flag", DataType::Boolean, true),
+        ]);
+        ctx.register_batch(
+            "bar",
+            RecordBatch::try_new(
+                Arc::new(bar),
+                vec![
+                    Arc::new(Int64Array::from(vec![1, 1, 3])),
+                    Arc::new(BooleanArray::from(vec![true, false, true])),
+                ],
+            )?,
+        )?;
+        Ok(ctx)
+    }
+
+    fn foo() -> Box<Rel> {
+        read(
+            "foo",
+            &["id", "name"],
+            vec![
+                Type::I64 { nullable: false },
+                Type::String { nullable: true },
+            ],
+        )
+    }
+
+    fn bar() -> Box<Rel> {
+        read(
+            "bar",
+            &["id", "flag"],
+            vec![Type::I64 { nullable: false }, Type::Bool { nullable: true }],
+        )
+    }
+
+    // foo joined with bar on id, without the id of bar
+    fn keyed_join() -> KeyedJoin {
+        KeyedJoin {
+            emit: Emit::Remap(vec![0, 1, 3]),
+            left: foo(),
+            right: bar(),
+            left_keys: vec![FieldReference { field: 0 }],
+            right_keys: vec![FieldReference { field: 0 }],
+            post_join_filter: None,
+            join_type: JoinType::Inner,
+        }
+    }
+
+    fn plan(root: Rel, names: &[&str]) -> Plan {
+        Plan {
+            root: Box::new(root),
+            names: names.iter().map(|n| n.to_string()).collect(),
+            subtrees: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn plans_hash_join_below_filter() -> Result<()> {
+        let ctx = context()?;
+        let filter = Rel::Filter(Filter {
+            emit: Emit::Direct,
+            input: Box::new(Rel::HashJoin(keyed_join())),
+            condition: Expression::FieldReference(FieldReference { field: 2 }),
+        });
+        let plan = plan(filter, &["id", "name", "flag"]);
+
+        let execution_plan = to_execution_plan(&ctx, &plan).await?;
+        let display = displayable(execution_plan.as_ref())
+            .indent(true)
+            .to_string();
+        assert!(display.contains("HashJoinExec"), "{}", display);
+        let batches = collect(execution_plan, ctx.task_ctx()).await?;
+        assert_batches_sorted_eq!(
+            [
+                "+----+------+------+",
+                "| id | name | flag |",
+                "+----+------+------+",
+                "| 1  | a    | true |",
+                "| 3  | c    | true |",
+                "+----+------+------+",
+            ],
+            &batches
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn plans_merge_join() -> Result<()> {
+        let ctx = context()?;
+        let plan = plan(Rel::MergeJoin(keyed_join()), &["id", "name", "flag"]);
+
+        let execution_plan = to_execution_plan(&ctx, &plan).await?;
+        let display = displayable(execution_plan.as_ref())
+            .indent(true)
+            .to_string();
+        assert!(display.contains("SortMergeJoin"), "{}", display);
+        let batches = collect(execution_plan, ctx.task_ctx()).await?;
+        assert_batches_sorted_eq!(
+            [
+                "+----+------+-------+",
+                "| id | name | flag  |",
+                "+----+------+-------+",
+                "| 1  | a    | false |",
+                "| 1  | a    | true  |",
+                "| 3  | c    | true  |",
+                "+----+------+-------+",
+            ],
+            &batches
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn plans_exchange_as_repartition() -> Result<()> {
+        let ctx = context()?;
+        let exchange = |kind| {
+            Rel::Exchange(Exchange {
+                emit: Emit::Direct,
+                input: foo(),
+                partition_count: 3,
+                kind,
+            })
+        };
+        let scatter = exchange(ExchangeKind::ScatterByFields(vec![FieldReference {
+            field: 0,
+        }]));
+
+        let execution_plan = to_execution_plan(&ctx, &plan(scatter, &["id", "name"])).await?;
+        let display = displayable(execution_plan.as_ref())
+            .indent(true)
+            .to_string();
+        assert!(display.contains("RepartitionExec"), "{}", display);
+        assert_eq!(execution_plan.output_partitioning().partition_count(), 3);
+        let batches = collect(execution_plan, ctx.task_ctx()).await?;
+        assert_batches_sorted_eq!(
+            [
+                "+----+------+",
+                "| id | name |",
+                "+----+------+",
+                "| 1  | a    |",
+                "| 2  | b    |",
+                "| 3  | c    |",
+                "+----+------+",
+            ],
+            &batches
+        );
+
+        // DataFusion cannot distribute single rows
+        let exact = exchange(ExchangeKind::RoundRobin { exact: true });
+        let error = to_execution_plan(&ctx, &plan(exact, &[]))
+            .await
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("RoundRobin { exact: true }"),
+            "{}",
+            error
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,643 @@
+use crate::functions::{FunctionMappings, Target};
+use crate::types::{from_arrow_schema, from_arrow_type, from_scalar_value};
+use datafusion::common::{not_impl_err, plan_err, DFSchema, TableReference};
+use datafusion::error::Result;
+use datafusion::execution::context::SessionContext;
+use datafusion::logical_expr::expr::{AggregateFunctionDefinition, Sort as SortExpr};
+use datafusion::logical_expr::utils::{conjunction, COUNT_STAR_EXPANSION};
+use datafusion::logical_expr::{
+    binary_expr, Distinct, Expr, ExprSchemable, GroupingSet, JoinType, LogicalPlan, Operator,
+    TableScan,
+};
+use rustrait_core::extensions::overload::BoundArgument;
+use rustrait_core::extensions::registry::ExtensionRegistry;
+use rustrait_core::extensions::signature::{type_code, Signature};
+use rustrait_core::plans;
+use rustrait_core::plans::expressions::aggregate_function::{
+    AggregateFunctionInvocation, AggregationInvocation, AggregationPhase,
+};
+use rustrait_core::plans::expressions::cast::{Cast, FailureBehavior};
+use rustrait_core::plans::expressions::field_reference::FieldReference;
+use rustrait_core::plans::expressions::scalar_function::ScalarFunctionInvocation;
+use rustrait_core::plans::expressions::sort_field::{SortDirection, SortField};
+use rustrait_core::plans::expressions::{
+    Expression, Function, FunctionArgument, FunctionSignature, URI,
+};
+use rustrait_core::plans::{
+    Aggregate, Cross, Emit, Fetch, Filter, Join, Measure, Plan, Project, Read, ReadType, Rel, Set,
+    SetOp, Sort,
+};
+use rustrait_core::types::Type;
+
+// The standard extension files functions are looked up in, in order of preference. Functions
+// are declared with the `/<file name>` URI.
+const FUNCTION_FILES: [&str; 10] = [
+    "functions_arithmetic.yaml",
+    "functions_arithmetic_decimal.yaml",
+    "functions_boolean.yaml",
+    "functions_comparison.yaml",
+    "functions_string.yaml",
+    "functions_datetime.yaml",
+    "functions_rounding.yaml",
+    "functions_logarithmic.yaml",
+    "functions_aggregate_generic.yaml",
+    "functions_aggregate_approx.yaml",
+];
+
+// Plans the query and converts the optimized logical plan
+pub async fn from_sql(ctx: &SessionContext, sql: &str) -> Result<Plan> {
+    let logical_plan = ctx.sql(sql).await?.into_optimized_plan()?;
+    from_logical_plan(&logical_plan)
+}
+
+pub fn from_logical_plan(logical_plan: &LogicalPlan) -> Result<Plan> {
+    from_logical_plan_with_mappings(logical_plan, FunctionMappings::standard())
+}
+
+// Functions are named after the Substrait functions the mappings map onto them
+pub fn from_logical_plan_with_mappings(
+    logical_plan: &LogicalPlan,
+    mappings: FunctionMappings,
+) -> Result<Plan> {
+    let producer = LogicalPlanProducer {
+        registry: ExtensionRegistry::standard(),
+        mappings,
+    };
+    let root = producer.produce_rel(logical_plan)?;
+    let names = from_arrow_schema(logical_plan.schema().inner())?.names;
+    Ok(Plan {
+        root: Box::new(root),
+        names,
+        subtrees: vec![],
+    })
+}
+
+struct LogicalPlanProducer {
+    registry: ExtensionRegistry,
+    mappings: FunctionMappings,
+}
+
+impl LogicalPlanProducer {
+    fn produce_rel(&self, logical_plan: &LogicalPlan) -> Result<Rel> {
+        match logical_plan {
+            LogicalPlan::TableScan(scan) => self.produce_scan(scan),
+            LogicalPlan::Projection(projection) => {
+                let schema = projection.input.schema();
+                let expressions = projection
+                    .expr
+                    .iter()
+                    .map(|e| self.produce_expression(e, schema))
+                    .collect::<Result<Vec<_>>>()?;
+                // a project outputs its input columns before the computed ones
+                let input_count = schema.fields().len();
+                let mapping = (input_count..input_count + expressions.len())
+                    .map(|i| i as i32)
+                    .collect();
+                Ok(Rel::Project(Project {
+                    emit: Emit::Remap(mapping),
+                    input: Box::new(self.produce_rel(&projection.input)?),
+                    expressions,
+                }))
+            }
+            LogicalPlan::Filter(filter) => Ok(Rel::Filter(Filter {
+                emit: Emit::Direct,
+                input: Box::new(self.produce_rel(&filter.input)?),
+                condition: self.produce_expression(&filter.predicate, filter.input.schema())?,
+            })),
+            LogicalPlan::Limit(limit) => Ok(Rel::Fetch(Fetch {
+                emit: Emit::Direct,
+                input: Box::new(self.produce_rel(&limit.input)?),
+                offset: limit.skip as i64,
+                count: limit.fetch.map_or(-1, |fetch| fetch as i64),
+            })),
+            LogicalPlan::Sort(sort) => {
+                let rel = Rel::Sort(Sort {
+                    emit: Emit::Direct,
+                    input: Box::new(self.produce_rel(&sort.input)?),
+                    sorts: self.produce_sorts(&sort.expr, sort.input.schema())?,
+                });
+                Ok(with_fetch(rel, sort.fetch))
+            }
+            LogicalPlan::Aggregate(aggregate) => {
+                let schema = aggregate.input.schema();
+                let groupings = match aggregate.group_expr.as_slice() {
+                    [Expr::GroupingSet(grouping_set)] => grouping_sets(grouping_set),
+                    [] => vec![],
+                    keys => vec![keys.to_vec()],
+                };
+                let groupings = groupings
+                    .iter()
+                    .map(|keys| {
+                        keys.iter()
+                            .map(|key| self.produce_expression(key, schema))
+                            .collect::<Result<Vec<_>>>()
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let measures = aggregate
+                    .aggr_expr
+                    .iter()
+                    .map(|e| self.produce_measure(e, schema))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Rel::Aggregate(Aggregate {
+                    emit: Emit::Direct,
+                    input: Box::new(self.produce_rel(&aggregate.input)?),
+                    groupings,
+                    measures,
+                }))
+            }
+            // grouping by every column without measures removes duplicate rows
+            LogicalPlan::Distinct(Distinct::All(input)) => {
+                let keys = (0..input.schema().fields().len())
+                    .map(|i| Expression::FieldReference(FieldReference { field: i as i32 }))
+                    .collect();
+                Ok(Rel::Aggregate(Aggregate {
+                    emit: Emit::Direct,
+                    input: Box::new(self.produce_rel(input)?),
+                    groupings: vec![keys],
+                    measures: vec![],
+                }))
+            }
+            LogicalPlan::Join(join) => {
+                let join_type = match join.join_type {
+                    JoinType::Inner => plans::JoinType::Inner,
+                    JoinType::Left => plans::JoinType::Left,
+                    JoinType::Right => plans::JoinType::Right,
+                    JoinType::Full => plans::JoinType::Outer,
+                    JoinType::LeftSemi => plans::JoinType::LeftSemi,
+                    JoinType::LeftAnti => plans::JoinType::LeftAnti,
+                    JoinType::RightSemi => plans::JoinType::RightSemi,
+                    JoinType::RightAnti => plans::JoinType::RightAnti,
+                };
+                let operator = match join.null_equals_null {
+                    true => Operator::IsNotDistinctFrom,
+                    false => Operator::Eq,
+                };
+                let conditions = join
+                    .on
+                    .iter()
+                    .map(|(left, right)| binary_expr(left.clone(), operator, right.clone()))
+                    .chain(join.filter.clone());
+                // the join condition refers to the columns of both inputs
+                let schema = join.left.schema().join(join.right.schema())?;
+                let expression = match conjunction(conditions) {
+                    Some(condition) => Some(self.produce_expression(&condition, &schema)?),
+                    None => None,
+                };
+                Ok(Rel::Join(Join {
+                    emit: Emit::Direct,
+                    left: Box::new(self.produce_rel(&join.left)?),
+                    right: Box::new(self.produce_rel(&join.right)?),
+                    expression,
+                    post_join_filter: None,
+                    join_type,
+                }))
+            }
+            LogicalPlan::CrossJoin(cross) => Ok(Rel::Cross(Cross {
+                emit: Emit::Direct,
+                left: Box::new(self.produce_rel(&cross.left)?),
+                right: Box::new(self.produce_rel(&cross.right)?),
+            })),
+            LogicalPlan::Union(union) => Ok(Rel::Set(Set {
+                emit: Emit::Direct,
+                inputs: union
+                    .inputs
+                    .iter()
+                    .map(|input| self.produce_rel(input))
+                    .collect::<Result<Vec<_>>>()?,
+                op: SetOp::UnionAll,
+            })),
+            // aliases only change the qualifiers of column names
+            LogicalPlan::SubqueryAlias(alias) => self.produce_rel(&alias.input),
+            LogicalPlan::Values(values) => Ok(Rel::Read(Read {
+                emit: Emit::Direct,
+                base_schema: from_arrow_schema(values.schema.inner())?,
+                read_type: ReadType::VirtualTable {
+                    rows: values
+                        .values
+                        .iter()
+                        .map(|row| {
+                            row.iter()
+                                .map(|expr| match expr {
+                                    Expr::Literal(value) => from_scalar_value(value),
+                                    _ => not_impl_err!("VALUES entry {} is not a literal", expr),
+                                })
+                                .collect::<Result<Vec<_>>>()
+                        })
+                        .collect::<Result<Vec<_>>>()?,
+                },
+            })),
+            _ => not_impl_err!("unsupported logical plan node {}", logical_plan.display()),
+        }
+    }
+
+    fn produce_scan(&self, scan: &TableScan) -> Result<Rel> {
+        let names = match &scan.table_name {
+            TableReference::Bare { table } => vec![table.to_string()],
+            TableReference::Partial { schema, table } => {
+                vec![schema.to_string(), table.to_string()]
+            }
+            TableReference::Full {
+                catalog,
+                schema,
+                table,
+            } => vec![catalog.to_string(), schema.to_string(), table.to_string()],
+        };
+        let read = |schema: &DFSchema| -> Result<Rel> {
+            Ok(Rel::Read(Read {
+                emit: Emit::Direct,
+                base_schema: from_arrow_schema(schema.inner())?,
+                read_type: ReadType::NamedTable {
+                    names: names.clone(),
+                },
+            }))
+        };
+
+        let rel = match conjunction(scan.filters.clone()) {
+            None => read(scan.projected_schema.as_ref())?,
+            // pushed down filters may refer to columns that are not projected, so the whole
+            // table is read and the projection applied after filtering
+            Some(condition) => {
+                let schema = DFSchema::try_from_qualified_schema(
+                    scan.table_name.clone(),
+                    &scan.source.schema(),
+                )?;
+                let emit = match &scan.projection {
+                    Some(projection) => Emit::Remap(projection.iter().map(|i| *i as i32).collect()),
+                    None => Emit::Direct,
+                };
+                Rel::Filter(Filter {
+                    emit,
+                    input: Box::new(read(&schema)?),
+                    condition: self.produce_expression(&condition, &schema)?,
+                })
+            }
+        };
+        Ok(with_fetch(rel, scan.fetch))
+    }
+
+    fn produce_measure(&self, expr: &Expr, schema: &DFSchema) -> Result<Measure> {
+        let aggregate = match expr {
+            Expr::Alias(alias) => return self.produce_measure(&alias.expr, schema),
+            Expr::AggregateFunction(aggregate) => aggregate,
+            _ => return plan_err!("expected an aggregate function, got {}", expr),
+        };
+        let name = match &aggregate.func_def {
+            AggregateFunctionDefinition::BuiltIn(fun) => fun.to_string(),
+            AggregateFunctionDefinition::UDF(udf) => udf.name().to_string(),
+        }
+        .to_lowercase();
+        let name = self
+            .mappings
+            .substrait_name(&Target::Aggregate(name.clone()))
+            .unwrap_or(&name);
+
+        // count(*) is planned as the count of a constant
+        let arguments: Vec<&Expr> = match aggregate.args.as_slice() {
+            [Expr::Literal(value)] if name == "count" && *value == COUNT_STAR_EXPANSION => vec![],
+            args => args.iter().collect(),
+        };
+        let types = arguments
+            .iter()
+            .map(|arg| expression_type(arg, schema))
+            .collect::<Result<Vec<_>>>()?;
+        let args = arguments
+            .iter()
+            .map(|arg| {
+                Ok(FunctionArgument::Value(
+                    self.produce_expression(arg, schema)?,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let sorts = match &aggregate.order_by {
+            Some(order_by) => self.produce_sorts(order_by, schema)?,
+            None => vec![],
+        };
+        let filter = match &aggregate.filter {
+            Some(filter) => Some(self.produce_expression(filter, schema)?),
+            None => None,
+        };
+        Ok(Measure {
+            function: AggregateFunctionInvocation {
+                function: self.aggregate_function(name, &types)?,
+                args,
+                output_type: expression_type(expr, schema)?,
+                phase: AggregationPhase::InitialToResult,
+                invocation: match aggregate.distinct {
+                    true => AggregationInvocation::Distinct,
+                    false => AggregationInvocation::All,
+                },
+                sorts,
+            },
+            filter,
+        })
+    }
+
+    fn produce_sorts(&self, exprs: &[Expr], schema: &DFSchema) -> Result<Vec<SortField>> {
+        exprs
+            .iter()
+            .map(|expr| {
+                let Expr::Sort(SortExpr {
+                    expr,
+                    asc,
+                    nulls_first,
+                }) = expr
+                else {
+                    return plan_err!("expected a sort expression, got {}", expr);
+                };
+                let direction = match (asc, nulls_first) {
+                    (true, true) => SortDirection::AscNullsFirst,
+                    (true, false) => SortDirection::AscNullsLast,
+                    (false, true) => SortDirection::DescNullsFirst,
+                    (false, false) => SortDirection::DescNullsLast,
+                };
+                Ok(SortField {
+                    expression: self.produce_expression(expr, schema)?,
+                    direction,
+                })
+            })
+            .collect()
+    }
+
+    fn produce_expression(&self, expr: &Expr, schema: &DFSchema) -> Result<Expression> {
+        match expr {
+            Expr::Alias(alias) => self.produce_expression(&alias.expr, schema),
+            Expr::Column(column) => Ok(Expression::FieldReference(FieldReference {
+                field: schema.index_of_column(column)? as i32,
+            })),
+            Expr::Literal(value) => Ok(Expression::Literal(from_scalar_value(value)?)),
+            Expr::BinaryExpr(binary) => {
+                let Some(name) = self.mappings.substrait_name(&Target::Operator(binary.op)) else {
+                    return not_impl_err!("unsupported operator {}", binary.op);
+                };
+                self.produce_scalar_function(name, &[&binary.left, &binary.right], expr, schema)
+            }
+            Expr::Not(e) => self.produce_scalar_function("not", &[e], expr, schema),
+            Expr::Negative(e) => self.produce_scalar_function("negate", &[e], expr, schema),
+            Expr::IsNull(e) => self.produce_scalar_function("is_null", &[e], expr, schema),
+            Expr::IsNotNull(e) => self.produce_scalar_function("is_not_null", &[e], expr, schema),
+            Expr::ScalarFunction(function) => {
+                let args: Vec<&Expr> = function.args.iter().collect();
+                let name = function.func.name();
+                let name = self
+                    .mappings
+                    .substrait_name(&Target::ScalarUdf(name.to_string()))
+                    .unwrap_or(name);
+                self.produce_scalar_function(name, &args, expr, schema)
+            }
+            Expr::Cast(cast) => Ok(Expression::Cast(Cast {
+                r#type: expression_type(expr, schema)?,
+                input: Box::new(self.produce_expression(&cast.expr, schema)?),
+                failure_behavior: FailureBehavior::ThrowException,
+            })),
+            Expr::TryCast(cast) => Ok(Expression::Cast(Cast {
+                r#type: expression_type(expr, schema)?,
+                input: Box::new(self.produce_expression(&cast.expr, schema)?),
+                failure_behavior: FailureBehavior::ReturnNull,
+            })),
+            _ => not_impl_err!("unsupported expression {}", expr),
+        }
+    }
+
+    fn produce_scalar_function(
+        &self,
+        name: &str,
+        arguments: &[&Expr],
+        call: &Expr,
+        schema: &DFSchema,
+    ) -> Result<Expression> {
+        let types = arguments
+            .iter()
+            .map(|arg| expression_type(arg, schema))
+            .collect::<Result<Vec<_>>>()?;
+        let args = arguments
+            .iter()
+            .map(|arg| {
+                Ok(FunctionArgument::Value(
+                    self.produce_expression(arg, schema)?,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Expression::ScalarFunction(ScalarFunctionInvocation {
+            function: self.scalar_function(name, &types)?,
+            args,
+            output_type: expression_type(call, schema)?,
+        }))
+    }
+
+    // Finds the standard function variant accepting the argument types
+    fn scalar_function(&self, name: &str, types: &[Type]) -> Result<Function> {
+        let arguments: Vec<BoundArgument> = types.iter().map(BoundArgument::Value).collect();
+        for file in FUNCTION_FILES {
+            let uri = format!("/{}", file);
+            let Some(extensions) = self.registry.get(&uri) else {
+                continue;
+            };
+            for function in extensions
+                .scalar_functions
+                .iter()
+                .filter(|f| f.name == name)
+            {
+                if let Ok(resolution) = function.resolve_overload(&arguments) {
+                    let signature = Signature::from_arguments(name, &resolution.variant.arguments);
+                    return Ok(declared_function(signature, uri));
+                }
+            }
+        }
+        not_impl_err!(
+            "no standard scalar function {} accepts ({})",
+            name,
+            display_types(types)
+        )
+    }
+
+    fn aggregate_function(&self, name: &str, types: &[Type]) -> Result<Function> {
+        let codes: Vec<String> = types.iter().map(type_code).collect();
+        for file in FUNCTION_FILES {
+            let uri = format!("/{}", file);
+            let Some(extensions) = self.registry.get(&uri) else {
+                continue;
+            };
+            for function in extensions
+                .aggregate_functions
+                .iter()
+                .filter(|f| f.name == name)
+            {
+                for variant in &function.variants {
+                    let signature = Signature::from_arguments(name, &variant.arguments);
+                    let declared = signature.arguments.as_deref().unwrap_or_default();
+                    let matches = declared.len() == codes.len()
+                        && declared
+                            .iter()
+                            .zip(&codes)
+                            .all(|(declared, code)| declared == code || declared == "any");
+                    if matches {
+                        return Ok(declared_function(signature, uri));
+                    }
+                }
+            }
+        }
+        not_impl_err!(
+            "no standard aggregate function {} accepts ({})",
+            name,
+            display_types(types)
+        )
+    }
+}
+
+fn declared_function(signature: Signature, uri: String) -> Function {
+    Function {
+        signature: FunctionSignature(signature.encode()),
+        extension: URI(uri),
+        variant: None,
+    }
+}
+
+// Expands rollups and cubes into the grouping sets they stand for
+fn grouping_sets(grouping_set: &GroupingSet) -> Vec<Vec<Expr>> {
+    match grouping_set {
+        GroupingSet::GroupingSets(sets) => sets.clone(),
+        GroupingSet::Rollup(exprs) => (0..=exprs.len())
+            .rev()
+            .map(|n| exprs[..n].to_vec())
+            .collect(),
+        GroupingSet::Cube(exprs) => (0..1usize << exprs.len())
+            .rev()
+            .map(|mask| {
+                exprs
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| mask & (1 << (exprs.len() - 1 - i)) != 0)
+                    .map(|(_, e)| e.clone())
+                    .collect()
+            })
+            .collect(),
+    }
+}
+
+fn with_fetch(rel: Rel, fetch: Option<usize>) -> Rel {
+    match fetch {
+        Some(count) => Rel::Fetch(Fetch {
+            emit: Emit::Direct,
+            input: Box::new(rel),
+            offset: 0,
+            count: count as i64,
+        }),
+        None => rel,
+    }
+}
+
+fn expression_type(expr: &Expr, schema: &DFSchema) -> Result<Type> {
+    from_arrow_type(&expr.get_type(schema)?, expr.nullable(schema)?)
+}
+
+fn display_types(types: &[Type]) -> String {
+    types
+        .iter()
+        .map(|t| t.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consumer::to_dataframe;
+    use datafusion::arrow::array::Int32Array;
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::arrow::record_batch::RecordBatch;
+    use datafusion::assert_batches_sorted_eq;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn produces_simple_select_from_sql() -> Result<()> {
+        let ctx = SessionContext::new();
+        let schema = Schema::new(vec![Field::new("BAR", DataType::Int32, true)]);
+        let bar = Int32Array::from(vec![Some(1), None, Some(41)]);
+        ctx.register_batch(
+            "foo",
+            RecordBatch::try_new(Arc::new(schema), vec![Arc::new(bar)])?,
+        )?;
+
+        // the i32 column is cast to the type of the i64 literal
+        let plan = from_sql(&ctx, "SELECT \"BAR\" + 1 AS \"EXPR$0\" FROM FOO").await?;
+        assert_eq!(
+            plan.to_string(),
+            "Root[EXPR$0]\n  \
+             Project[expressions=[add(cast(BAR) -> i64?, 1:i64) -> i64?], emit=[1]]\n    \
+             Read[table=foo, columns=[BAR: i32?]]\n"
+        );
+        let Rel::Project(project) = plan.root.as_ref() else {
+            panic!("expected a project");
+        };
+        let Expression::ScalarFunction(add) = &project.expressions[0] else {
+            panic!("expected a function call");
+        };
+        assert_eq!(add.function.signature.0, "add:i64_i64");
+        assert_eq!(add.function.extension.0, "/functions_arithmetic.yaml");
+
+        // the produced plan runs against the same tables
+        let batches = to_dataframe(&ctx, &plan).await?.collect().await?;
+        assert_batches_sorted_eq!(
+            [
+                "+--------+",
+                "| EXPR$0 |",
+                "+--------+",
+                "|        |",
+                "| 2      |",
+                "| 42     |",
+                "+--------+",
+            ],
+            &batches
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn produces_virtual_tables_from_values() -> Result<()> {
+        let ctx = SessionContext::new();
+        let plan = from_sql(
+            &ctx,
+            "SELECT a FROM (VALUES (1, true), (2, false), (3, true)) AS t(a, b) WHERE b",
+        )
+        .await?;
+        assert!(plan.to_string().contains("virtual_table=3 rows"));
+
+        let batches = to_dataframe(&ctx, &plan).await?.collect().await?;
+        assert_batches_sorted_eq!(
+            ["+---+", "| a |", "+---+", "| 1 |", "| 3 |", "+---+"],
+            &batches
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn produces_literals_and_casts() -> Result<()> {
+        let ctx = SessionContext::new();
+        let schema = Schema::new(vec![Field::new("BAR", DataType::Int32, true)]);
+        let bar = Int32Array::from(vec![Some(1), None]);
+        ctx.register_batch(
+            "foo",
+            RecordBatch::try_new(Arc::new(schema), vec![Arc::new(bar)])?,
+        )?;
+
+        let plan = from_sql(
+            &ctx,
+            "SELECT 'a' AS s, 1.5 AS f, DATE '2024-01-01' AS d, CAST(NULL AS INT) AS n, \
+             TRY_CAST(\"BAR\" AS VARCHAR) AS t FROM FOO",
+        )
+        .await?;
+        let batches = to_dataframe(&ctx, &plan).await?.collect().await?;
+        assert_batches_sorted_eq!(
+            [
+                "+---+-----+------------+---+---+",
+                "| s | f   | d          | n | t |",
+                "+---+-----+------------+---+---+",
+                "| a | 1.5 | 2024-01-01 |   |   |",
+                "| a | 1.5 | 2024-01-01 |   | 1 |",
+                "+---+-----+------------+---+---+",
+            ],
+            &batches
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,423 @@
+use datafusion::arrow::datatypes::{DataType, Field, Fields, IntervalUnit, Schema, TimeUnit};
+use datafusion::common::{not_impl_err, plan_err};
+use datafusion::error::Result;
+use datafusion::scalar::ScalarValue;
+use rustrait_core::plans::expressions::literal::{
+    Binary, Bool, Date, Decimal, Literal, Str, Timestamp, FP32, FP64, I32, I64,
+};
+use rustrait_core::types::{NamedStruct, Type};
+use std::sync::Arc;
+
+pub fn from_arrow_type(data_type: &DataType, nullable: bool) -> Result<Type> {
+    let t = match data_type {
+        DataType::Boolean => Type::Bool { nullable },
+        DataType::Int8 => Type::I8 { nullable },
+        DataType::Int16 => Type::I16 { nullable },
+        DataType::Int32 => Type::I32 { nullable },
+        DataType::Int64 => Type::I64 { nullable },
+        DataType::Float32 => Type::FP32 { nullable },
+        DataType::Float64 => Type::FP64 { nullable },
+        DataType::Utf8 | DataType::LargeUtf8 => Type::String { nullable },
+        DataType::Binary | DataType::LargeBinary => Type::Binary { nullable },
+        DataType::FixedSizeBinary(length) => Type::FixedBinary {
+            nullable,
+            length: *length,
+        },
+        DataType::Date32 => Type::Date { nullable },
+        // Substrait times and timestamps have microsecond precision
+        DataType::Time64(TimeUnit::Microsecond) => Type::Time { nullable },
+        DataType::Timestamp(TimeUnit::Microsecond, None) => Type::Timestamp { nullable },
+        DataType::Timestamp(TimeUnit::Microsecond, Some(_)) => Type::TimestampTz { nullable },
+        DataType::Time32(unit) | DataType::Time64(unit) | DataType::Timestamp(unit, _) => {
+            return not_impl_err!(
+                "Arrow type {} has {:?} precision, Substrait times and timestamps have \
+                 microsecond precision",
+                data_type,
+                unit
+            )
+        }
+        DataType::Interval(IntervalUnit::YearMonth) => Type::IntervalYear { nullable },
+        DataType::Interval(IntervalUnit::DayTime) => Type::IntervalDay { nullable },
+        DataType::Interval(IntervalUnit::MonthDayNano) => {
+            return not_impl_err!(
+                "Arrow type {} corresponds to the Substrait interval_compound type, which is \
+                 not supported",
+                data_type
+            )
+        }
+        DataType::Decimal128(precision, scale) => Type::Decimal {
+            nullable,
+            precision: *precision as i32,
+            scale: *scale as i32,
+        },
+        DataType::List(field) | DataType::LargeList(field) => Type::List {
+            nullable,
+            r#type: Box::new(from_arrow_type(field.data_type(), field.is_nullable())?),
+        },
+        DataType::Struct(fields) => Type::Struct {
+            nullable,
+            types: fields
+                .iter()
+                .map(|f| from_arrow_type(f.data_type(), f.is_nullable()))
+                .collect::<Result<Vec<_>>>()?,
+        },
+        DataType::Map(entries, _) => match entries.data_type() {
+            DataType::Struct(fields) if fields.len() == 2 => Type::Map {
+                nullable,
+                key: Box::new(from_arrow_type(fields[0].data_type(), false)?),
+                value: Box::new(from_arrow_type(
+                    fields[1].data_type(),
+                    fields[1].is_nullable(),
+                )?),
+            },
+            _ => return not_impl_err!("map entries of type {}", entries.data_type()),
+        },
+        _ => return not_impl_err!("Arrow type {} has no Substrait equivalent", data_type),
+    };
+    Ok(t)
+}
+
+// Fields of structs are named c0, c1, ... as Substrait types do not name them
+pub fn to_arrow_type(t: &Type) -> Result<DataType> {
+    arrow_type(t, &mut |i| Ok(format!("c{}", i)))
+}
+
+// Converts the type, naming the fields of structs through `field_name`
+fn arrow_type(t: &Type, field_name: &mut dyn FnMut(usize) -> Result<String>) -> Result<DataType> {
+    let data_type = match t {
+        Type::Bool { .. } => DataType::Boolean,
+        Type::I8 { .. } => DataType::Int8,
+        Type::I16 { .. } => DataType::Int16,
+        Type::I32 { .. } => DataType::Int32,
+        Type::I64 { .. } => DataType::Int64,
+        Type::FP32 { .. } => DataType::Float32,
+        Type::FP64 { .. } => DataType::Float64,
+        // Arrow strings have no length, so the length of char types is not enforced
+        Type::String { .. } | Type::FixedChar { .. } | Type::VarChar { .. } => DataType::Utf8,
+        Type::Binary { .. } => DataType::Binary,
+        Type::FixedBinary { length, .. } => DataType::FixedSizeBinary(*length),
+        Type::UUID { .. } => DataType::FixedSizeBinary(16),
+        Type::Date { .. } => DataType::Date32,
+        Type::Time { .. } => DataType::Time64(TimeUnit::Microsecond),
+        Type::Timestamp { .. } => DataType::Timestamp(TimeUnit::Microsecond, None),
+        Type::TimestampTz { .. } => {
+            DataType::Timestamp(TimeUnit::Microsecond, Some("+00:00".into()))
+        }
+        Type::IntervalYear { .. } => DataType::Interval(IntervalUnit::YearMonth),
+        Type::IntervalDay { .. } => DataType::Interval(IntervalUnit::DayTime),
+        Type::Decimal {
+            precision, scale, ..
+        } => {
+            if !(1..=38).contains(precision) || *scale < 0 || scale > precision {
+                return plan_err!(
+                    "{} is not a valid decimal, Arrow decimals have a precision of 1 to 38 and \
+                     a scale of 0 to the precision",
+                    t
+                );
+            }
+            DataType::Decimal128(*precision as u8, *scale as i8)
+        }
+        Type::Struct { types, .. } => {
+            let mut fields = vec![];
+            for (i, t) in types.iter().enumerate() {
+                let name = field_name(i)?;
+                fields.push(Field::new(name, arrow_type(t, field_name)?, t.nullable()));
+            }
+            DataType::Struct(Fields::from(fields))
+        }
+        Type::List { r#type, .. } => DataType::List(Arc::new(Field::new(
+            "item",
+            arrow_type(r#type, field_name)?,
+            r#type.nullable(),
+        ))),
+        Type::Map { key, value, .. } => {
+            let entries = DataType::Struct(Fields::from(vec![
+                Field::new("key", arrow_type(key, field_name)?, false),
+                Field::new("value", arrow_type(value, field_name)?, value.nullable()),
+            ]));
+            DataType::Map(Arc::new(Field::new("entries", entries, false)), false)
+        }
+        Type::UserDefined {
+            extension, name, ..
+        } => {
+            return not_impl_err!(
+                "user-defined type {} of extension {} has no Arrow equivalent",
+                name,
+                extension.0
+            )
+        }
+    };
+    Ok(data_type)
+}
+
+pub fn from_arrow_schema(schema: &Schema) -> Result<NamedStruct> {
+    let mut names = vec![];
+    let mut types = vec![];
+    for field in schema.fields() {
+        collect_names(field, &mut names);
+        types.push(from_arrow_type(field.data_type(), field.is_nullable())?);
+    }
+    Ok(NamedStruct { names, types })
+}
+
+// Substrait lists the names of struct fields depth-first, after the name of their parent
+fn collect_names(field: &Field, names: &mut Vec<String>) {
+    names.push(field.name().clone());
+    collect_nested_names(field.data_type(), names);
+}
+
+fn collect_nested_names(data_type: &DataType, names: &mut Vec<String>) {
+    match data_type {
+        DataType::Struct(fields) => {
+            for field in fields {
+                collect_names(field, names);
+            }
+        }
+        DataType::List(field) | DataType::LargeList(field) => {
+            collect_nested_names(field.data_type(), names)
+        }
+        // the key and value fields themselves are not named
+        DataType::Map(entries, _) => {
+            if let DataType::Struct(fields) = entries.data_type() {
+                for field in fields {
+                    collect_nested_names(field.data_type(), names);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+// Builds nested fields from the depth-first names, the inverse of from_arrow_schema
+pub fn to_arrow_schema(named_struct: &NamedStruct) -> Result<Schema> {
+    let mut names = named_struct.names.iter();
+    let mut next_name = |_| match names.next() {
+        Some(name) => Ok(name.clone()),
+        None => plan_err!(
+            "schema has {} names, too few for its types {}",
+            named_struct.names.len(),
+            display_types(&named_struct.types)
+        ),
+    };
+    let mut fields = vec![];
+    for t in &named_struct.types {
+        let name = next_name(0)?;
+        fields.push(Field::new(
+            name,
+            arrow_type(t, &mut next_name)?,
+            t.nullable(),
+        ));
+    }
+    let used = fields.len() + nested_field_count(&fields);
+    if used != named_struct.names.len() {
+        return plan_err!(
+            "schema has {} names, but its types {} need {}",
+            named_struct.names.len(),
+            display_types(&named_struct.types),
+            used
+        );
+    }
+    Ok(Schema::new(fields))
+}
+
+fn nested_field_count(fields: &[Field]) -> usize {
+    fields
+        .iter()
+        .map(|f| nested_name_count(f.data_type()))
+        .sum()
+}
+
+fn nested_name_count(data_type: &DataType) -> usize {
+    let mut names = vec![];
+    collect_nested_names(data_type, &mut names);
+    names.len()
+}
+
+fn display_types(types: &[Type]) -> String {
+    let types: Vec<String> = types.iter().map(|t| t.to_string()).collect();
+    format!("[{}]", types.join(", "))
+}
+
+pub fn to_scalar_value(literal: &Literal) -> Result<ScalarValue> {
+    let value = match literal {
+        Literal::Null(t) => ScalarValue::try_from(&to_arrow_type(t)?)?,
+        Literal::Bool(l) => ScalarValue::Boolean(Some(l.value)),
+        Literal::I32(l) => ScalarValue::Int32(Some(l.value)),
+        Literal::I64(l) => ScalarValue::Int64(Some(l.value)),
+        Literal::FP32(l) => ScalarValue::Float32(Some(l.value)),
+        Literal::FP64(l) => ScalarValue::Float64(Some(l.value)),
+        Literal::String(l) => ScalarValue::Utf8(Some(l.value.clone())),
+        Literal::Binary(l) => ScalarValue::Binary(Some(l.value.clone())),
+        Literal::Date(l) => ScalarValue::Date32(Some(l.value)),
+        Literal::Timestamp(l) => ScalarValue::TimestampMicrosecond(Some(l.value), None),
+        Literal::Decimal(l) => {
+            // converting the type checks the precision and scale fit an Arrow decimal
+            let DataType::Decimal128(precision, scale) = to_arrow_type(&literal.output_type())?
+            else {
+                unreachable!("decimals are converted to Decimal128")
+            };
+            ScalarValue::Decimal128(Some(l.value), precision, scale)
+        }
+    };
+    Ok(value)
+}
+
+// Scalar values carry no nullability, so the literals are not nullable, except for nulls,
+// which are typed nulls of the nullable type
+pub fn from_scalar_value(value: &ScalarValue) -> Result<Literal> {
+    if value.is_null() {
+        return Ok(Literal::Null(from_arrow_type(&value.data_type(), true)?));
+    }
+    let nullable = false;
+    let literal = match value {
+        ScalarValue::Boolean(Some(value)) => Literal::Bool(Bool {
+            value: *value,
+            nullable,
+        }),
+        ScalarValue::Int32(Some(value)) => Literal::I32(I32 {
+            value: *value,
+            nullable,
+        }),
+        ScalarValue::Int64(Some(value)) => Literal::I64(I64 {
+            value: *value,
+            nullable,
+        }),
+        ScalarValue::Float32(Some(value)) => Literal::FP32(FP32 {
+            value: *value,
+            nullable,
+        }),
+        ScalarValue::Float64(Some(value)) => Literal::FP64(FP64 {
+            value: *value,
+            nullable,
+        }),
+        ScalarValue::Utf8(Some(value)) | ScalarValue::LargeUtf8(Some(value)) => {
+            Literal::String(Str {
+                value: value.clone(),
+                nullable,
+            })
+        }
+        ScalarValue::Binary(Some(value)) | ScalarValue::LargeBinary(Some(value)) => {
+            Literal::Binary(Binary {
+                value: value.clone(),
+                nullable,
+            })
+        }
+        ScalarValue::Date32(Some(value)) => Literal::Date(Date {
+            value: *value,
+            nullable,
+        }),
+        ScalarValue::TimestampMicrosecond(Some(value), None) => Literal::Timestamp(Timestamp {
+            value: *value,
+            nullable,
+        }),
+        ScalarValue::Decimal128(Some(value), precision, scale) => Literal::Decimal(Decimal {
+            value: *value,
+            precision: *precision as i32,
+            scale: *scale as i32,
+            nullable,
+        }),
+        _ => {
+            return not_impl_err!(
+                "literal {} of type {} has no Substrait literal equivalent",
+                value,
+                value.data_type()
+            )
+        }
+    };
+    Ok(literal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_nested_schemas() {
+        let named_struct = NamedStruct {
+            names: vec!["a", "b", "x", "y", "c"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            types: vec![
+                Type::I32 { nullable: false },
+                Type::Struct {
+                    nullable: true,
+                    types: vec![
+                        Type::String { nullable: true },
+                        Type::Decimal {
+                            nullable: false,
+                            precision: 10,
+                            scale: 2,
+                        },
+                    ],
+                },
+                Type::List {
+                    nullable: true,
+                    r#type: Box::new(Type::I64 { nullable: true }),
+                },
+            ],
+        };
+        let schema = to_arrow_schema(&named_struct).unwrap();
+        assert_eq!(
+            schema.field(1).data_type(),
+            &DataType::Struct(Fields::from(vec![
+                Field::new("x", DataType::Utf8, true),
+                Field::new("y", DataType::Decimal128(10, 2), false),
+            ]))
+        );
+        assert_eq!(from_arrow_schema(&schema).unwrap(), named_struct);
+
+        let too_few = NamedStruct {
+            names: vec!["a".to_string(), "b".to_string()],
+            types: named_struct.types.clone(),
+        };
+        assert!(to_arrow_schema(&too_few)
+            .unwrap_err()
+            .to_string()
+            .contains("too few"));
+    }
+
+    #[test]
+    fn reports_types_without_equivalents() {
+        let error =
+            from_arrow_type(&DataType::Interval(IntervalUnit::MonthDayNano), true).unwrap_err();
+        assert!(error.to_string().contains("interval_compound"));
+
+        let error = from_scalar_value(&ScalarValue::IntervalMonthDayNano(None)).unwrap_err();
+        assert!(error.to_string().contains("interval_compound"));
+
+        let error = from_scalar_value(&ScalarValue::UInt8(Some(1))).unwrap_err();
+        assert!(error.to_string().contains("no Substrait literal"));
+    }
+
+    #[test]
+    fn round_trips_literals() {
+        let values = [
+            ScalarValue::Boolean(Some(true)),
+            ScalarValue::Int32(Some(-7)),
+            ScalarValue::Int64(Some(1 << 40)),
+            ScalarValue::Float32(Some(0.5)),
+            ScalarValue::Float64(Some(-2.25)),
+            ScalarValue::Utf8(Some("text".to_string())),
+            ScalarValue::Binary(Some(vec![0, 255])),
+            ScalarValue::Date32(Some(19_000)),
+            ScalarValue::TimestampMicrosecond(Some(1_641_600_000_000_000), None),
+            ScalarValue::Decimal128(Some(-1_234_567), 38, 4),
+            ScalarValue::Boolean(None),
+            ScalarValue::Int32(None),
+            ScalarValue::Int64(None),
+            ScalarValue::Float32(None),
+            ScalarValue::Float64(None),
+            ScalarValue::Utf8(None),
+            ScalarValue::Binary(None),
+            ScalarValue::Date32(None),
+            ScalarValue::TimestampMicrosecond(None, None),
+            ScalarValue::Decimal128(None, 10, 2),
+        ];
+        for value in values {
+            let literal = from_scalar_value(&value).unwrap();
+            assert_eq!(literal.output_type().nullable(), value.is_null());
+            assert_eq!(to_scalar_value(&literal).unwrap(), value);
+        }
+    }
+}
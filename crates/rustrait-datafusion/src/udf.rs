@@ -0,0 +1,222 @@
+use crate::functions::{FunctionMapping, FunctionMappings};
+use crate::types::{from_arrow_type, to_arrow_type};
+use datafusion::arrow::datatypes::DataType;
+use datafusion::common::{not_impl_err, plan_err};
+use datafusion::error::Result;
+use datafusion::execution::context::SessionContext;
+use datafusion::logical_expr::{
+    ColumnarValue, ScalarUDF, ScalarUDFImpl, Signature, TypeSignature, Volatility,
+};
+use rustrait_core::extensions::arguments::Argument;
+use rustrait_core::extensions::overload::{instantiate, Bindings, BoundArgument};
+use rustrait_core::extensions::scalar_function::{ScalarFunction, ScalarFunctionVariant};
+use rustrait_core::extensions::Extensions;
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+pub type Implementation = Arc<dyn Fn(&[ColumnarValue]) -> Result<ColumnarValue> + Send + Sync>;
+
+// A scalar function declared in an extension file and implemented in Rust. The argument and
+// return types come from the variants of the declaration.
+pub struct SubstraitScalarUdf {
+    function: ScalarFunction,
+    signature: Signature,
+    implementation: Implementation,
+}
+
+impl SubstraitScalarUdf {
+    pub fn new(
+        extensions: &Extensions,
+        name: &str,
+        implementation: Implementation,
+    ) -> Result<SubstraitScalarUdf> {
+        let Some(function) = extensions.scalar_functions.iter().find(|f| f.name == name) else {
+            return plan_err!("extension declares no scalar function {}", name);
+        };
+        let signature = signature(function)?;
+        Ok(SubstraitScalarUdf {
+            function: function.clone(),
+            signature,
+            implementation,
+        })
+    }
+}
+
+impl fmt::Debug for SubstraitScalarUdf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SubstraitScalarUdf")
+            .field("name", &self.function.name)
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+impl ScalarUDFImpl for SubstraitScalarUdf {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.function.name
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    // DataFusion does not track the nullability of arguments, so they are resolved as
+    // non-nullable
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
+        let types = arg_types
+            .iter()
+            .map(|t| from_arrow_type(t, false))
+            .collect::<Result<Vec<_>>>()?;
+        let arguments: Vec<BoundArgument> = types.iter().map(BoundArgument::Value).collect();
+        match self.function.resolve_overload(&arguments) {
+            Ok(resolution) => to_arrow_type(&resolution.output_type),
+            Err(error) => plan_err!("{}", error),
+        }
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        (self.implementation)(args)
+    }
+}
+
+// Registers the function with the context, and maps the function of the extension to it so
+// that plans referring to it can be consumed
+pub fn register_scalar_function(
+    ctx: &SessionContext,
+    mappings: &mut FunctionMappings,
+    uri: &str,
+    extensions: &Extensions,
+    name: &str,
+    implementation: Implementation,
+) -> Result<()> {
+    let udf = SubstraitScalarUdf::new(extensions, name, implementation)?;
+    ctx.register_udf(ScalarUDF::new_from_impl(udf));
+    mappings.insert(uri, name, FunctionMapping::scalar_udf(name));
+    Ok(())
+}
+
+// Variants whose arguments are all concrete types are accepted exactly. Those with type
+// variables, parameterized types or variadic arguments accept any arguments and are checked
+// when the return type is resolved.
+fn signature(function: &ScalarFunction) -> Result<Signature> {
+    let mut signatures = vec![];
+    let mut polymorphic = false;
+    for variant in &function.variants {
+        match exact_types(&function.name, variant)? {
+            Some(types) => signatures.push(TypeSignature::Exact(types)),
+            None => polymorphic = true,
+        }
+    }
+    if polymorphic {
+        signatures.push(TypeSignature::VariadicAny);
+    }
+    Ok(Signature::one_of(
+        signatures,
+        volatility(&function.variants),
+    ))
+}
+
+fn exact_types(name: &str, variant: &ScalarFunctionVariant) -> Result<Option<Vec<DataType>>> {
+    if variant.variadic.is_some() {
+        return Ok(None);
+    }
+    let mut types = vec![];
+    for argument in &variant.arguments {
+        match argument {
+            Argument::Value { value, .. } => match instantiate(value, &Bindings::default()) {
+                Ok(t) => types.push(to_arrow_type(&t)?),
+                Err(_) => return Ok(None),
+            },
+            // DataFusion functions only take values
+            Argument::Enum { .. } | Argument::Type { .. } => {
+                return not_impl_err!(
+                    "{} takes enum or type arguments, which scalar UDFs cannot receive",
+                    name
+                )
+            }
+        }
+    }
+    Ok(Some(types))
+}
+
+// The least volatile category that holds for every variant
+fn volatility(variants: &[ScalarFunctionVariant]) -> Volatility {
+    if variants.iter().any(|v| !v.deterministic) {
+        Volatility::Volatile
+    } else if variants.iter().any(|v| v.session_dependent) {
+        Volatility::Stable
+    } else {
+        Volatility::Immutable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::{ArrayRef, Int32Array};
+    use datafusion::arrow::compute::cast;
+    use datafusion::arrow::compute::kernels::numeric::add;
+    use datafusion::arrow::datatypes::{Field, Schema};
+    use datafusion::arrow::record_batch::RecordBatch;
+    use datafusion::assert_batches_sorted_eq;
+    use rustrait_core::extensions::registry::ExtensionRegistry;
+
+    const FUNCTIONS: &str = r#"
+scalar_functions:
+  - name: twice
+    impls:
+      - args:
+          - name: x
+            value: i32
+        return: i64
+      - args:
+          - name: x
+            value: decimal<P1,S1>
+        return: decimal<P1,S1>
+"#;
+
+    #[tokio::test]
+    async fn registers_declared_functions() -> Result<()> {
+        let mut registry = ExtensionRegistry::new();
+        registry
+            .register_yaml("/functions_custom.yaml", FUNCTIONS)
+            .unwrap();
+        let extensions = registry.get("/functions_custom.yaml").unwrap();
+
+        let ctx = SessionContext::new();
+        let mut mappings = FunctionMappings::standard();
+        register_scalar_function(
+            &ctx,
+            &mut mappings,
+            "/functions_custom.yaml",
+            extensions,
+            "twice",
+            Arc::new(|args: &[ColumnarValue]| {
+                let values = ColumnarValue::values_to_arrays(args)?;
+                let values = cast(&values[0], &DataType::Int64)?;
+                Ok(ColumnarValue::Array(add(&values, &values)?))
+            }),
+        )?;
+        assert!(mappings.get("/functions_custom.yaml", "twice").is_ok());
+
+        let schema = Arc::new(Schema::new(vec![Field::new("x", DataType::Int32, true)]));
+        let values: ArrayRef = Arc::new(Int32Array::from(vec![Some(1), None, Some(21)]));
+        ctx.register_batch("t", RecordBatch::try_new(schema, vec![values])?)?;
+
+        let df = ctx.sql("SELECT twice(x) AS d FROM t").await?;
+        assert_eq!(df.schema().field(0).data_type(), &DataType::Int64);
+        let batches = df.collect().await?;
+        assert_batches_sorted_eq!(
+            ["+----+", "| d  |", "+----+", "|    |", "| 2  |", "| 42 |", "+----+"],
+            &batches
+        );
+
+        assert!(ctx.sql("SELECT twice('a')").await.is_err());
+        Ok(())
+    }
+}